@@ -0,0 +1,275 @@
+//! Tiny reference CORS proxy for Codetective, meant to run locally.
+//!
+//! Forwards requests for `http://localhost:8787/<target URL>` (or
+//! `http://localhost:8787/?url=<percent-encoded target URL>`) to the target,
+//! and adds CORS headers to the responses, so that the browser app can reach
+//! API providers, LLM gateways, and code hosts that do not allow cross-origin
+//! requests.
+//!
+//! Only pages of the allowed origins may use the proxy; requests from any
+//! other page, or with no `Origin` header, are rejected, so that websites the
+//! user visits cannot reach hosts behind the user's firewall through it.
+//!
+//! Usage: `cargo run -p codetective-proxy -- [LISTEN_ADDR]`, with
+//! `PROXY_ALLOWED_ORIGINS` optionally set to comma-separated page origins
+//! allowed to use the proxy, the hosted app's by default (e.g. add
+//! `http://127.0.0.1:8080` for `trunk serve`).
+
+use std::convert::Infallible;
+use std::env;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use http_body_util::{BodyExt, Full};
+
+use hyper::body::Incoming;
+use hyper::header::{
+    HeaderMap, HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, CONTENT_LENGTH, CONTENT_TYPE, ORIGIN, VARY,
+};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+
+use tokio::net::TcpListener;
+
+use url::{form_urlencoded, Url};
+
+/// Default address to listen on; loopback only.
+const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8787";
+
+/// Origin of the hosted web app, the only page allowed to use the proxy by
+/// default.
+const DEFAULT_ALLOWED_ORIGIN: &str = "https://josehu.com";
+
+/// Request headers that are not forwarded: hop-by-hop ones, and ones that
+/// describe the browser page rather than the request.
+const SKIPPED_REQUEST_HEADERS: [&str; 8] = [
+    "host",
+    "origin",
+    "referer",
+    "connection",
+    "keep-alive",
+    "upgrade",
+    "transfer-encoding",
+    "content-length",
+];
+
+/// Response headers that are not passed back: hop-by-hop ones, and ones
+/// recomputed by the proxy.
+const SKIPPED_RESPONSE_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "upgrade",
+    "transfer-encoding",
+    "content-length",
+];
+
+/// Methods allowed in preflight responses.
+const ALLOWED_METHODS: &str = "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS";
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+#[tokio::main]
+async fn main() -> Result<(), BoxError> {
+    let addr: SocketAddr = env::args()
+        .nth(1)
+        .as_deref()
+        .unwrap_or(DEFAULT_LISTEN_ADDR)
+        .parse()?;
+    let allowed_origins = Arc::new(allowed_origins());
+    let listener = TcpListener::bind(addr).await?;
+    println!("Codetective CORS proxy listening on http://{}/", addr);
+    println!("Allowed page origins: {}", allowed_origins.join(", "));
+    if !addr.ip().is_loopback() {
+        eprintln!("Warning: listening beyond loopback makes this an open proxy");
+    }
+
+    let client = reqwest::Client::new();
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let client = client.clone();
+        let allowed_origins = allowed_origins.clone();
+        tokio::spawn(async move {
+            let service =
+                service_fn(move |req| handle(client.clone(), allowed_origins.clone(), req));
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                eprintln!("Connection error: {}", err);
+            }
+        });
+    }
+}
+
+/// Returns the page origins allowed to use the proxy, from
+/// `PROXY_ALLOWED_ORIGINS` if set.
+fn allowed_origins() -> Vec<String> {
+    let origins: Vec<String> = env::var("PROXY_ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|origin| !origin.is_empty())
+        .map(|origin| origin.to_string())
+        .collect();
+    if origins.is_empty() {
+        vec![DEFAULT_ALLOWED_ORIGIN.to_string()]
+    } else {
+        origins
+    }
+}
+
+/// Extracts the target URL from either of the two supported request forms.
+fn target_of(req: &Request<Incoming>) -> Option<Url> {
+    let target = if req.uri().path() == "/" {
+        form_urlencoded::parse(req.uri().query()?.as_bytes())
+            .find(|(key, _)| key == "url")
+            .map(|(_, url)| url.into_owned())?
+    } else {
+        // keeps the target's own query string
+        req.uri()
+            .path_and_query()?
+            .as_str()
+            .trim_start_matches('/')
+            .to_string()
+    };
+
+    Url::parse(&target)
+        .ok()
+        .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+}
+
+/// Answers one request from an allowed origin, adding CORS headers to
+/// whatever the outcome is. Requests from other origins, preflights included,
+/// are rejected without being forwarded.
+async fn handle(
+    client: reqwest::Client,
+    allowed_origins: Arc<Vec<String>>,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let origin = req.headers().get(ORIGIN).cloned().filter(|origin| {
+        allowed_origins
+            .iter()
+            .any(|allowed| origin.as_bytes() == allowed.as_bytes())
+    });
+    let Some(origin) = origin else {
+        let mut response = text_response(
+            StatusCode::FORBIDDEN,
+            "requesting page origin is not allowed to use this proxy",
+        );
+        response
+            .headers_mut()
+            .insert(VARY, HeaderValue::from_static("Origin"));
+        return Ok(response);
+    };
+    let requested_headers = req.headers().get(ACCESS_CONTROL_REQUEST_HEADERS).cloned();
+
+    let mut response = if req.method() == Method::OPTIONS {
+        text_response(StatusCode::NO_CONTENT, "")
+    } else {
+        match target_of(&req) {
+            None => text_response(
+                StatusCode::BAD_REQUEST,
+                "expected /<target URL> or /?url=<percent-encoded target URL>",
+            ),
+            Some(target) => match forward(&client, req, target).await {
+                Ok(response) => response,
+                Err(err) => {
+                    eprintln!("Forwarding failed: {}", err);
+                    text_response(StatusCode::BAD_GATEWAY, &err.to_string())
+                }
+            },
+        }
+    };
+
+    add_cors_headers(response.headers_mut(), origin, requested_headers);
+    Ok(response)
+}
+
+/// Forwards the request to the target URL and relays the response back.
+async fn forward(
+    client: &reqwest::Client,
+    req: Request<Incoming>,
+    target: Url,
+) -> Result<Response<Full<Bytes>>, BoxError> {
+    let (parts, body) = req.into_parts();
+    let body = body.collect().await?.to_bytes();
+    let mut headers = parts.headers;
+    for name in SKIPPED_REQUEST_HEADERS {
+        headers.remove(name);
+    }
+
+    println!("{} {}", parts.method, redacted(&target));
+    let is_head = parts.method == Method::HEAD;
+    // errors would otherwise carry the full target URL back to the log
+    let upstream = client
+        .request(parts.method, target)
+        .headers(headers)
+        .body(body)
+        .send()
+        .await
+        .map_err(reqwest::Error::without_url)?;
+
+    let mut response = Response::builder().status(upstream.status());
+    let headers = response.headers_mut().expect("fresh response builder");
+    for (name, value) in upstream.headers() {
+        // upstream CORS headers would conflict with the proxy's own; HEAD
+        // responses keep the length of the body they do not carry
+        let skipped = SKIPPED_RESPONSE_HEADERS.contains(&name.as_str())
+            && !(is_head && name == CONTENT_LENGTH);
+        if !skipped && !name.as_str().starts_with("access-control-") {
+            headers.append(name.clone(), value.clone());
+        }
+    }
+    let body = upstream
+        .bytes()
+        .await
+        .map_err(reqwest::Error::without_url)?;
+    Ok(response.body(Full::new(body))?)
+}
+
+/// Returns the target URL for logging, with only scheme, host, port and path.
+/// Queries and userinfo are dropped, as they may carry API keys.
+fn redacted(target: &Url) -> String {
+    let mut target = target.clone();
+    target.set_query(None);
+    target.set_fragment(None);
+    let _ = target.set_username("");
+    let _ = target.set_password(None);
+    target.to_string()
+}
+
+fn text_response(status: StatusCode, text: &str) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(text.to_string())));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+    response
+}
+
+/// Allows the given, already checked origin. Requested headers are echoed
+/// back, as the wildcard does not cover `Authorization`.
+fn add_cors_headers(
+    headers: &mut HeaderMap,
+    origin: HeaderValue,
+    requested_headers: Option<HeaderValue>,
+) {
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.insert(VARY, HeaderValue::from_static("Origin"));
+    headers.insert(
+        ACCESS_CONTROL_ALLOW_METHODS,
+        HeaderValue::from_static(ALLOWED_METHODS),
+    );
+    headers.insert(
+        ACCESS_CONTROL_ALLOW_HEADERS,
+        requested_headers.unwrap_or_else(|| HeaderValue::from_static("*")),
+    );
+    headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static("*"));
+    headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static("600"));
+}
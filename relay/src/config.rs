@@ -0,0 +1,138 @@
+//! Relay settings, read from environment variables at startup.
+
+use std::collections::HashSet;
+use std::env;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::upstream::Upstream;
+
+/// Default address to listen on; loopback only, put behind a reverse proxy
+/// with TLS for a shared instance.
+const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8788";
+
+/// Default number of detection calls each user may make per quota window.
+const DEFAULT_QUOTA: u32 = 200;
+
+/// Default quota window length, a day.
+const DEFAULT_QUOTA_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// Default max size of the code in one detection call.
+const DEFAULT_MAX_CODE_BYTES: usize = 64 * 1024;
+
+/// Default max output tokens of one detection call; the web app adapters use
+/// the same.
+const DEFAULT_MAX_OUTPUT_TOKENS: u32 = 500;
+
+/// Providers the relay can hold keys for, all reached through their
+/// OpenAI-compatible chat completion APIs: (name, env var prefix, API base
+/// URL, default model).
+const PROVIDERS: [(&str, &str, &str, &str); 3] = [
+    (
+        "Gemini",
+        "RELAY_GEMINI",
+        "https://generativelanguage.googleapis.com/v1beta/openai",
+        "gemini-2.0-flash",
+    ),
+    (
+        "Groq Cloud",
+        "RELAY_GROQ",
+        "https://api.groq.com/openai/v1",
+        "llama3-70b-8192",
+    ),
+    (
+        "OpenRouter",
+        "RELAY_OPENROUTER",
+        "https://openrouter.ai/api/v1",
+        "mistralai/mistral-large",
+    ),
+];
+
+/// Relay server settings.
+pub(crate) struct Config {
+    pub(crate) listen_addr: SocketAddr,
+    pub(crate) upstreams: Vec<Upstream>,
+    /// If set, only requests bearing one of these tokens are served, and
+    /// quotas are per token; otherwise quotas are per client IP.
+    pub(crate) access_tokens: Option<HashSet<String>>,
+    /// Whether to take the client IP from `X-Forwarded-For`, when running
+    /// behind a reverse proxy.
+    pub(crate) trust_forwarded: bool,
+    pub(crate) quota: u32,
+    pub(crate) quota_window: Duration,
+    pub(crate) max_code_bytes: usize,
+    pub(crate) max_output_tokens: u32,
+    /// If set, only these page origins get CORS headers; otherwise any does.
+    pub(crate) allowed_origins: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Reads the settings from environment variables. Returns an error message
+    /// if any is invalid or if no provider key is given.
+    pub(crate) fn from_env() -> Result<Self, String> {
+        let upstreams: Vec<Upstream> = PROVIDERS
+            .iter()
+            .filter_map(|&(name, prefix, base_url, default_model)| {
+                let keys = list_var(&format!("{}_KEYS", prefix))?;
+                let model =
+                    var(&format!("{}_MODEL", prefix)).unwrap_or_else(|| default_model.to_string());
+                Some(Upstream::new(name, base_url.to_string(), model, keys))
+            })
+            .collect();
+        if upstreams.is_empty() {
+            return Err(format!(
+                "no provider keys given; set at least one of {}",
+                PROVIDERS
+                    .iter()
+                    .map(|(_, prefix, _, _)| format!("{}_KEYS", prefix))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        Ok(Config {
+            listen_addr: parse_var("RELAY_LISTEN_ADDR")?
+                .unwrap_or_else(|| DEFAULT_LISTEN_ADDR.parse().unwrap()),
+            upstreams,
+            access_tokens: list_var("RELAY_ACCESS_TOKENS")
+                .map(|tokens| tokens.into_iter().collect()),
+            trust_forwarded: parse_var("RELAY_TRUST_FORWARDED")?.unwrap_or(false),
+            quota: parse_var("RELAY_QUOTA")?.unwrap_or(DEFAULT_QUOTA),
+            quota_window: Duration::from_secs(
+                parse_var("RELAY_QUOTA_WINDOW_SECS")?.unwrap_or(DEFAULT_QUOTA_WINDOW_SECS),
+            ),
+            max_code_bytes: parse_var("RELAY_MAX_CODE_BYTES")?.unwrap_or(DEFAULT_MAX_CODE_BYTES),
+            max_output_tokens: parse_var("RELAY_MAX_OUTPUT_TOKENS")?
+                .unwrap_or(DEFAULT_MAX_OUTPUT_TOKENS),
+            allowed_origins: list_var("RELAY_ALLOWED_ORIGINS"),
+        })
+    }
+}
+
+/// Returns the non-empty value of an environment variable, if set.
+fn var(name: &str) -> Option<String> {
+    env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Splits a comma- or whitespace-separated environment variable into items.
+fn list_var(name: &str) -> Option<Vec<String>> {
+    let items: Vec<String> = var(name)?
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_string())
+        .collect();
+    (!items.is_empty()).then_some(items)
+}
+
+fn parse_var<T: std::str::FromStr>(name: &str) -> Result<Option<T>, String> {
+    var(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("invalid value '{}' of {}", value, name))
+        })
+        .transpose()
+}
@@ -0,0 +1,386 @@
+//! Self-hostable relay server for Codetective, for offering detection to a
+//! group of users, e.g. students of a class, without handing out provider
+//! keys.
+//!
+//! Holds the provider keys server-side and forwards detection calls of the web
+//! app's "Relay" provider to them, enforcing a per-user quota of calls: per
+//! access token if tokens are configured, per client IP otherwise. Calls carry
+//! only the code; the relay builds the detection prompt itself, so that it
+//! cannot be used as a general-purpose model endpoint.
+//!
+//! Usage: `cargo run -p codetective-relay --release`, configured through
+//! environment variables:
+//!
+//! - `RELAY_GEMINI_KEYS`, `RELAY_GROQ_KEYS`, `RELAY_OPENROUTER_KEYS`: provider
+//!   keys, comma-separated; at least one provider is required
+//! - `RELAY_GEMINI_MODEL`, `RELAY_GROQ_MODEL`, `RELAY_OPENROUTER_MODEL`: model
+//!   overrides
+//! - `RELAY_LISTEN_ADDR`: address to listen on, `127.0.0.1:8788` by default
+//! - `RELAY_ACCESS_TOKENS`: comma-separated tokens to hand out to users
+//! - `RELAY_TRUST_FORWARDED`: `true` to take client IPs from the last hop of
+//!   `X-Forwarded-For`, as appended by the reverse proxy in front
+//! - `RELAY_QUOTA`, `RELAY_QUOTA_WINDOW_SECS`: calls allowed per user per
+//!   window, 200 per day by default
+//! - `RELAY_MAX_CODE_BYTES`, `RELAY_MAX_OUTPUT_TOKENS`: per-call caps
+//! - `RELAY_ALLOWED_ORIGINS`: comma-separated page origins allowed to call the
+//!   relay, any by default
+
+use std::convert::Infallible;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use serde::{Deserialize, Serialize};
+
+use http_body_util::{BodyExt, Full, Limited};
+
+use hyper::body::{Body, Incoming};
+use hyper::header::{
+    HeaderMap, HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS,
+    AUTHORIZATION, CONTENT_TYPE, ORIGIN, RETRY_AFTER, VARY,
+};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+
+use tokio::net::TcpListener;
+
+mod config;
+mod quota;
+mod upstream;
+
+#[cfg(test)]
+mod tests;
+
+use config::Config;
+use quota::QuotaBook;
+use upstream::{GenerationConf, UpstreamError};
+
+/// Detection rubric, shared with the web app.
+const RUBRIC_PROMPT: &str = include_str!("../../src/apis/rubric.txt");
+
+/// Methods allowed in preflight responses.
+const ALLOWED_METHODS: &str = "GET, POST, OPTIONS";
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/// Detection call request body.
+#[derive(Deserialize, Debug)]
+struct DetectionRequest {
+    code: String,
+    #[serde(flatten)]
+    generation: GenerationConf,
+}
+
+/// Status response body, also used by the web app to check the connection.
+#[derive(Serialize, Debug)]
+struct StatusReply {
+    providers: Vec<String>,
+    quota: u32,
+    remaining: u32,
+    window_secs: u64,
+}
+
+/// Shared state of the relay.
+struct Relay {
+    config: Config,
+    quotas: QuotaBook,
+    client: reqwest::Client,
+    next_upstream: AtomicUsize,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), BoxError> {
+    let config = Config::from_env().unwrap_or_else(|msg| {
+        eprintln!("Invalid configuration: {}", msg);
+        process::exit(1);
+    });
+    let listener = TcpListener::bind(config.listen_addr).await?;
+    println!(
+        "Codetective relay listening on http://{}/, forwarding to {}",
+        config.listen_addr,
+        config
+            .upstreams
+            .iter()
+            .map(|upstream| format!("{} ({})", upstream.name, upstream.model))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if config.access_tokens.is_none() {
+        println!("No access tokens configured; quotas apply per client IP");
+    }
+
+    let relay = Arc::new(Relay {
+        quotas: QuotaBook::new(config.quota, config.quota_window),
+        config,
+        client: reqwest::Client::new(),
+        next_upstream: AtomicUsize::new(0),
+    });
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let relay = relay.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |req| handle(relay.clone(), req, peer));
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                eprintln!("Connection error: {}", err);
+            }
+        });
+    }
+}
+
+/// Answers one request, adding CORS headers to whatever the outcome is.
+async fn handle(
+    relay: Arc<Relay>,
+    req: Request<Incoming>,
+    peer: SocketAddr,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let origin = req.headers().get(ORIGIN).cloned();
+    let requested_headers = req.headers().get(ACCESS_CONTROL_REQUEST_HEADERS).cloned();
+
+    let mut response = match (req.method(), req.uri().path()) {
+        (&Method::OPTIONS, _) => json_response(StatusCode::NO_CONTENT, ""),
+        (&Method::GET, "/v1/status") => match relay.user_of(req.headers(), peer) {
+            Some(user) => relay.status(&user),
+            None => unauthorized_response(),
+        },
+        (&Method::POST, "/v1/detect") => match relay.user_of(req.headers(), peer) {
+            Some(user) => relay.detect(&user, req).await,
+            None => unauthorized_response(),
+        },
+        _ => error_response(StatusCode::NOT_FOUND, "no such relay endpoint"),
+    };
+
+    relay.add_cors_headers(response.headers_mut(), origin, requested_headers);
+    Ok(response)
+}
+
+impl Relay {
+    /// Identifies the user a quota applies to, checking their access token if
+    /// tokens are configured. Returns `None` if the token is missing or unknown.
+    fn user_of(&self, headers: &HeaderMap, peer: SocketAddr) -> Option<String> {
+        if let Some(tokens) = &self.config.access_tokens {
+            let token = headers
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .map(|token| token.trim());
+            return token
+                .filter(|token| tokens.contains(*token))
+                .map(|token| format!("token:{}", token));
+        }
+
+        // clients can put anything in the header themselves; only the last hop,
+        // appended by the trusted reverse proxy, is the address it saw
+        let forwarded = self
+            .config
+            .trust_forwarded
+            .then(|| headers.get("x-forwarded-for"))
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .map(|ip| ip.trim().to_string())
+            .filter(|ip| !ip.is_empty());
+        Some(format!(
+            "ip:{}",
+            forwarded.unwrap_or_else(|| peer.ip().to_string())
+        ))
+    }
+
+    /// Reports the available providers and the user's remaining quota.
+    fn status(&self, user: &str) -> Response<Full<Bytes>> {
+        let reply = StatusReply {
+            providers: self
+                .config
+                .upstreams
+                .iter()
+                .map(|upstream| upstream.name.to_string())
+                .collect(),
+            quota: self.config.quota,
+            remaining: self.quotas.remaining(user),
+            window_secs: self.config.quota_window.as_secs(),
+        };
+        json_response(StatusCode::OK, &serde_json::to_string(&reply).unwrap())
+    }
+
+    /// Forwards a detection call to the providers in round-robin order,
+    /// falling over to the next one on failure, and charges it to the user.
+    /// Calls the providers reject as invalid are charged and not retried.
+    async fn detect<B>(&self, user: &str, req: Request<B>) -> Response<Full<Bytes>>
+    where
+        B: Body,
+        B::Error: Into<BoxError>,
+    {
+        // code may double in size when escaped as a JSON string
+        let body_limit = 2 * self.config.max_code_bytes + 4096;
+        let body = match Limited::new(req.into_body(), body_limit).collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(_) => {
+                return error_response(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "request is too long for this relay",
+                )
+            }
+        };
+        let request = match serde_json::from_slice::<DetectionRequest>(&body) {
+            Ok(request) => request,
+            Err(err) => return error_response(StatusCode::BAD_REQUEST, &err.to_string()),
+        };
+        if request.code.len() > self.config.max_code_bytes {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &format!(
+                    "input is too long: this relay accepts up to {} bytes of code",
+                    self.config.max_code_bytes
+                ),
+            );
+        }
+        if let Err(msg) = request.generation.validate() {
+            return error_response(StatusCode::BAD_REQUEST, &msg);
+        }
+
+        if let Err(reset_in) = self.quotas.take(user) {
+            let mut response = error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                "request quota on this relay used up for now",
+            );
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, (reset_in.as_secs() + 1).into());
+            return response;
+        }
+
+        let prompt = format!(
+            "{}
+CODE BEGINS HERE
+{}
+CODE ENDS HERE
+",
+            RUBRIC_PROMPT, request.code
+        );
+        let max_tokens = request
+            .generation
+            .max_tokens
+            .map_or(self.config.max_output_tokens, |max| {
+                max.min(self.config.max_output_tokens)
+            });
+
+        let upstreams = &self.config.upstreams;
+        let first = self.next_upstream.fetch_add(1, Ordering::Relaxed);
+        let mut limited = None;
+        for idx in 0..upstreams.len() {
+            let upstream = &upstreams[(first + idx) % upstreams.len()];
+            match upstream
+                .detect(&self.client, &prompt, &request.generation, max_tokens)
+                .await
+            {
+                Ok(reply) => {
+                    return json_response(StatusCode::OK, &serde_json::to_string(&reply).unwrap())
+                }
+                Err(UpstreamError::Filtered) => {
+                    return error_response(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "content_filter: output withheld by the provider",
+                    )
+                }
+                Err(UpstreamError::Rejected(msg)) => {
+                    eprintln!("{} rejected the call: {}", upstream.name, msg);
+                    return error_response(
+                        StatusCode::BAD_REQUEST,
+                        &format!("rejected by the provider: {}", msg),
+                    );
+                }
+                Err(UpstreamError::Limited(retry_after)) => {
+                    eprintln!("{} is rate limited", upstream.name);
+                    limited = Some(retry_after);
+                }
+                Err(UpstreamError::Failed(msg)) => {
+                    eprintln!("{} call failed: {}", upstream.name, msg);
+                }
+            }
+        }
+
+        // not the user's fault, so not charged
+        self.quotas.refund(user);
+        match limited {
+            Some(retry_after) => {
+                let mut response = error_response(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "all providers of this relay are rate limited",
+                );
+                if let Some(value) = retry_after.and_then(|value| value.parse().ok()) {
+                    response.headers_mut().insert(RETRY_AFTER, value);
+                }
+                response
+            }
+            None => error_response(
+                StatusCode::BAD_GATEWAY,
+                "all providers of this relay failed",
+            ),
+        }
+    }
+
+    /// Allows the configured page origins, or any if none configured.
+    fn add_cors_headers(
+        &self,
+        headers: &mut HeaderMap,
+        origin: Option<HeaderValue>,
+        requested_headers: Option<HeaderValue>,
+    ) {
+        let allowed_origin = match &self.config.allowed_origins {
+            None => HeaderValue::from_static("*"),
+            Some(allowed) => {
+                headers.insert(VARY, HeaderValue::from_static("Origin"));
+                match origin {
+                    Some(origin)
+                        if allowed
+                            .iter()
+                            .any(|allowed| origin.as_bytes() == allowed.as_bytes()) =>
+                    {
+                        origin
+                    }
+                    _ => return,
+                }
+            }
+        };
+
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allowed_origin);
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static(ALLOWED_METHODS),
+        );
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_HEADERS,
+            requested_headers.unwrap_or_else(|| HeaderValue::from_static("*")),
+        );
+        headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static("600"));
+    }
+}
+
+fn json_response(status: StatusCode, body: &str) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+fn error_response(status: StatusCode, msg: &str) -> Response<Full<Bytes>> {
+    json_response(status, &serde_json::json!({ "error": msg }).to_string())
+}
+
+fn unauthorized_response() -> Response<Full<Bytes>> {
+    error_response(
+        StatusCode::UNAUTHORIZED,
+        "missing or unknown relay access token",
+    )
+}
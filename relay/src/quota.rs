@@ -0,0 +1,83 @@
+//! Per-user detection call quotas over fixed time windows.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Number of tracked users beyond which expired entries get pruned.
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// Usage of one user in their current window.
+struct Usage {
+    window_start: Instant,
+    used: u32,
+}
+
+/// Quota book of all users, keyed by access token or client IP.
+pub(crate) struct QuotaBook {
+    limit: u32,
+    window: Duration,
+    usages: Mutex<HashMap<String, Usage>>,
+}
+
+impl QuotaBook {
+    pub(crate) fn new(limit: u32, window: Duration) -> Self {
+        QuotaBook {
+            limit,
+            window,
+            usages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the number of calls left to the user in their current window.
+    pub(crate) fn remaining(&self, user: &str) -> u32 {
+        self.remaining_at(user, Instant::now())
+    }
+
+    /// Takes one call off the user's quota. Returns the time until their
+    /// window resets if they have none left.
+    pub(crate) fn take(&self, user: &str) -> Result<(), Duration> {
+        self.take_at(user, Instant::now())
+    }
+
+    /// Like `remaining()`, as of the given time.
+    pub(crate) fn remaining_at(&self, user: &str, now: Instant) -> u32 {
+        let usages = self.usages.lock().expect("quota book lock poisoned");
+        match usages.get(user) {
+            Some(usage) if now < usage.window_start + self.window => {
+                self.limit.saturating_sub(usage.used)
+            }
+            _ => self.limit,
+        }
+    }
+
+    /// Like `take()`, as of the given time.
+    pub(crate) fn take_at(&self, user: &str, now: Instant) -> Result<(), Duration> {
+        let mut usages = self.usages.lock().expect("quota book lock poisoned");
+        if usages.len() > PRUNE_THRESHOLD {
+            usages.retain(|_, usage| now < usage.window_start + self.window);
+        }
+
+        let usage = usages.entry(user.to_string()).or_insert(Usage {
+            window_start: now,
+            used: 0,
+        });
+        if now >= usage.window_start + self.window {
+            usage.window_start = now;
+            usage.used = 0;
+        }
+        if usage.used >= self.limit {
+            return Err(usage.window_start + self.window - now);
+        }
+        usage.used += 1;
+        Ok(())
+    }
+
+    /// Gives back a call taken for a detection that failed on the relay's side.
+    pub(crate) fn refund(&self, user: &str) {
+        let mut usages = self.usages.lock().expect("quota book lock poisoned");
+        if let Some(usage) = usages.get_mut(user) {
+            usage.used = usage.used.saturating_sub(1);
+        }
+    }
+}
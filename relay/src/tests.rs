@@ -0,0 +1,259 @@
+//! Quota bookkeeping, user identification, and detection forwarding tests,
+//! the latter against local stand-in provider servers.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+
+use http_body_util::{BodyExt, Full};
+
+use hyper::header::{HeaderMap, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+
+use serde_json::Value;
+
+use tokio::net::TcpListener;
+
+use crate::config::Config;
+use crate::quota::QuotaBook;
+use crate::upstream::Upstream;
+use crate::Relay;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Builds a relay forwarding to the given upstreams, identifying users by the
+/// given tokens, or by client IP if none.
+fn relay(access_tokens: Option<&[&str]>, trust_forwarded: bool, upstreams: Vec<Upstream>) -> Relay {
+    Relay {
+        config: Config {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            upstreams,
+            access_tokens: access_tokens
+                .map(|tokens| tokens.iter().map(|token| token.to_string()).collect()),
+            trust_forwarded,
+            quota: 2,
+            quota_window: WINDOW,
+            max_code_bytes: 1024,
+            max_output_tokens: 500,
+            allowed_origins: None,
+        },
+        quotas: QuotaBook::new(2, WINDOW),
+        client: reqwest::Client::new(),
+        next_upstream: AtomicUsize::new(0),
+    }
+}
+
+/// Starts a stand-in provider server answering every call with the given
+/// status and body. Returns an upstream pointing at it, and its call count.
+async fn stand_in_upstream(
+    name: &'static str,
+    status: u16,
+    body: &'static str,
+) -> (Upstream, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+    let calls = Arc::new(AtomicUsize::new(0));
+    let server_calls = calls.clone();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let calls = server_calls.clone();
+            let service = service_fn(move |_req| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                let mut response = Response::new(Full::new(Bytes::from(body)));
+                *response.status_mut() = StatusCode::from_u16(status).unwrap();
+                async move { Ok::<_, Infallible>(response) }
+            });
+            tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+        }
+    });
+    let upstream = Upstream::new(name, base_url, "model".to_string(), vec!["key".to_string()]);
+    (upstream, calls)
+}
+
+/// Sends a detection call to the relay as the given user.
+async fn detect(relay: &Relay, user: &str, body: Value) -> (StatusCode, Value) {
+    let req = Request::post("/v1/detect")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap();
+    let response = relay.detect(user, req).await;
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+const REPLY_OK: &str =
+    r#"{"choices": [{"message": {"content": "{\"score\": 2}"}, "finish_reason": "stop"}]}"#;
+
+fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for &(name, value) in pairs {
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+    }
+    headers
+}
+
+fn peer() -> SocketAddr {
+    "203.0.113.7:51234".parse().unwrap()
+}
+
+#[test]
+fn quota_take_until_used_up() {
+    let quotas = QuotaBook::new(2, WINDOW);
+    let start = Instant::now();
+    assert_eq!(quotas.remaining_at("alice", start), 2);
+
+    assert!(quotas.take_at("alice", start).is_ok());
+    assert!(quotas.take_at("alice", start).is_ok());
+    assert_eq!(quotas.remaining_at("alice", start), 0);
+    let reset_in = quotas
+        .take_at("alice", start + Duration::from_secs(20))
+        .unwrap_err();
+    assert_eq!(reset_in, Duration::from_secs(40));
+
+    // other users keep their own quota
+    assert_eq!(quotas.remaining_at("bob", start), 2);
+}
+
+#[test]
+fn quota_refund_gives_back_one_call() {
+    let quotas = QuotaBook::new(2, WINDOW);
+    let start = Instant::now();
+    quotas.take_at("alice", start).unwrap();
+    quotas.take_at("alice", start).unwrap();
+    quotas.refund("alice");
+    assert_eq!(quotas.remaining_at("alice", start), 1);
+    assert!(quotas.take_at("alice", start).is_ok());
+
+    // never goes above the limit, nor creates unknown users
+    quotas.refund("bob");
+    quotas.refund("bob");
+    assert_eq!(quotas.remaining_at("bob", start), 2);
+}
+
+#[test]
+fn quota_resets_after_window() {
+    let quotas = QuotaBook::new(2, WINDOW);
+    let start = Instant::now();
+    quotas.take_at("alice", start).unwrap();
+    quotas.take_at("alice", start).unwrap();
+    assert!(quotas.take_at("alice", start + WINDOW / 2).is_err());
+
+    let later = start + WINDOW;
+    assert_eq!(quotas.remaining_at("alice", later), 2);
+    assert!(quotas.take_at("alice", later).is_ok());
+    assert_eq!(quotas.remaining_at("alice", later), 1);
+}
+
+#[test]
+fn user_of_checks_access_tokens() {
+    let relay = relay(Some(&["t0k3n"]), false, vec![]);
+    assert_eq!(
+        relay.user_of(&headers(&[("authorization", "Bearer t0k3n")]), peer()),
+        Some("token:t0k3n".to_string())
+    );
+    assert_eq!(
+        relay.user_of(&headers(&[("authorization", "Bearer guess")]), peer()),
+        None
+    );
+    assert_eq!(
+        relay.user_of(&headers(&[("authorization", "Basic t0k3n")]), peer()),
+        None
+    );
+    assert_eq!(relay.user_of(&HeaderMap::new(), peer()), None);
+}
+
+#[test]
+fn user_of_takes_last_forwarded_hop() {
+    let forwarded = headers(&[("x-forwarded-for", "10.9.9.9, 198.51.100.2")]);
+
+    // the first entries are whatever the client sent
+    let relay_behind_proxy = relay(None, true, vec![]);
+    assert_eq!(
+        relay_behind_proxy.user_of(&forwarded, peer()),
+        Some("ip:198.51.100.2".to_string())
+    );
+    assert_eq!(
+        relay_behind_proxy.user_of(&HeaderMap::new(), peer()),
+        Some("ip:203.0.113.7".to_string())
+    );
+
+    let relay_exposed = relay(None, false, vec![]);
+    assert_eq!(
+        relay_exposed.user_of(&forwarded, peer()),
+        Some("ip:203.0.113.7".to_string())
+    );
+}
+
+#[tokio::test]
+async fn detect_falls_over_on_provider_failure() {
+    let (down, down_calls) = stand_in_upstream("Down", 503, "{}").await;
+    let (up, up_calls) = stand_in_upstream("Up", 200, REPLY_OK).await;
+    let relay = relay(None, false, vec![down, up]);
+
+    let (status, reply) = detect(&relay, "alice", serde_json::json!({ "code": "x = 1" })).await;
+    assert_eq!(status, StatusCode::OK, "{}", reply);
+    assert_eq!(reply["provider"], "Up");
+    assert_eq!(reply["output"], r#"{"score": 2}"#);
+    assert_eq!(down_calls.load(Ordering::Relaxed), 1);
+    assert_eq!(up_calls.load(Ordering::Relaxed), 1);
+    assert_eq!(relay.quotas.remaining("alice"), 1);
+}
+
+#[tokio::test]
+async fn detect_refunds_if_all_providers_fail() {
+    let (down, _) = stand_in_upstream("Down", 500, "{}").await;
+    let (limited, _) = stand_in_upstream("Limited", 429, "{}").await;
+    let relay = relay(None, false, vec![down, limited]);
+
+    let (status, _) = detect(&relay, "alice", serde_json::json!({ "code": "x = 1" })).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(relay.quotas.remaining("alice"), 2);
+}
+
+#[tokio::test]
+async fn detect_charges_rejected_calls_without_falling_over() {
+    let (strict, strict_calls) =
+        stand_in_upstream("Strict", 400, r#"{"error": "bad request"}"#).await;
+    let (other, other_calls) = stand_in_upstream("Other", 200, REPLY_OK).await;
+    let relay = relay(None, false, vec![strict, other]);
+
+    let (status, reply) = detect(&relay, "alice", serde_json::json!({ "code": "x = 1" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", reply);
+    assert_eq!(strict_calls.load(Ordering::Relaxed), 1);
+    assert_eq!(other_calls.load(Ordering::Relaxed), 0);
+    assert_eq!(relay.quotas.remaining("alice"), 1);
+}
+
+#[tokio::test]
+async fn detect_validates_settings_before_forwarding() {
+    let (up, up_calls) = stand_in_upstream("Up", 200, REPLY_OK).await;
+    let relay = relay(None, false, vec![up]);
+
+    for body in [
+        serde_json::json!({ "code": "x = 1", "temperature": 5.0 }),
+        serde_json::json!({ "code": "x = 1", "temperature": -0.5 }),
+        serde_json::json!({ "code": "x = 1", "seed": u64::MAX }),
+        serde_json::json!({ "code": "x = 1", "max_tokens": 0 }),
+    ] {
+        let (status, reply) = detect(&relay, "alice", body.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}: {}", body, reply);
+    }
+    assert_eq!(up_calls.load(Ordering::Relaxed), 0);
+    assert_eq!(relay.quotas.remaining("alice"), 2);
+
+    let (status, _) = detect(
+        &relay,
+        "alice",
+        serde_json::json!({ "code": "x = 1", "temperature": 2.0, "seed": 42 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
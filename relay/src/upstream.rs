@@ -0,0 +1,189 @@
+//! Provider APIs that detection calls are forwarded to, with the relay's own
+//! keys.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, StatusCode};
+
+/// Max sampling temperature accepted by the OpenAI-compatible providers.
+const MAX_TEMPERATURE: f64 = 2.0;
+
+/// Generation settings a detection call may carry, as sent by the web app.
+#[derive(Deserialize, Default, Debug)]
+pub(crate) struct GenerationConf {
+    pub(crate) temperature: Option<f64>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) max_tokens: Option<u32>,
+}
+
+impl GenerationConf {
+    /// Checks that the settings are within the ranges all providers accept,
+    /// so that calls the providers would reject never reach them.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self
+            .temperature
+            .is_some_and(|temp| !(0.0..=MAX_TEMPERATURE).contains(&temp))
+        {
+            return Err(format!(
+                "temperature must be a number between 0 and {}",
+                MAX_TEMPERATURE
+            ));
+        }
+        // providers take the seed as a signed 64-bit integer
+        if self.seed.is_some_and(|seed| seed > i64::MAX as u64) {
+            return Err(format!("seed must be at most {}", i64::MAX));
+        }
+        if self.max_tokens == Some(0) {
+            return Err("max tokens must be a positive integer".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Successful detection reply, sent back to the web app as is.
+#[derive(Serialize, Debug)]
+pub(crate) struct DetectionReply {
+    pub(crate) output: String,
+    pub(crate) provider: &'static str,
+    pub(crate) model: String,
+}
+
+/// Ways in which forwarding to one provider can fail.
+#[derive(Debug)]
+pub(crate) enum UpstreamError {
+    /// Rate limited or out of quota, with the provider's `Retry-After` hint.
+    Limited(Option<String>),
+    /// Output withheld by the provider's content filter.
+    Filtered,
+    /// Request rejected as invalid, which other providers would reject too,
+    /// with the provider's message.
+    Rejected(String),
+    /// Anything else, with a message for the relay log.
+    Failed(String),
+}
+
+/// OpenAI-compatible chat completion response body.
+#[derive(Deserialize, Debug)]
+struct ChatResponse {
+    choices: Vec<ChatResponseChoice>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatResponseChoice {
+    message: ChatResponseMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatResponseMessage {
+    content: Option<String>, // null when withheld
+}
+
+/// One provider with the relay's keys for it, rotated among per call.
+pub(crate) struct Upstream {
+    pub(crate) name: &'static str,
+    base_url: String,
+    pub(crate) model: String,
+    keys: Vec<String>,
+    next_key: AtomicUsize,
+}
+
+impl Upstream {
+    pub(crate) fn new(
+        name: &'static str,
+        base_url: String,
+        model: String,
+        keys: Vec<String>,
+    ) -> Self {
+        assert!(!keys.is_empty());
+        Upstream {
+            name,
+            base_url,
+            model,
+            keys,
+            next_key: AtomicUsize::new(0),
+        }
+    }
+
+    /// Forwards a detection prompt to this provider and returns its raw output
+    /// text, which the web app parses.
+    pub(crate) async fn detect(
+        &self,
+        client: &Client,
+        prompt: &str,
+        gen: &GenerationConf,
+        max_tokens: u32,
+    ) -> Result<DetectionReply, UpstreamError> {
+        let key = &self.keys[self.next_key.fetch_add(1, Ordering::Relaxed) % self.keys.len()];
+
+        let mut request = serde_json::json!({
+            "model": self.model,
+            "messages": [{
+                "role": "user",
+                "content": prompt
+            }],
+            "max_tokens": max_tokens,
+        });
+        if let Some(temp) = gen.temperature {
+            request["temperature"] = temp.into();
+        }
+        if let Some(seed) = gen.seed {
+            request["seed"] = seed.into();
+        }
+
+        let response = client
+            .post(format!("{}/chat/completions", self.base_url))
+            .bearer_auth(key)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|err| UpstreamError::Failed(err.to_string()))?;
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::PAYMENT_REQUIRED {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string());
+            return Err(UpstreamError::Limited(retry_after));
+        }
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            // auth, billing, and unknown model errors are about the relay's
+            // own keys and configuration, not the request
+            let rejected = status.is_client_error()
+                && !matches!(
+                    status,
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND
+                );
+            return Err(if rejected {
+                UpstreamError::Rejected(format!("{}: {}", status, text))
+            } else {
+                UpstreamError::Failed(format!("{}: {}", status, text))
+            });
+        }
+
+        let resp = response
+            .json::<ChatResponse>()
+            .await
+            .map_err(|err| UpstreamError::Failed(err.to_string()))?;
+        let choice = resp
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| UpstreamError::Failed("no choices found in response".to_string()))?;
+        if choice.finish_reason.as_deref() == Some("content_filter") {
+            return Err(UpstreamError::Filtered);
+        }
+
+        Ok(DetectionReply {
+            output: choice.message.content.unwrap_or_default(),
+            provider: self.name,
+            model: self.model.clone(),
+        })
+    }
+}
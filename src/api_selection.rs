@@ -5,12 +5,18 @@ use leptos::task::spawn_local;
 
 use gloo_timers::future::TimeoutFuture;
 
-use crate::apis::ApiClient;
+use crate::apis::{
+    default_model_of, ollama_list_installed_models, ApiClient, ApiEndpointConf,
+    AZURE_DEFAULT_API_VERSION, BEDROCK_DEFAULT_MODEL_ID, BEDROCK_DEFAULT_REGION,
+    OLLAMA_DEFAULT_ENDPOINT, RELAY_DEFAULT_ENDPOINT,
+};
 use crate::utils::error::{ApiKeyCheckError, CodeImportError};
 use crate::utils::gadgets::{
     FailureIndicator, HoverInfoIcon, InvisibleIndicator, SpinningIndicator, StepHeaderCollapsed,
     StepHeaderExpanded, SuccessIndicator,
 };
+use crate::utils::keystore::{self, MIN_PASSPHRASE_LEN};
+use crate::utils::proxy::{self, LOCAL_PROXY_TEMPLATE};
 use crate::utils::{NBHY, NBSP};
 use crate::{CodeGroup, FileResults, StepStage, TaskQueue, ValidationState};
 
@@ -22,6 +28,11 @@ pub(crate) enum ApiProvider {
     Gemini,
     OpenRt,
     GroqCl,
+    Azure,
+    Bedrock,
+    Ollama,
+    Relay,
+    Mock,
     Free,
     Null,
 }
@@ -34,10 +45,67 @@ impl ApiProvider {
             ApiProvider::Gemini => "Gemini (2.0 Flash)",
             ApiProvider::OpenRt => "OpenRouter (Mistral Large)",
             ApiProvider::GroqCl => "Groq Cloud (Llama-3-70B)",
+            ApiProvider::Azure => "Azure OpenAI (Deployment)",
+            ApiProvider::Bedrock => "AWS Bedrock (Converse)",
+            ApiProvider::Ollama => "Ollama (Local)",
+            ApiProvider::Relay => "Relay (Shared)",
+            ApiProvider::Mock => "Mock (Offline)",
             ApiProvider::Free => "Free Quota (Preset)",
             ApiProvider::Null => "Null",
         }
     }
+
+    /// Stable short identifier, e.g. for naming local storage entries.
+    pub(crate) fn id(&self) -> &'static str {
+        match self {
+            ApiProvider::OpenAI => "openai",
+            ApiProvider::Claude => "claude",
+            ApiProvider::Gemini => "gemini",
+            ApiProvider::OpenRt => "openrt",
+            ApiProvider::GroqCl => "groqcl",
+            ApiProvider::Azure => "azure",
+            ApiProvider::Bedrock => "bedrock",
+            ApiProvider::Ollama => "ollama",
+            ApiProvider::Relay => "relay",
+            ApiProvider::Mock => "mock",
+            ApiProvider::Free => "free",
+            ApiProvider::Null => "null",
+        }
+    }
+
+    /// Inverse of `id()`, only for providers whose keys and settings can be
+    /// remembered.
+    pub(crate) fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "openai" => Some(ApiProvider::OpenAI),
+            "claude" => Some(ApiProvider::Claude),
+            "gemini" => Some(ApiProvider::Gemini),
+            "openrt" => Some(ApiProvider::OpenRt),
+            "groqcl" => Some(ApiProvider::GroqCl),
+            "azure" => Some(ApiProvider::Azure),
+            "bedrock" => Some(ApiProvider::Bedrock),
+            "ollama" => Some(ApiProvider::Ollama),
+            "relay" => Some(ApiProvider::Relay),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the provider's keys and settings can be remembered.
+    pub(crate) fn remembers_conf(&self) -> bool {
+        self.takes_keys() || matches!(self, ApiProvider::Ollama | ApiProvider::Relay)
+    }
+
+    /// Returns true if the provider is used with user-given API keys.
+    pub(crate) fn takes_keys(&self) -> bool {
+        !matches!(
+            self,
+            ApiProvider::Free
+                | ApiProvider::Ollama
+                | ApiProvider::Relay
+                | ApiProvider::Mock
+                | ApiProvider::Null
+        )
+    }
 }
 
 // Helper functions and handler "closure"s:
@@ -48,57 +116,123 @@ fn button_style_classes(is_selected: bool) -> String {
     )
 }
 
+/// Splits the API key input field into individual keys. Multiple keys of the
+/// same provider may be separated by commas or whitespaces.
+fn split_api_keys(input: &str) -> Vec<String> {
+    input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|key| !key.is_empty())
+        .map(|key| key.to_string())
+        .collect()
+}
+
 fn handle_api_select_button(
     api_provider: RwSignal<ApiProvider>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
     api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
     selected_provider: ApiProvider,
 ) {
+    if api_provider.get_untracked() != selected_provider {
+        // model names are provider-specific
+        input_api_conf.update(|conf| conf.model.clear());
+    }
     api_provider.set(selected_provider);
     api_key_vstate.set(ValidationState::Idle);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_api_key_submit(
     api_provider: RwSignal<ApiProvider>,
     input_api_key: RwSignal<String>,
+    remember_key: RwSignal<bool>,
+    input_passphrase: RwSignal<String>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
     api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
     api_client: RwSignal<Option<ApiClient>>,
     stage: RwSignal<StepStage>,
 ) {
     let current_api_provider = api_provider.get();
-    let mut api_key = input_api_key.read().trim().to_string();
+    let mut api_keys = split_api_keys(&input_api_key.read());
+    let remember = remember_key.get() && current_api_provider.remembers_conf();
+    let passphrase = input_passphrase.get();
+    let api_conf = input_api_conf.get();
 
-    if current_api_provider != ApiProvider::Free && (api_key.is_empty() || !api_key.is_ascii()) {
+    if current_api_provider.takes_keys()
+        && (api_keys.is_empty() || api_keys.iter().any(|key| !key.is_ascii()))
+    {
         log::warn!("API key input field is empty or non-ASCII, please try again...");
         api_key_vstate.set(ValidationState::Failure(ApiKeyCheckError::ascii(
             "API key input is empty or non-ASCII",
         )));
         return;
     } else if current_api_provider == ApiProvider::Free {
-        api_key = "preset".to_string();
+        api_keys = vec!["preset".to_string()];
+    } else if current_api_provider == ApiProvider::Relay {
+        // at most one access token, which the relay may not even require
+        api_keys.truncate(1);
+    } else if !current_api_provider.takes_keys() {
+        // key field may still hold keys typed in for another provider
+        api_keys.clear();
+    }
+
+    if remember && passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        log::warn!("Passphrase for remembering keys is too short, please try again...");
+        api_key_vstate.set(ValidationState::Failure(ApiKeyCheckError::store(format!(
+            "passphrase must be at least {} characters long",
+            MIN_PASSPHRASE_LEN
+        ))));
+        return;
+    }
+
+    // the proxy also applies to code fetching in later steps
+    if let Err(msg) = proxy::set_template(&api_conf.proxy) {
+        log::warn!("Proxy URL template is invalid, please try again...");
+        api_key_vstate.set(ValidationState::Failure(ApiKeyCheckError::config(msg)));
+        return;
     }
 
     api_key_vstate.set(ValidationState::Pending);
 
     spawn_local(async move {
         log::info!(
-            "Step 1 validating: using {} key '{}'...",
+            "Step 1 validating: using {} key(s) '{}'...",
             current_api_provider.name(),
-            api_key
+            api_keys.join("', '")
         );
 
-        match ApiClient::new(current_api_provider, api_key.clone()).await {
+        match ApiClient::new(current_api_provider, api_keys.clone(), &api_conf).await {
             Ok(client) => {
                 let chosen_provider = client.provider();
                 api_client.set(Some(client));
                 api_key_vstate.set(ValidationState::Success);
 
+                // keys are known valid at this point; failing to remember
+                // them is not fatal to this session
+                if remember {
+                    match keystore::remember_api_keys(
+                        chosen_provider,
+                        &api_keys,
+                        &api_conf,
+                        &passphrase,
+                    )
+                    .await
+                    {
+                        Ok(()) => log::info!(
+                            "Remembered {} key(s) and settings for {} in local storage",
+                            api_keys.len(),
+                            chosen_provider.name()
+                        ),
+                        Err(err) => log::error!("Failed to remember API key(s): {}", err),
+                    }
+                }
+
                 // small delay before proceeding to next stage
                 TimeoutFuture::new(500).await;
 
                 log::info!(
-                    "Step 1 confirmed: using {} key '{}'",
+                    "Step 1 confirmed: using {} key(s) '{}'",
                     chosen_provider.name(),
-                    api_key
+                    api_keys.join("', '")
                 );
                 stage.set(StepStage::ApiDone);
             }
@@ -115,6 +249,102 @@ fn handle_api_key_submit(
     });
 }
 
+#[allow(clippy::too_many_arguments)]
+fn handle_api_key_unlock(
+    api_provider: RwSignal<ApiProvider>,
+    input_api_key: RwSignal<String>,
+    remember_key: RwSignal<bool>,
+    input_passphrase: RwSignal<String>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+    api_client: RwSignal<Option<ApiClient>>,
+    stage: RwSignal<StepStage>,
+) {
+    let current_api_provider = api_provider.get();
+    let passphrase = input_passphrase.get();
+
+    api_key_vstate.set(ValidationState::Pending);
+
+    spawn_local(async move {
+        log::info!(
+            "Step 1 unlocking: remembered key(s) of {}...",
+            current_api_provider.name()
+        );
+
+        match keystore::recall_api_keys(current_api_provider, &passphrase).await {
+            Ok((api_keys, api_conf)) => {
+                input_api_key.set(api_keys.join(", "));
+                input_api_conf.set(api_conf);
+                // already remembered, no need to store them again
+                remember_key.set(false);
+
+                // validate the recalled keys through the usual path
+                handle_api_key_submit(
+                    api_provider,
+                    input_api_key,
+                    remember_key,
+                    input_passphrase,
+                    input_api_conf,
+                    api_key_vstate,
+                    api_client,
+                    stage,
+                );
+            }
+
+            Err(err) => {
+                log::error!(
+                    "Unlocking remembered key(s) failed for {}: {}",
+                    current_api_provider.name(),
+                    err
+                );
+                api_key_vstate.set(ValidationState::Failure(err));
+            }
+        }
+    });
+}
+
+fn handle_list_models_button(
+    input_api_conf: RwSignal<ApiEndpointConf>,
+    installed_models: RwSignal<Vec<String>>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+) {
+    let endpoint = input_api_conf.read().endpoint.clone();
+    api_key_vstate.set(ValidationState::Pending);
+
+    spawn_local(async move {
+        match ollama_list_installed_models(&endpoint).await {
+            Ok(models) => {
+                log::info!("Listed {} installed Ollama model(s)", models.len());
+                installed_models.set(models);
+                api_key_vstate.set(ValidationState::Idle);
+            }
+            Err(err) => {
+                log::error!("Listing installed Ollama models failed: {}", err);
+                installed_models.set(Vec::new());
+                api_key_vstate.set(ValidationState::Failure(err));
+            }
+        }
+    });
+}
+
+fn handle_forget_keys_button(
+    input_passphrase: RwSignal<String>,
+    has_remembered: RwSignal<bool>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+) {
+    match keystore::forget_all_api_keys() {
+        Ok(()) => {
+            input_passphrase.set(String::new());
+            has_remembered.set(false);
+            api_key_vstate.set(ValidationState::Idle);
+        }
+        Err(err) => {
+            log::error!("Forgetting remembered API keys failed: {}", err);
+            api_key_vstate.set(ValidationState::Failure(err));
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn handle_back_button(
     api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
@@ -143,90 +373,673 @@ fn handle_back_button(
     nothing_to_retry.set(false);
     stage.set(StepStage::Initial);
 
-    log::info!("Step 1 rolled back: resetting API key validation stage");
-}
+    log::info!("Step 1 rolled back: resetting API key validation stage");
+}
+
+// Different display componenets shown selectively:
+#[component]
+fn ValidationIndicator(
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+) -> impl IntoView {
+    move || match api_key_vstate.get() {
+        ValidationState::Idle => InvisibleIndicator().into_any(),
+        ValidationState::Pending => SpinningIndicator().into_any(),
+        ValidationState::Success => SuccessIndicator().into_any(),
+        ValidationState::Failure(_) => FailureIndicator().into_any(),
+    }
+}
+
+#[component]
+fn ValidationErrorMsg(
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+) -> impl IntoView {
+    move || {
+        if let ValidationState::Failure(err) = api_key_vstate.get() {
+            Some(view! {
+                <div class="text-red-700 text-base font-mono mt-4 text-center animate-fade-in">
+                    {format!(
+                        "API key validation failed: {}",
+                        match &err {
+                            ApiKeyCheckError::Parse(_) => "internal parsing error...",
+                            ApiKeyCheckError::Status(_) => "authorization failure, invalid API key?",
+                            ApiKeyCheckError::Limit(_) => "usage limit seems to have been exceeded!",
+                            ApiKeyCheckError::Ascii(_) => "please provide a legit API key...",
+                            ApiKeyCheckError::Random(_) => "random number generation error...",
+                            ApiKeyCheckError::Store(msg) => msg.as_str(),
+                            ApiKeyCheckError::Config(msg) => msg.as_str(),
+                        },
+                    )}
+                </div>
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[component]
+fn ApiKeyInputSection(
+    api_provider: RwSignal<ApiProvider>,
+    input_api_key: RwSignal<String>,
+    remember_key: RwSignal<bool>,
+    input_passphrase: RwSignal<String>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+    api_client: RwSignal<Option<ApiClient>>,
+    stage: RwSignal<StepStage>,
+    placeholder: &'static str,
+) -> impl IntoView {
+    view! {
+        <div class="pt-6 pb-2 px-2 animate-slide-down origin-top">
+            <div class="flex items-center justify-center space-x-4">
+                <label for="api-key" class="text-base text-gray-900 whitespace-nowrap">
+                    Enter API Key:
+                </label>
+                <input
+                    type="password"
+                    id="api-key"
+                    placeholder=placeholder
+                    prop:value=move || input_api_key.get()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_key.set(event_target_value(&ev));
+                    }
+                    on:keydown=move |ev| {
+                        if ev.key_code() != 0 && ev.key() == "Enter"
+                            && api_key_vstate.get() != ValidationState::Pending
+                            && api_key_vstate.get() != ValidationState::Success
+                        {
+                            handle_api_key_submit(
+                                api_provider,
+                                input_api_key,
+                                remember_key,
+                                input_passphrase,
+                                input_api_conf,
+                                api_key_vstate,
+                                api_client,
+                                stage,
+                            );
+                        }
+                    }
+                    class="flex-1 p-2 max-w-xl border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <HoverInfoIcon text="Codetective is a fully client-side WASM app. Your API key is not exposed to any middle server. Charges apply to your API key, of course. Multiple keys of the same provider may be given separated by commas; they are rotated among per request, and a key hitting rate limit or quota is set aside for a while." />
+
+                <button
+                    on:click=move |_| {
+                        if api_key_vstate.get() != ValidationState::Pending
+                            && api_key_vstate.get() != ValidationState::Success
+                        {
+                            handle_api_key_submit(
+                                api_provider,
+                                input_api_key,
+                                remember_key,
+                                input_passphrase,
+                                input_api_conf,
+                                api_key_vstate,
+                                api_client,
+                                stage,
+                            );
+                        }
+                    }
+                    disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    class=move || {
+                        let base = "px-4 py-2 bg-gray-500 text-white rounded-md shadow transition-colors";
+                        match api_key_vstate.get() {
+                            ValidationState::Pending => {
+                                format!("{} opacity-75 cursor-not-allowed", base)
+                            }
+                            _ => format!("{} hover:bg-gray-600", base),
+                        }
+                    }
+                >
+                    Confirm
+                </button>
+
+                <ValidationIndicator api_key_vstate />
+            </div>
+
+            <RememberKeySection
+                api_provider
+                input_api_key
+                remember_key
+                input_passphrase
+                input_api_conf
+                api_key_vstate
+                api_client
+                stage
+            />
+
+            <ValidationErrorMsg api_key_vstate />
+        </div>
+    }
+}
+
+#[component]
+fn RememberKeySection(
+    api_provider: RwSignal<ApiProvider>,
+    input_api_key: RwSignal<String>,
+    remember_key: RwSignal<bool>,
+    input_passphrase: RwSignal<String>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+    api_client: RwSignal<Option<ApiClient>>,
+    stage: RwSignal<StepStage>,
+) -> impl IntoView {
+    let has_remembered = RwSignal::new(keystore::has_remembered(api_provider.get_untracked()));
+
+    view! {
+        <div class="mt-4 flex items-center justify-center space-x-4 text-base text-gray-900">
+            {move || {
+                (has_remembered.get())
+                    .then_some(
+                        view! {
+                            <label for="api-passphrase" class="whitespace-nowrap">
+                                Unlock remembered key(s) and settings with passphrase:
+                            </label>
+                            <input
+                                type="password"
+                                id="api-passphrase"
+                                prop:value=move || input_passphrase.get()
+                                prop:disabled=move || {
+                                    api_key_vstate.get() == ValidationState::Pending
+                                }
+                                on:input=move |ev| {
+                                    input_passphrase.set(event_target_value(&ev));
+                                }
+                                on:keydown=move |ev| {
+                                    if ev.key_code() != 0 && ev.key() == "Enter"
+                                        && api_key_vstate.get() != ValidationState::Pending
+                                        && api_key_vstate.get() != ValidationState::Success
+                                    {
+                                        handle_api_key_unlock(
+                                            api_provider,
+                                            input_api_key,
+                                            remember_key,
+                                            input_passphrase,
+                                            input_api_conf,
+                                            api_key_vstate,
+                                            api_client,
+                                            stage,
+                                        );
+                                    }
+                                }
+                                class="flex-1 p-1 max-w-xs border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                            />
+                            <button
+                                on:click=move |_| {
+                                    if api_key_vstate.get() != ValidationState::Pending
+                                        && api_key_vstate.get() != ValidationState::Success
+                                    {
+                                        handle_api_key_unlock(
+                                            api_provider,
+                                            input_api_key,
+                                            remember_key,
+                                            input_passphrase,
+                                            input_api_conf,
+                                            api_key_vstate,
+                                            api_client,
+                                            stage,
+                                        );
+                                    }
+                                }
+                                class="px-3 py-1 bg-gray-500 hover:bg-gray-600 text-white rounded-md shadow transition-colors"
+                            >
+                                Unlock
+                            </button>
+                        },
+                    )
+            }}
+
+            {move || {
+                (!has_remembered.get())
+                    .then_some(
+                        view! {
+                            <label class="flex items-center whitespace-nowrap cursor-pointer">
+                                <input
+                                    type="checkbox"
+                                    prop:checked=move || remember_key.get()
+                                    on:change=move |ev| {
+                                        remember_key.set(event_target_checked(&ev));
+                                    }
+                                    class="mr-2"
+                                />
+                                Remember key(s) and settings in this browser
+                            </label>
+                        },
+                    )
+            }}
+            {move || {
+                (!has_remembered.get() && remember_key.get())
+                    .then_some(
+                        view! {
+                            <input
+                                type="password"
+                                placeholder="passphrase"
+                                prop:value=move || input_passphrase.get()
+                                on:input=move |ev| {
+                                    input_passphrase.set(event_target_value(&ev));
+                                }
+                                class="flex-1 p-1 max-w-xs border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                            />
+                        },
+                    )
+            }}
+
+            <button
+                on:click=move |_| handle_forget_keys_button(
+                    input_passphrase,
+                    has_remembered,
+                    api_key_vstate,
+                )
+                class="px-3 py-1 bg-white hover:bg-gray-200 text-gray-700 border border-gray-300 rounded-md shadow-sm transition-colors whitespace-nowrap"
+            >
+                Forget all keys
+            </button>
+        </div>
+
+        {move || {
+            (has_remembered.get() || remember_key.get())
+                .then_some(
+                    view! {
+                        <div class="text-orange-700 text-sm font-mono mt-2 text-center animate-fade-in">
+                            "Remembered keys are AES-GCM encrypted under your passphrase in this browser's local storage. They are only as safe as the passphrase and this device; do not use on shared computers."
+                        </div>
+                    },
+                )
+        }}
+    }
+}
+
+#[component]
+fn ThinkingToggle(input_api_conf: RwSignal<ApiEndpointConf>) -> impl IntoView {
+    view! {
+        <label class="flex items-center text-base text-gray-900 whitespace-nowrap cursor-pointer">
+            <input
+                type="checkbox"
+                prop:checked=move || input_api_conf.read().thinking
+                on:change=move |ev| {
+                    input_api_conf.update(|conf| conf.thinking = event_target_checked(&ev));
+                }
+                class="mr-2"
+            />
+            Extended thinking
+        </label>
+    }
+}
+
+#[component]
+fn ModelConfInputSection(
+    input_api_conf: RwSignal<ApiEndpointConf>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+    default_model: &'static str,
+) -> impl IntoView {
+    view! {
+        <div class="pt-6 px-2 animate-slide-down origin-top">
+            <div class="flex items-center justify-center space-x-4">
+                <label for="api-model" class="text-base text-gray-900 whitespace-nowrap">
+                    Model:
+                </label>
+                <input
+                    type="text"
+                    id="api-model"
+                    placeholder=default_model
+                    prop:value=move || input_api_conf.read().model.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.model = event_target_value(&ev));
+                    }
+                    class="flex-1 p-2 max-w-xs border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <ThinkingToggle input_api_conf />
+
+                <HoverInfoIcon text="Optionally choose another model of this provider, including reasoning models (e.g. o-series or DeepSeek-R1), which get a larger output budget for their thinking. Extended thinking turns on thinking where the model supports it, and keeps a summary of it as extended reasoning in the results." />
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn AzureConfInputSection(
+    input_api_conf: RwSignal<ApiEndpointConf>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+) -> impl IntoView {
+    view! {
+        <div class="pt-6 px-2 animate-slide-down origin-top">
+            <div class="flex items-center justify-center space-x-4">
+                <label for="azure-endpoint" class="text-base text-gray-900 whitespace-nowrap">
+                    Endpoint:
+                </label>
+                <input
+                    type="url"
+                    id="azure-endpoint"
+                    placeholder="https://<resource>.openai.azure.com"
+                    prop:value=move || input_api_conf.read().endpoint.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.endpoint = event_target_value(&ev));
+                    }
+                    class="flex-1 p-2 max-w-md border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <HoverInfoIcon text="Azure OpenAI resource endpoint, the name of a chat model deployment under it, and the data-plane API version to use (defaults to a recent GA version if left empty)." />
+            </div>
+
+            <div class="flex items-center justify-center space-x-4 mt-4">
+                <label for="azure-deployment" class="text-base text-gray-900 whitespace-nowrap">
+                    Deployment:
+                </label>
+                <input
+                    type="text"
+                    id="azure-deployment"
+                    placeholder="gpt-4o"
+                    prop:value=move || input_api_conf.read().deployment.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.deployment = event_target_value(&ev));
+                    }
+                    class="flex-1 p-2 max-w-xs border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <label for="azure-api-version" class="text-base text-gray-900 whitespace-nowrap">
+                    API Version:
+                </label>
+                <input
+                    type="text"
+                    id="azure-api-version"
+                    placeholder=AZURE_DEFAULT_API_VERSION
+                    prop:value=move || input_api_conf.read().api_version.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.api_version = event_target_value(&ev));
+                    }
+                    class="w-36 p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn BedrockConfInputSection(
+    input_api_conf: RwSignal<ApiEndpointConf>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+) -> impl IntoView {
+    view! {
+        <div class="pt-6 px-2 animate-slide-down origin-top">
+            <div class="flex items-center justify-center space-x-4">
+                <label for="bedrock-region" class="text-base text-gray-900 whitespace-nowrap">
+                    Region:
+                </label>
+                <input
+                    type="text"
+                    id="bedrock-region"
+                    placeholder=BEDROCK_DEFAULT_REGION
+                    prop:value=move || input_api_conf.read().region.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.region = event_target_value(&ev));
+                    }
+                    class="w-36 p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <label for="bedrock-model" class="text-base text-gray-900 whitespace-nowrap">
+                    Model ID:
+                </label>
+                <input
+                    type="text"
+                    id="bedrock-model"
+                    placeholder=BEDROCK_DEFAULT_MODEL_ID
+                    prop:value=move || input_api_conf.read().model.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.model = event_target_value(&ev));
+                    }
+                    class="flex-1 p-2 max-w-md border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <ThinkingToggle input_api_conf />
+
+                <HoverInfoIcon text="AWS region and the ID of a Claude or Llama model (or cross-region inference profile) enabled for your account there. Enter credentials below as ACCESS_KEY_ID:SECRET_ACCESS_KEY, optionally followed by :SESSION_TOKEN." />
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn FreeApiChoiceSection(
+    api_provider: RwSignal<ApiProvider>,
+    input_api_key: RwSignal<String>,
+    remember_key: RwSignal<bool>,
+    input_passphrase: RwSignal<String>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+    api_client: RwSignal<Option<ApiClient>>,
+    stage: RwSignal<StepStage>,
+) -> impl IntoView {
+    view! {
+        <div class="pt-6 pb-2 px-2 animate-slide-down origin-top">
+            <div class="flex items-center justify-center space-x-4">
+                <div class="text-base text-gray-900 whitespace-nowrap">
+                    Use a provider of our choice that currently grants limited free{NBHY}tier quota.
+                </div>
+
+                <HoverInfoIcon text="Limited availability per minute, day, and/or month, of course." />
+
+                <button
+                    on:click=move |_| {
+                        if api_key_vstate.get() != ValidationState::Pending
+                            && api_key_vstate.get() != ValidationState::Success
+                        {
+                            handle_api_key_submit(
+                                api_provider,
+                                input_api_key,
+                                remember_key,
+                                input_passphrase,
+                                input_api_conf,
+                                api_key_vstate,
+                                api_client,
+                                stage,
+                            );
+                        }
+                    }
+                    disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    class=move || {
+                        let base = "px-5 py-2 bg-gray-500 text-white rounded-md shadow transition-colors";
+                        match api_key_vstate.get() {
+                            ValidationState::Pending => {
+                                format!("{} opacity-75 cursor-not-allowed", base)
+                            }
+                            _ => format!("{} hover:bg-gray-600", base),
+                        }
+                    }
+                >
+                    Confirm
+                </button>
+
+                <ValidationIndicator api_key_vstate />
+            </div>
+
+            <ValidationErrorMsg api_key_vstate />
+        </div>
+    }
+}
+
+#[component]
+fn OllamaInputSection(
+    api_provider: RwSignal<ApiProvider>,
+    input_api_key: RwSignal<String>,
+    remember_key: RwSignal<bool>,
+    input_passphrase: RwSignal<String>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+    api_client: RwSignal<Option<ApiClient>>,
+    stage: RwSignal<StepStage>,
+) -> impl IntoView {
+    let installed_models = RwSignal::new(Vec::<String>::new());
+
+    view! {
+        <div class="pt-6 pb-2 px-2 animate-slide-down origin-top">
+            <div class="flex items-center justify-center space-x-4">
+                <label for="ollama-endpoint" class="text-base text-gray-900 whitespace-nowrap">
+                    Server:
+                </label>
+                <input
+                    type="url"
+                    id="ollama-endpoint"
+                    placeholder=OLLAMA_DEFAULT_ENDPOINT
+                    prop:value=move || input_api_conf.read().endpoint.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.endpoint = event_target_value(&ev));
+                    }
+                    class="flex-1 p-2 max-w-xs border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <label for="ollama-model" class="text-base text-gray-900 whitespace-nowrap">
+                    Model:
+                </label>
+                <input
+                    type="text"
+                    id="ollama-model"
+                    list="ollama-models"
+                    placeholder="first installed"
+                    prop:value=move || input_api_conf.read().model.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.model = event_target_value(&ev));
+                    }
+                    class="w-48 p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+                <datalist id="ollama-models">
+                    {move || {
+                        installed_models
+                            .get()
+                            .into_iter()
+                            .map(|model| view! { <option value=model /> })
+                            .collect::<Vec<_>>()
+                    }}
+                </datalist>
+
+                <ThinkingToggle input_api_conf />
+
+                <HoverInfoIcon text="Detection runs against an Ollama server on your own machine; no code leaves it. The page talks to the server directly from the browser, so start it with OLLAMA_ORIGINS set to allow this page's origin. Click List to fetch installed models." />
+
+                <button
+                    on:click=move |_| {
+                        if api_key_vstate.get() != ValidationState::Pending
+                            && api_key_vstate.get() != ValidationState::Success
+                        {
+                            handle_list_models_button(
+                                input_api_conf,
+                                installed_models,
+                                api_key_vstate,
+                            );
+                        }
+                    }
+                    disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    class="px-3 py-2 bg-white hover:bg-gray-200 text-gray-700 border border-gray-300 rounded-md shadow-sm transition-colors"
+                >
+                    List
+                </button>
+
+                <button
+                    on:click=move |_| {
+                        if api_key_vstate.get() != ValidationState::Pending
+                            && api_key_vstate.get() != ValidationState::Success
+                        {
+                            handle_api_key_submit(
+                                api_provider,
+                                input_api_key,
+                                remember_key,
+                                input_passphrase,
+                                input_api_conf,
+                                api_key_vstate,
+                                api_client,
+                                stage,
+                            );
+                        }
+                    }
+                    disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    class=move || {
+                        let base = "px-4 py-2 bg-gray-500 text-white rounded-md shadow transition-colors";
+                        match api_key_vstate.get() {
+                            ValidationState::Pending => {
+                                format!("{} opacity-75 cursor-not-allowed", base)
+                            }
+                            _ => format!("{} hover:bg-gray-600", base),
+                        }
+                    }
+                >
+                    Confirm
+                </button>
+
+                <ValidationIndicator api_key_vstate />
+            </div>
 
-// Different display componenets shown selectively:
-#[component]
-fn ValidationIndicator(
-    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
-) -> impl IntoView {
-    move || match api_key_vstate.get() {
-        ValidationState::Idle => InvisibleIndicator().into_any(),
-        ValidationState::Pending => SpinningIndicator().into_any(),
-        ValidationState::Success => SuccessIndicator().into_any(),
-        ValidationState::Failure(_) => FailureIndicator().into_any(),
-    }
-}
+            <RememberKeySection
+                api_provider
+                input_api_key
+                remember_key
+                input_passphrase
+                input_api_conf
+                api_key_vstate
+                api_client
+                stage
+            />
 
-#[component]
-fn ValidationErrorMsg(
-    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
-) -> impl IntoView {
-    move || {
-        if let ValidationState::Failure(err) = api_key_vstate.get() {
-            Some(view! {
-                <div class="text-red-700 text-base font-mono mt-4 text-center animate-fade-in">
-                    {format!(
-                        "API key validation failed: {}",
-                        match &err {
-                            ApiKeyCheckError::Parse(_) => "internal parsing error...",
-                            ApiKeyCheckError::Status(_) => "authorization failure, invalid API key?",
-                            ApiKeyCheckError::Limit(_) => "usage limit seems to have been exceeded!",
-                            ApiKeyCheckError::Ascii(_) => "please provide a legit API key...",
-                            ApiKeyCheckError::Random(_) => "random number generation error...",
-                        },
-                    )}
-                </div>
-            })
-        } else {
-            None
-        }
+            <ValidationErrorMsg api_key_vstate />
+        </div>
     }
 }
 
 #[component]
-fn ApiKeyInputSection(
+fn RelayInputSection(
     api_provider: RwSignal<ApiProvider>,
     input_api_key: RwSignal<String>,
+    remember_key: RwSignal<bool>,
+    input_passphrase: RwSignal<String>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
     api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
     api_client: RwSignal<Option<ApiClient>>,
     stage: RwSignal<StepStage>,
-    placeholder: &'static str,
 ) -> impl IntoView {
     view! {
         <div class="pt-6 pb-2 px-2 animate-slide-down origin-top">
             <div class="flex items-center justify-center space-x-4">
-                <label for="api-key" class="text-base text-gray-900 whitespace-nowrap">
-                    Enter API Key:
+                <label for="relay-endpoint" class="text-base text-gray-900 whitespace-nowrap">
+                    Relay:
+                </label>
+                <input
+                    type="url"
+                    id="relay-endpoint"
+                    placeholder=RELAY_DEFAULT_ENDPOINT.unwrap_or("https://relay.example.edu")
+                    prop:value=move || input_api_conf.read().endpoint.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.endpoint = event_target_value(&ev));
+                    }
+                    class="flex-1 p-2 max-w-xs border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <label for="relay-token" class="text-base text-gray-900 whitespace-nowrap">
+                    Token:
                 </label>
                 <input
                     type="password"
-                    id="api-key"
-                    placeholder=placeholder
+                    id="relay-token"
+                    placeholder="if required"
                     prop:value=move || input_api_key.get()
                     prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
                     on:input=move |ev| {
                         input_api_key.set(event_target_value(&ev));
                     }
-                    on:keydown=move |ev| {
-                        if ev.key_code() != 0 && ev.key() == "Enter"
-                            && api_key_vstate.get() != ValidationState::Pending
-                            && api_key_vstate.get() != ValidationState::Success
-                        {
-                            handle_api_key_submit(
-                                api_provider,
-                                input_api_key,
-                                api_key_vstate,
-                                api_client,
-                                stage,
-                            );
-                        }
-                    }
-                    class="flex-1 p-2 max-w-xl border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                    class="w-48 p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
                 />
 
-                <HoverInfoIcon text="Codetective is a fully client-side WASM app. Your API key is not exposed to any middle server. Charges apply to your API key, of course." />
+                <HoverInfoIcon text="A relay server hosted by your institution or team, which holds the provider keys and allows each user a quota of detection calls. Only the code is sent to the relay, which forwards it to a provider of its choice. Enter the access token you were given, if the relay requires one." />
 
                 <button
                     on:click=move |_| {
@@ -236,6 +1049,9 @@ fn ApiKeyInputSection(
                             handle_api_key_submit(
                                 api_provider,
                                 input_api_key,
+                                remember_key,
+                                input_passphrase,
+                                input_api_conf,
                                 api_key_vstate,
                                 api_client,
                                 stage,
@@ -259,15 +1075,29 @@ fn ApiKeyInputSection(
                 <ValidationIndicator api_key_vstate />
             </div>
 
+            <RememberKeySection
+                api_provider
+                input_api_key
+                remember_key
+                input_passphrase
+                input_api_conf
+                api_key_vstate
+                api_client
+                stage
+            />
+
             <ValidationErrorMsg api_key_vstate />
         </div>
     }
 }
 
 #[component]
-fn FreeApiChoiceSection(
+fn MockInputSection(
     api_provider: RwSignal<ApiProvider>,
     input_api_key: RwSignal<String>,
+    remember_key: RwSignal<bool>,
+    input_passphrase: RwSignal<String>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
     api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
     api_client: RwSignal<Option<ApiClient>>,
     stage: RwSignal<StepStage>,
@@ -275,11 +1105,11 @@ fn FreeApiChoiceSection(
     view! {
         <div class="pt-6 pb-2 px-2 animate-slide-down origin-top">
             <div class="flex items-center justify-center space-x-4">
-                <div class="text-base text-gray-900 whitespace-nowrap">
-                    Use a provider of our choice that currently grants limited free{NBHY}tier quota.
+                <div class="text-base text-gray-900">
+                    Offline mock provider, for development only.
                 </div>
 
-                <HoverInfoIcon text="Limited availability per minute, day, and/or month, of course." />
+                <HoverInfoIcon text="The mock provider makes no network requests and needs no key. Its scores are derived from a hash of the code and carry no meaning." />
 
                 <button
                     on:click=move |_| {
@@ -289,6 +1119,9 @@ fn FreeApiChoiceSection(
                             handle_api_key_submit(
                                 api_provider,
                                 input_api_key,
+                                remember_key,
+                                input_passphrase,
+                                input_api_conf,
                                 api_key_vstate,
                                 api_client,
                                 stage,
@@ -296,15 +1129,7 @@ fn FreeApiChoiceSection(
                         }
                     }
                     disabled=move || api_key_vstate.get() == ValidationState::Pending
-                    class=move || {
-                        let base = "px-5 py-2 bg-gray-500 text-white rounded-md shadow transition-colors";
-                        match api_key_vstate.get() {
-                            ValidationState::Pending => {
-                                format!("{} opacity-75 cursor-not-allowed", base)
-                            }
-                            _ => format!("{} hover:bg-gray-600", base),
-                        }
-                    }
+                    class="px-4 py-2 bg-gray-500 hover:bg-gray-600 text-white rounded-md shadow transition-colors"
                 >
                     Confirm
                 </button>
@@ -317,10 +1142,91 @@ fn FreeApiChoiceSection(
     }
 }
 
+#[component]
+fn GenerationConfInputSection(
+    input_api_conf: RwSignal<ApiEndpointConf>,
+    api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
+) -> impl IntoView {
+    view! {
+        <div class="pt-6 px-2 animate-slide-down origin-top">
+            <div class="flex items-center justify-center space-x-4">
+                <label for="api-temperature" class="text-base text-gray-900 whitespace-nowrap">
+                    Temperature:
+                </label>
+                <input
+                    type="text"
+                    id="api-temperature"
+                    placeholder="default"
+                    prop:value=move || input_api_conf.read().temperature.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.temperature = event_target_value(&ev));
+                    }
+                    class="w-20 p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <label for="api-seed" class="text-base text-gray-900 whitespace-nowrap">
+                    Seed:
+                </label>
+                <input
+                    type="text"
+                    id="api-seed"
+                    placeholder="none"
+                    prop:value=move || input_api_conf.read().seed.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.seed = event_target_value(&ev));
+                    }
+                    class="w-28 p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <label for="api-max-tokens" class="text-base text-gray-900 whitespace-nowrap">
+                    Max tokens:
+                </label>
+                <input
+                    type="text"
+                    id="api-max-tokens"
+                    placeholder="500"
+                    prop:value=move || input_api_conf.read().max_tokens.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.max_tokens = event_target_value(&ev));
+                    }
+                    class="w-20 p-2 border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <HoverInfoIcon text="Optional generation settings applied to every request of this run, and recorded in the downloaded results. Use temperature 0 and a fixed seed for more reproducible runs; Claude and Bedrock ignore the seed. Max tokens caps the answer, on top of any thinking budget." />
+            </div>
+
+            <div class="flex items-center justify-center space-x-4 mt-4">
+                <label for="api-proxy" class="text-base text-gray-900 whitespace-nowrap">
+                    CORS proxy:
+                </label>
+                <input
+                    type="text"
+                    id="api-proxy"
+                    placeholder=format!("none, e.g. {}", LOCAL_PROXY_TEMPLATE)
+                    prop:value=move || input_api_conf.read().proxy.clone()
+                    prop:disabled=move || api_key_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_api_conf.update(|conf| conf.proxy = event_target_value(&ev));
+                    }
+                    class="flex-1 p-2 max-w-md border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <HoverInfoIcon text="Optional proxy that all requests, to the API provider and to code hosts alike, go through; for providers, gateways, and Git hosts that do not allow browser access. Use {url} in the template for the percent-encoded target URL, or leave it out to have the URL appended. The reference proxy under proxy/ in the repo runs locally with cargo run -p codetective-proxy." />
+            </div>
+        </div>
+    }
+}
+
 #[component]
 fn ApiSelectionExpandedView(
     api_provider: RwSignal<ApiProvider>,
     input_api_key: RwSignal<String>,
+    remember_key: RwSignal<bool>,
+    input_passphrase: RwSignal<String>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
     api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
     api_client: RwSignal<Option<ApiClient>>,
     stage: RwSignal<StepStage>,
@@ -331,10 +1237,11 @@ fn ApiSelectionExpandedView(
 
             <div class="text-xl text-center text-gray-900">Select API Provider...</div>
 
-            <div class="flex space-x-6 mt-6 mb-2 justify-center">
+            <div class="flex flex-wrap gap-6 mt-6 mb-2 justify-center">
                 <button
                     on:click=move |_| handle_api_select_button(
                         api_provider,
+                        input_api_conf,
                         api_key_vstate,
                         ApiProvider::Free,
                     )
@@ -348,6 +1255,7 @@ fn ApiSelectionExpandedView(
                 <button
                     on:click=move |_| handle_api_select_button(
                         api_provider,
+                        input_api_conf,
                         api_key_vstate,
                         ApiProvider::OpenAI,
                     )
@@ -361,6 +1269,7 @@ fn ApiSelectionExpandedView(
                 <button
                     on:click=move |_| handle_api_select_button(
                         api_provider,
+                        input_api_conf,
                         api_key_vstate,
                         ApiProvider::Claude,
                     )
@@ -374,6 +1283,7 @@ fn ApiSelectionExpandedView(
                 <button
                     on:click=move |_| handle_api_select_button(
                         api_provider,
+                        input_api_conf,
                         api_key_vstate,
                         ApiProvider::Gemini,
                     )
@@ -387,6 +1297,7 @@ fn ApiSelectionExpandedView(
                 <button
                     on:click=move |_| handle_api_select_button(
                         api_provider,
+                        input_api_conf,
                         api_key_vstate,
                         ApiProvider::OpenRt,
                     )
@@ -396,8 +1307,91 @@ fn ApiSelectionExpandedView(
                     <br />
                     <div class="font-mono">mistral</div>
                 </button>
+
+                <button
+                    on:click=move |_| handle_api_select_button(
+                        api_provider,
+                        input_api_conf,
+                        api_key_vstate,
+                        ApiProvider::Azure,
+                    )
+                    class=move || button_style_classes(api_provider.get() == ApiProvider::Azure)
+                >
+                    Azure
+                    <br />
+                    <div class="font-mono">openai</div>
+                </button>
+
+                <button
+                    on:click=move |_| handle_api_select_button(
+                        api_provider,
+                        input_api_conf,
+                        api_key_vstate,
+                        ApiProvider::Bedrock,
+                    )
+                    class=move || button_style_classes(api_provider.get() == ApiProvider::Bedrock)
+                >
+                    Bedrock
+                    <br />
+                    <div class="font-mono">claude/llama</div>
+                </button>
+
+                <button
+                    on:click=move |_| handle_api_select_button(
+                        api_provider,
+                        input_api_conf,
+                        api_key_vstate,
+                        ApiProvider::Ollama,
+                    )
+                    class=move || button_style_classes(api_provider.get() == ApiProvider::Ollama)
+                >
+                    Ollama
+                    <br />
+                    <div class="font-mono">local</div>
+                </button>
+
+                <button
+                    on:click=move |_| handle_api_select_button(
+                        api_provider,
+                        input_api_conf,
+                        api_key_vstate,
+                        ApiProvider::Relay,
+                    )
+                    class=move || button_style_classes(api_provider.get() == ApiProvider::Relay)
+                >
+                    Relay
+                    <br />
+                    <div class="font-mono">shared</div>
+                </button>
+
+                // offline mock provider is only offered in debug builds
+                {cfg!(debug_assertions)
+                    .then_some(
+                        view! {
+                            <button
+                                on:click=move |_| handle_api_select_button(
+                                    api_provider,
+                                    input_api_conf,
+                                    api_key_vstate,
+                                    ApiProvider::Mock,
+                                )
+                                class=move || button_style_classes(
+                                    api_provider.get() == ApiProvider::Mock,
+                                )
+                            >
+                                Mock
+                                <br />
+                                <div class="font-mono">offline</div>
+                            </button>
+                        },
+                    )}
             </div>
 
+            {move || {
+                (api_provider.get() != ApiProvider::Null)
+                    .then_some(view! { <GenerationConfInputSection input_api_conf api_key_vstate /> })
+            }}
+
             {move || {
                 (api_provider.get() == ApiProvider::Free)
                     .then_some(
@@ -405,6 +1399,9 @@ fn ApiSelectionExpandedView(
                             <FreeApiChoiceSection
                                 api_provider
                                 input_api_key
+                                remember_key
+                                input_passphrase
+                                input_api_conf
                                 api_key_vstate
                                 api_client
                                 stage
@@ -417,9 +1414,17 @@ fn ApiSelectionExpandedView(
                 (api_provider.get() == ApiProvider::OpenAI)
                     .then_some(
                         view! {
+                            <ModelConfInputSection
+                                input_api_conf
+                                api_key_vstate
+                                default_model=default_model_of(ApiProvider::OpenAI)
+                            />
                             <ApiKeyInputSection
                                 api_provider
                                 input_api_key
+                                remember_key
+                                input_passphrase
+                                input_api_conf
                                 api_key_vstate
                                 api_client
                                 stage
@@ -433,9 +1438,17 @@ fn ApiSelectionExpandedView(
                 (api_provider.get() == ApiProvider::Claude)
                     .then_some(
                         view! {
+                            <ModelConfInputSection
+                                input_api_conf
+                                api_key_vstate
+                                default_model=default_model_of(ApiProvider::Claude)
+                            />
                             <ApiKeyInputSection
                                 api_provider
                                 input_api_key
+                                remember_key
+                                input_passphrase
+                                input_api_conf
                                 api_key_vstate
                                 api_client
                                 stage
@@ -452,6 +1465,9 @@ fn ApiSelectionExpandedView(
                             <ApiKeyInputSection
                                 api_provider
                                 input_api_key
+                                remember_key
+                                input_passphrase
+                                input_api_conf
                                 api_key_vstate
                                 api_client
                                 stage
@@ -465,9 +1481,17 @@ fn ApiSelectionExpandedView(
                 (api_provider.get() == ApiProvider::OpenRt)
                     .then_some(
                         view! {
+                            <ModelConfInputSection
+                                input_api_conf
+                                api_key_vstate
+                                default_model=default_model_of(ApiProvider::OpenRt)
+                            />
                             <ApiKeyInputSection
                                 api_provider
                                 input_api_key
+                                remember_key
+                                input_passphrase
+                                input_api_conf
                                 api_key_vstate
                                 api_client
                                 stage
@@ -476,6 +1500,98 @@ fn ApiSelectionExpandedView(
                         },
                     )
             }}
+
+            {move || {
+                (api_provider.get() == ApiProvider::Azure)
+                    .then_some(
+                        view! {
+                            <AzureConfInputSection input_api_conf api_key_vstate />
+                            <ApiKeyInputSection
+                                api_provider
+                                input_api_key
+                                remember_key
+                                input_passphrase
+                                input_api_conf
+                                api_key_vstate
+                                api_client
+                                stage
+                                placeholder="api-key..."
+                            />
+                        },
+                    )
+            }}
+
+            {move || {
+                (api_provider.get() == ApiProvider::Bedrock)
+                    .then_some(
+                        view! {
+                            <BedrockConfInputSection input_api_conf api_key_vstate />
+                            <ApiKeyInputSection
+                                api_provider
+                                input_api_key
+                                remember_key
+                                input_passphrase
+                                input_api_conf
+                                api_key_vstate
+                                api_client
+                                stage
+                                placeholder="AKIA...:secret"
+                            />
+                        },
+                    )
+            }}
+
+            {move || {
+                (api_provider.get() == ApiProvider::Ollama)
+                    .then_some(
+                        view! {
+                            <OllamaInputSection
+                                api_provider
+                                input_api_key
+                                remember_key
+                                input_passphrase
+                                input_api_conf
+                                api_key_vstate
+                                api_client
+                                stage
+                            />
+                        },
+                    )
+            }}
+            {move || {
+                (api_provider.get() == ApiProvider::Relay)
+                    .then_some(
+                        view! {
+                            <RelayInputSection
+                                api_provider
+                                input_api_key
+                                remember_key
+                                input_passphrase
+                                input_api_conf
+                                api_key_vstate
+                                api_client
+                                stage
+                            />
+                        },
+                    )
+            }}
+            {move || {
+                (api_provider.get() == ApiProvider::Mock)
+                    .then_some(
+                        view! {
+                            <MockInputSection
+                                api_provider
+                                input_api_key
+                                remember_key
+                                input_passphrase
+                                input_api_conf
+                                api_key_vstate
+                                api_client
+                                stage
+                            />
+                        },
+                    )
+            }}
         </div>
     }
 }
@@ -548,6 +1664,9 @@ fn ApiSelectionCollapsedView(
 pub(crate) fn ApiSelection(
     api_provider: RwSignal<ApiProvider>,
     input_api_key: RwSignal<String>,
+    remember_key: RwSignal<bool>,
+    input_passphrase: RwSignal<String>,
+    input_api_conf: RwSignal<ApiEndpointConf>,
     api_key_vstate: RwSignal<ValidationState<ApiKeyCheckError>>,
     api_client: RwSignal<Option<ApiClient>>,
     code_in_vstate: RwSignal<ValidationState<CodeImportError>>,
@@ -559,6 +1678,14 @@ pub(crate) fn ApiSelection(
     nothing_to_retry: RwSignal<bool>,
     stage: RwSignal<StepStage>,
 ) -> impl IntoView {
+    // preselect the provider whose keys were remembered most recently, so
+    // that they can be unlocked right away on page load
+    if api_provider.get_untracked() == ApiProvider::Null {
+        if let Some(provider) = keystore::last_remembered_provider() {
+            api_provider.set(provider);
+        }
+    }
+
     view! {
         {move || {
             (stage.get() == StepStage::Initial)
@@ -567,6 +1694,9 @@ pub(crate) fn ApiSelection(
                         <ApiSelectionExpandedView
                             api_provider
                             input_api_key
+                            remember_key
+                            input_passphrase
+                            input_api_conf
                             api_key_vstate
                             api_client
                             stage
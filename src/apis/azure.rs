@@ -0,0 +1,257 @@
+//! API adapter for Azure OpenAI.
+//!
+//! Reference: https://learn.microsoft.com/en-us/azure/ai-services/openai/reference
+
+use serde::{Deserialize, Serialize};
+
+use url::Url;
+
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use reqwest::Client;
+
+use crate::apis::openai::{check_finish_reason, set_completion_tokens};
+use crate::apis::{
+    is_reasoning_model, ApiClient as GenericApiClient, ApiEndpointConf, ApiKeyPool, ApiTransport,
+    GenerationConf,
+};
+use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
+
+/// Azure OpenAI data-plane API version used for the deployment lookup. Newer
+/// versions dropped the deployments endpoint, so it is pinned here regardless
+/// of the user-chosen version for chat completions.
+const CHECK_API_VERSION: &str = "2022-12-01";
+
+/// Default API version for chat completions, if user leaves it empty.
+pub(crate) const DEFAULT_API_VERSION: &str = "2024-10-21";
+
+/// Max output tokens cap.
+const MAX_OUTPUT_TOKENS: u32 = 500;
+
+/// Azure OpenAI API client.
+pub(crate) struct ApiClient {
+    api_keys: ApiKeyPool,
+    deployment_url: Url,
+    api_version: String,
+    model: String,
+    reasoning: bool,
+    gen: GenerationConf,
+    transport: ApiTransport,
+    client: Client,
+}
+
+/// Azure OpenAI deployment lookup response body.
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiKeyCheckResponse {
+    id: String,
+    model: String,
+    status: String,
+}
+
+/// Azure OpenAI detection API call response body.
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponse {
+    id: String,
+    model: String,
+    choices: Vec<ApiDetectionResponseChoice>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponseChoice {
+    message: ApiDetectionResponseMessage,
+    finish_reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponseMessage {
+    content: Option<String>, // null when withheld
+}
+
+impl ApiClient {
+    /// Creates a new Azure OpenAI API client for the resource endpoint and
+    /// deployment given in `conf`. Only successful if passes the API key
+    /// validity check.
+    pub(crate) async fn new(
+        api_keys: Option<Vec<String>>,
+        conf: &ApiEndpointConf,
+        gen: GenerationConf,
+        transport: ApiTransport,
+    ) -> Result<Self, ApiKeyCheckError> {
+        let api_keys = if let Some(api_keys) = api_keys {
+            ApiKeyPool::new(api_keys)
+        } else {
+            return Err(ApiKeyCheckError::limit(
+                "API provider Azure OpenAI has no free quota available",
+            ));
+        };
+
+        let endpoint = Url::parse(conf.endpoint.trim())
+            .map_err(|err| ApiKeyCheckError::config(format!("invalid endpoint URL: {}", err)))?;
+        if endpoint.scheme() != "https" {
+            return Err(ApiKeyCheckError::config("endpoint URL must be https"));
+        }
+        let deployment = conf.deployment.trim();
+        if deployment.is_empty() || !deployment.is_ascii() {
+            return Err(ApiKeyCheckError::config(
+                "deployment name is empty or non-ASCII",
+            ));
+        }
+        let deployment_url = endpoint
+            .join(&format!("/openai/deployments/{}/", deployment))
+            .map_err(|err| ApiKeyCheckError::config(format!("invalid deployment name: {}", err)))?;
+
+        let api_version = match conf.api_version.trim() {
+            "" => DEFAULT_API_VERSION.to_string(),
+            version => version.to_string(),
+        };
+
+        let mut client = Self {
+            api_keys,
+            deployment_url,
+            api_version,
+            model: String::new(),
+            reasoning: false,
+            gen,
+            transport,
+            client: Client::new(),
+        };
+
+        let mut model = String::new();
+        for api_key in client.api_keys.keys() {
+            model = client.check_api_key(api_key, deployment).await?;
+        }
+        // known only from the deployment, whatever it is named
+        client.reasoning = is_reasoning_model(&model);
+        client.model = model;
+        Ok(client)
+    }
+
+    /// Makes an API key validity check request and returns an error if unsuccessful.
+    /// Accompolished by looking up the deployment itself, whose model is returned.
+    async fn check_api_key(
+        &self,
+        api_key: &str,
+        deployment: &str,
+    ) -> Result<String, ApiKeyCheckError> {
+        log::debug!("Choosing the Azure OpenAI API...");
+
+        let mut headers = HeaderMap::new();
+        headers.insert("api-key", HeaderValue::from_str(api_key)?);
+        let response = self
+            .client
+            .get(
+                self.transport.url(
+                    self.versioned_url("", CHECK_API_VERSION)
+                        .map_err(ApiKeyCheckError::parse)?,
+                ),
+            )
+            .headers(headers)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            // probably network error, authorization failure, or no such deployment
+            let status = response.status();
+            let text = response.text().await?;
+            Err(ApiKeyCheckError::status(format!(
+                "API key validation failed with {}: {}",
+                status, text
+            )))
+        } else {
+            // successful (quota not guaranteed)
+            let resp = response.json::<ApiKeyCheckResponse>().await?;
+            if resp.id != deployment {
+                return Err(ApiKeyCheckError::status(format!(
+                    "API key validation successful, but unexpected deployment: {}",
+                    resp.id
+                )));
+            }
+            if resp.status != "succeeded" {
+                return Err(ApiKeyCheckError::status(format!(
+                    "API key validation successful, but deployment of {} is {}",
+                    resp.model, resp.status
+                )));
+            }
+            Ok(resp.model)
+        }
+    }
+
+    /// Returns the URL of the given subpath of the deployment, or of the
+    /// deployment itself if empty, with the api-version query param. Built in
+    /// full before proxying, as proxy templates encode the whole target URL.
+    fn versioned_url(&self, subpath: &str, api_version: &str) -> Result<Url, url::ParseError> {
+        let mut url = self.deployment_url.join(subpath)?;
+        if subpath.is_empty() {
+            let path = url.path().trim_end_matches('/').to_string();
+            url.set_path(&path);
+        }
+        url.query_pairs_mut()
+            .append_pair("api-version", api_version);
+        Ok(url)
+    }
+
+    /// Returns the model detection calls are made with.
+    #[inline]
+    pub(crate) fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Returns the generation settings detection calls are made with.
+    #[inline]
+    pub(crate) fn generation(&self) -> GenerationConf {
+        self.gen.openai_style(self.reasoning)
+    }
+
+    /// Makes an detection API call and returns the response.
+    pub(crate) async fn call(&self, prompt: String) -> Result<(u8, String), ApiMakeCallError> {
+        log::debug!("Making API call to Azure OpenAI...");
+
+        let api_key = self
+            .api_keys
+            .pick()
+            .ok_or_else(|| ApiMakeCallError::rate_limit("all API keys are temporarily retired"))?;
+
+        let mut request = serde_json::json!({
+            "messages": [{
+                "role": "user",
+                "content": prompt
+            }],
+        });
+        set_completion_tokens(&mut request, &self.gen, MAX_OUTPUT_TOKENS, self.reasoning);
+        self.gen.apply_openai_style(&mut request, self.reasoning);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("api-key", HeaderValue::from_str(api_key)?);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let response = self
+            .client
+            .post(
+                self.transport.url(
+                    self.versioned_url("chat/completions", &self.api_version)
+                        .map_err(ApiMakeCallError::parse)?,
+                ),
+            )
+            .headers(headers)
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            // probably network error or rate limited
+            let err = ApiMakeCallError::from_response(response).await;
+            self.api_keys.retire_if_exhausted(api_key, &err);
+            Err(err)
+        } else {
+            // successful
+            let mut resp = response.json::<ApiDetectionResponse>().await?;
+            if resp.choices.is_empty() {
+                return Err(ApiMakeCallError::parse("no choices found in response"));
+            }
+            check_finish_reason(
+                &resp.choices[0].finish_reason,
+                resp.choices[0].message.content.as_deref(),
+            )?;
+            let output = resp.choices[0].message.content.take().unwrap_or_default();
+            GenericApiClient::output_parse_pair(output)
+        }
+    }
+}
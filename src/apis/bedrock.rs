@@ -0,0 +1,379 @@
+//! API adapter for AWS Bedrock, through the model-agnostic Converse API.
+//!
+//! Reference: https://docs.aws.amazon.com/bedrock/latest/APIReference/API_runtime_Converse.html
+
+use serde::{Deserialize, Serialize};
+
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Client, Method};
+
+use crate::apis::sigv4::{self, AwsCredentials, SigningRequest};
+use crate::apis::{
+    ApiClient as GenericApiClient, ApiEndpointConf, ApiKeyPool, ApiTransport, GenerationConf,
+    THINKING_BUDGET_TOKENS,
+};
+use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
+
+/// Default region, if user leaves it empty.
+pub(crate) const DEFAULT_REGION: &str = "us-east-1";
+
+/// Default model ID, if user leaves it empty. Any Claude or Llama model ID
+/// (or cross-region inference profile ID) enabled in the account works.
+pub(crate) const DEFAULT_MODEL_ID: &str = "anthropic.claude-3-5-sonnet-20240620-v1:0";
+
+/// Geography prefixes of cross-region inference profile IDs.
+const INFERENCE_PROFILE_PREFIXES: [&str; 5] = ["us.", "eu.", "apac.", "us-gov.", "global."];
+
+/// Max output tokens cap.
+const MAX_OUTPUT_TOKENS: u32 = 500;
+
+/// AWS Bedrock API client.
+pub(crate) struct ApiClient {
+    api_keys: ApiKeyPool,
+    region: String,
+    model_id: String,
+    thinking: bool,
+    gen: GenerationConf,
+    transport: ApiTransport,
+    client: Client,
+}
+
+/// Bedrock foundation model lookup response body.
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiKeyCheckResponse {
+    #[serde(rename = "modelDetails")]
+    model_details: ApiKeyCheckResponseDetails,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiKeyCheckResponseDetails {
+    #[serde(rename = "modelId")]
+    model_id: String,
+    #[serde(rename = "modelLifecycle")]
+    model_lifecycle: Option<ApiKeyCheckResponseLifecycle>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiKeyCheckResponseLifecycle {
+    status: String,
+}
+
+/// Bedrock Converse detection API call response body.
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponse {
+    output: ApiDetectionResponseOutput,
+    #[serde(rename = "stopReason")]
+    stop_reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponseOutput {
+    message: ApiDetectionResponseMessage,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponseMessage {
+    content: Vec<ApiDetectionResponseContent>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponseContent {
+    text: Option<String>,
+    #[serde(rename = "reasoningContent")]
+    reasoning_content: Option<ApiDetectionResponseReasoning>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponseReasoning {
+    #[serde(rename = "reasoningText")]
+    reasoning_text: Option<ApiDetectionResponseReasoningText>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponseReasoningText {
+    text: String,
+}
+
+impl ApiClient {
+    /// Creates a new Bedrock API client for the region and model given in
+    /// `conf`. Each API key is an `ACCESS_KEY_ID:SECRET_ACCESS_KEY` pair, with
+    /// an optional `:SESSION_TOKEN` suffix. Only successful if passes the API
+    /// key validity check.
+    pub(crate) async fn new(
+        api_keys: Option<Vec<String>>,
+        conf: &ApiEndpointConf,
+        gen: GenerationConf,
+        transport: ApiTransport,
+    ) -> Result<Self, ApiKeyCheckError> {
+        let api_keys = if let Some(api_keys) = api_keys {
+            if api_keys
+                .iter()
+                .any(|key| AwsCredentials::parse(key).is_none())
+            {
+                return Err(ApiKeyCheckError::config(
+                    "AWS credentials must be given as ACCESS_KEY_ID:SECRET_ACCESS_KEY",
+                ));
+            }
+            ApiKeyPool::new(api_keys)
+        } else {
+            return Err(ApiKeyCheckError::limit(
+                "API provider AWS Bedrock has no free quota available",
+            ));
+        };
+
+        let region = match conf.region.trim() {
+            "" => DEFAULT_REGION.to_string(),
+            region => region.to_string(),
+        };
+        if !region
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err(ApiKeyCheckError::config(format!(
+                "invalid AWS region '{}'",
+                region
+            )));
+        }
+
+        let model_id = match conf.model.trim() {
+            "" => DEFAULT_MODEL_ID.to_string(),
+            model_id => model_id.to_string(),
+        };
+        if !model_id.is_ascii() {
+            return Err(ApiKeyCheckError::config("model ID is non-ASCII"));
+        }
+
+        // Converse takes temperatures in range [0, 1] only, none with thinking
+        // on, and has no sampling seed
+        let gen = GenerationConf {
+            temperature: gen
+                .temperature
+                .filter(|_| !(conf.thinking && Self::is_anthropic(&model_id)))
+                .map(|temp| temp.min(1.0)),
+            seed: None,
+            ..gen
+        };
+        let client = Self {
+            api_keys,
+            region,
+            model_id,
+            thinking: conf.thinking,
+            gen,
+            transport,
+            client: Client::new(),
+        };
+
+        for api_key in client.api_keys.keys() {
+            client.check_api_key(api_key).await?;
+        }
+        Ok(client)
+    }
+
+    /// Returns the foundation model ID behind a possibly cross-region
+    /// inference profile ID.
+    fn base_model_id(&self) -> &str {
+        Self::base_of(&self.model_id)
+    }
+
+    /// Returns the foundation model ID behind the given model ID.
+    fn base_of(model_id: &str) -> &str {
+        INFERENCE_PROFILE_PREFIXES
+            .iter()
+            .find_map(|prefix| model_id.strip_prefix(prefix))
+            .unwrap_or(model_id)
+    }
+
+    /// Whether the given model ID is of a Claude model, which takes its
+    /// thinking switch as a model-specific field.
+    fn is_anthropic(model_id: &str) -> bool {
+        Self::base_of(model_id).starts_with("anthropic.")
+    }
+
+    /// Builds a SigV4-signed request to the given Bedrock host and already
+    /// URI-encoded path.
+    fn signed_request(
+        &self,
+        api_key: &str,
+        method: Method,
+        service: &str,
+        host: &str,
+        path: &str,
+        payload: &[u8],
+    ) -> Result<reqwest::RequestBuilder, reqwest::header::InvalidHeaderValue> {
+        let creds = AwsCredentials::parse(api_key).expect("AWS credentials checked on creation");
+        let signed = sigv4::sign_request(
+            &creds,
+            &SigningRequest {
+                method: method.as_str(),
+                host,
+                path,
+                payload,
+                region: &self.region,
+                service,
+            },
+            &sigv4::amz_date(&self.transport.now()),
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-amz-date", HeaderValue::from_str(&signed.amz_date)?);
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(&signed.authorization)?);
+        if let Some(token) = signed.security_token {
+            headers.insert("x-amz-security-token", HeaderValue::from_str(&token)?);
+        }
+
+        Ok(self
+            .client
+            .request(
+                method,
+                self.transport.url(format!("https://{}{}", host, path)),
+            )
+            .headers(headers))
+    }
+
+    /// Makes an API key validity check request and returns an error if unsuccessful.
+    /// Accompolished with the foundation model information API.
+    async fn check_api_key(&self, api_key: &str) -> Result<(), ApiKeyCheckError> {
+        log::debug!("Choosing the AWS Bedrock API...");
+
+        let host = format!("bedrock.{}.amazonaws.com", self.region);
+        let path = format!(
+            "/foundation-models/{}",
+            sigv4::uri_encode(self.base_model_id())
+        );
+        let response = self
+            .signed_request(api_key, Method::GET, "bedrock", &host, &path, b"")?
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            // probably network error, authorization failure, or model not enabled
+            let status = response.status();
+            let text = response.text().await?;
+            return Err(ApiKeyCheckError::status(format!(
+                "API key validation failed with {}: {}",
+                status, text
+            )));
+        } else {
+            // successful (quota not guaranteed)
+            let resp = response.json::<ApiKeyCheckResponse>().await?;
+            if resp.model_details.model_id != self.base_model_id() {
+                return Err(ApiKeyCheckError::status(format!(
+                    "API key validation successful, but unexpected model ID: {}",
+                    resp.model_details.model_id
+                )));
+            }
+            if let Some(lifecycle) = resp.model_details.model_lifecycle {
+                if lifecycle.status != "ACTIVE" {
+                    return Err(ApiKeyCheckError::status(format!(
+                        "API key validation successful, but model {} is {}",
+                        self.model_id, lifecycle.status
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the model detection calls are made with.
+    #[inline]
+    pub(crate) fn model(&self) -> &str {
+        &self.model_id
+    }
+
+    /// Returns the generation settings detection calls are made with.
+    #[inline]
+    pub(crate) fn generation(&self) -> GenerationConf {
+        self.gen
+    }
+
+    /// Makes an detection API call and returns the response.
+    pub(crate) async fn call(&self, prompt: String) -> Result<(u8, String), ApiMakeCallError> {
+        log::debug!("Making API call to AWS Bedrock...");
+
+        let api_key = self
+            .api_keys
+            .pick()
+            .ok_or_else(|| ApiMakeCallError::rate_limit("all API keys are temporarily retired"))?;
+
+        let mut request = serde_json::json!({
+            "messages": [{
+                "role": "user",
+                "content": [{
+                    "text": prompt
+                }]
+            }],
+            "inferenceConfig": {
+                "maxTokens": self.gen.max_tokens_or(MAX_OUTPUT_TOKENS),
+            }
+        });
+        // Converse has no common thinking switch; Claude models take theirs as a
+        // model-specific field, and then reject a custom temperature
+        let thinking = self.thinking && Self::is_anthropic(&self.model_id);
+        if let Some(temp) = self.gen.temperature {
+            request["inferenceConfig"]["temperature"] = temp.into();
+        }
+        if thinking {
+            request["inferenceConfig"]["maxTokens"] =
+                (self.gen.max_tokens_or(MAX_OUTPUT_TOKENS) + THINKING_BUDGET_TOKENS).into();
+            request["additionalModelRequestFields"] = serde_json::json!({
+                "thinking": {
+                    "type": "enabled",
+                    "budget_tokens": THINKING_BUDGET_TOKENS,
+                }
+            });
+        }
+
+        let payload = serde_json::to_vec(&request)?;
+
+        let host = format!("bedrock-runtime.{}.amazonaws.com", self.region);
+        let path = format!("/model/{}/converse", sigv4::uri_encode(&self.model_id));
+        let response = self
+            .signed_request(api_key, Method::POST, "bedrock", &host, &path, &payload)?
+            .header(CONTENT_TYPE, "application/json")
+            .body(payload)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            // probably network error or throttled
+            let err = ApiMakeCallError::from_response(response).await;
+            self.api_keys.retire_if_exhausted(api_key, &err);
+            Err(err)
+        } else {
+            // successful
+            let resp = response.json::<ApiDetectionResponse>().await?;
+            if resp.stop_reason == "model_context_window_exceeded" {
+                return Err(ApiMakeCallError::context_overflow(
+                    "prompt exceeds the model context window",
+                ));
+            }
+            if resp.stop_reason == "content_filtered" || resp.stop_reason == "guardrail_intervened"
+            {
+                return Err(ApiMakeCallError::content_filter(format!(
+                    "output blocked with stop reason {}",
+                    resp.stop_reason
+                )));
+            }
+            let mut output = Vec::new();
+            let mut thinking = Vec::new();
+            for content in resp.output.message.content {
+                if let Some(text) = content.text {
+                    output.push(text);
+                } else if let Some(reasoning_text) =
+                    content.reasoning_content.and_then(|rc| rc.reasoning_text)
+                {
+                    thinking.push(reasoning_text.text);
+                }
+            }
+            if output.is_empty() {
+                return Err(ApiMakeCallError::parse("no text content found in response"));
+            }
+            GenericApiClient::output_parse_reply(
+                output.join(" "),
+                Some(thinking.join(" ")),
+                self.thinking,
+            )
+        }
+    }
+}
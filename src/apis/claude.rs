@@ -2,8 +2,6 @@
 //!
 //! Reference: https://docs.anthropic.com/en/api/getting-started
 
-use std::mem;
-
 use const_format::concatcp;
 
 use serde::{Deserialize, Serialize};
@@ -11,21 +9,24 @@ use serde::{Deserialize, Serialize};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use reqwest::Client;
 
-use crate::apis::ApiClient as GenericApiClient;
+use crate::apis::{
+    ApiClient as GenericApiClient, ApiEndpointConf, ApiKeyPool, ApiTransport, GenerationConf,
+    RUBRIC_PROMPT, THINKING_BUDGET_TOKENS,
+};
 use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
 
 /// Claude API request URL prefix.
 const CLAUDE_API_PREFIX: &str = "https://api.anthropic.com/v1";
 
 /// Claude default model name.
-const CLAUDE_MODEL_NAME: &str = "claude-3-7-sonnet-20250219";
+pub(crate) const CLAUDE_MODEL_NAME: &str = "claude-3-7-sonnet-20250219";
 
 /// Claude requires an API version date.
 const CLAUDE_API_VERSION: &str = "2023-06-01";
 
-/// API key validity check request URL.
+/// API key validity check request URL prefix, to be followed by the model name.
 /// Accompolished with the model information URL.
-const CHECK_API_KEY_URL: &str = concatcp!(CLAUDE_API_PREFIX, "/models/", CLAUDE_MODEL_NAME);
+const CHECK_API_KEY_URL: &str = concatcp!(CLAUDE_API_PREFIX, "/models/");
 
 /// API chat completion request URL.
 const CHAT_COMPLETION_URL: &str = concatcp!(CLAUDE_API_PREFIX, "/messages");
@@ -35,7 +36,11 @@ const MAX_OUTPUT_TOKENS: u32 = 500;
 
 /// Claude API client.
 pub(crate) struct ApiClient {
-    api_key: String,
+    api_keys: ApiKeyPool,
+    model: String,
+    thinking: bool,
+    gen: GenerationConf,
+    transport: ApiTransport,
     client: Client,
 }
 
@@ -60,15 +65,43 @@ struct ApiDetectionResponse {
 struct ApiDetectionResponseContent {
     #[serde(rename = "type")]
     o_type: String,
+    #[serde(default)]
     text: String,
+    #[serde(default)]
+    thinking: String,
 }
 
 impl ApiClient {
-    /// Creates a new Claude API client. Only successful if passes the API key validity check.
-    pub(crate) async fn new(api_key: Option<String>) -> Result<Self, ApiKeyCheckError> {
-        let client = if let Some(api_key) = api_key {
+    /// Creates a new Claude API client, using the model given in `conf` if any,
+    /// with extended thinking if enabled there. Only successful if passes the
+    /// API key validity check.
+    pub(crate) async fn new(
+        api_keys: Option<Vec<String>>,
+        conf: &ApiEndpointConf,
+        gen: GenerationConf,
+        transport: ApiTransport,
+    ) -> Result<Self, ApiKeyCheckError> {
+        let model = match conf.model.trim() {
+            "" => CLAUDE_MODEL_NAME.to_string(),
+            model => model.to_string(),
+        };
+        // Claude takes temperatures in range [0, 1] only, none with thinking
+        // on, and has no sampling seed
+        let gen = GenerationConf {
+            temperature: gen
+                .temperature
+                .filter(|_| !conf.thinking)
+                .map(|temp| temp.min(1.0)),
+            seed: None,
+            ..gen
+        };
+        let client = if let Some(api_keys) = api_keys {
             Self {
-                api_key,
+                api_keys: ApiKeyPool::new(api_keys),
+                model,
+                thinking: conf.thinking,
+                gen,
+                transport,
                 client: Client::new(),
             }
         } else {
@@ -77,16 +110,18 @@ impl ApiClient {
             ));
         };
 
-        client.check_api_key().await?;
+        for api_key in client.api_keys.keys() {
+            client.check_api_key(api_key).await?;
+        }
         Ok(client)
     }
 
     /// Makes an API key validity check request and returns an error if unsuccessful.
-    async fn check_api_key(&self) -> Result<(), ApiKeyCheckError> {
+    async fn check_api_key(&self, api_key: &str) -> Result<(), ApiKeyCheckError> {
         log::debug!("Choosing the Claude API...");
 
         let mut headers = HeaderMap::new();
-        headers.insert("x-api-key", HeaderValue::from_str(&self.api_key)?);
+        headers.insert("x-api-key", HeaderValue::from_str(api_key)?);
         headers.insert(
             "anthropic-version",
             HeaderValue::from_static(CLAUDE_API_VERSION),
@@ -98,7 +133,10 @@ impl ApiClient {
         );
         let response = self
             .client
-            .get(CHECK_API_KEY_URL)
+            .get(
+                self.transport
+                    .url(format!("{}{}", CHECK_API_KEY_URL, self.model)),
+            )
             .headers(headers)
             .send()
             .await?;
@@ -114,7 +152,7 @@ impl ApiClient {
         } else {
             // successful (quota not guaranteed)
             let resp = response.json::<ApiKeyCheckResponse>().await?;
-            if resp.id != CLAUDE_MODEL_NAME {
+            if resp.id != self.model {
                 return Err(ApiKeyCheckError::status(format!(
                     "API key validation successful, but unexpected model name: {}",
                     resp.id
@@ -125,21 +163,54 @@ impl ApiClient {
         Ok(())
     }
 
-    /// Makes an detection API call and returns the response.
+    /// Returns the model detection calls are made with.
+    #[inline]
+    pub(crate) fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Returns the generation settings detection calls are made with.
+    #[inline]
+    pub(crate) fn generation(&self) -> GenerationConf {
+        self.gen
+    }
+
+    /// Makes an detection API call and returns the response. Takes only the
+    /// file-specific part of the prompt; the shared rubric goes in as the
+    /// system prompt.
     pub(crate) async fn call(&self, prompt: String) -> Result<(u8, String), ApiMakeCallError> {
         log::debug!("Making API call to Claude...");
 
-        let request = serde_json::json!({
-            "model": CLAUDE_MODEL_NAME,
+        let api_key = self
+            .api_keys
+            .pick()
+            .ok_or_else(|| ApiMakeCallError::rate_limit("all API keys are temporarily retired"))?;
+
+        // the rubric is shorter than the minimum cacheable prompt prefix (1024
+        // tokens or more), so it is not marked for prompt caching
+        let mut request = serde_json::json!({
+            "model": self.model,
+            "system": RUBRIC_PROMPT,
             "messages": [{
                 "role": "user",
                 "content": prompt
             }],
-            "max_tokens": MAX_OUTPUT_TOKENS,
+            "max_tokens": self.gen.max_tokens_or(MAX_OUTPUT_TOKENS),
         });
+        if self.thinking {
+            // thinking budget must be below and counts towards `max_tokens`
+            request["max_tokens"] =
+                (self.gen.max_tokens_or(MAX_OUTPUT_TOKENS) + THINKING_BUDGET_TOKENS).into();
+            request["thinking"] = serde_json::json!({
+                "type": "enabled",
+                "budget_tokens": THINKING_BUDGET_TOKENS,
+            });
+        } else if let Some(temp) = self.gen.temperature {
+            request["temperature"] = temp.into();
+        }
 
         let mut headers = HeaderMap::new();
-        headers.insert("x-api-key", HeaderValue::from_str(&self.api_key)?);
+        headers.insert("x-api-key", HeaderValue::from_str(api_key)?);
         headers.insert(
             "anthropic-version",
             HeaderValue::from_static(CLAUDE_API_VERSION),
@@ -151,7 +222,7 @@ impl ApiClient {
         );
         let response = self
             .client
-            .post(CHAT_COMPLETION_URL)
+            .post(self.transport.url(CHAT_COMPLETION_URL))
             .headers(headers)
             .json(&request)
             .send()
@@ -159,20 +230,36 @@ impl ApiClient {
 
         if !response.status().is_success() {
             // probably network error or rate limited
-            let status = response.status();
-            let text = response.text().await?;
-            Err(ApiMakeCallError::status(format!(
-                "API call failed with {}: {}",
-                status, text
-            )))
+            let err = ApiMakeCallError::from_response(response).await;
+            self.api_keys.retire_if_exhausted(api_key, &err);
+            Err(err)
         } else {
             // successful
-            let mut resp = response.json::<ApiDetectionResponse>().await?;
+            let resp = response.json::<ApiDetectionResponse>().await?;
             if resp.content.is_empty() {
                 return Err(ApiMakeCallError::parse("no content found in response"));
             }
-            let output = mem::take(&mut resp.content[0].text);
-            GenericApiClient::output_parse_pair(output)
+            if resp.stop_reason == "refusal" {
+                return Err(ApiMakeCallError::content_filter(
+                    "model refused to respond for safety reasons",
+                ));
+            }
+
+            // with extended thinking, thinking blocks come before the answer
+            let mut output = Vec::new();
+            let mut thinking = Vec::new();
+            for block in resp.content {
+                match block.o_type.as_str() {
+                    "text" => output.push(block.text),
+                    "thinking" => thinking.push(block.thinking),
+                    _ => {} // e.g., redacted thinking
+                }
+            }
+            GenericApiClient::output_parse_reply(
+                output.join(" "),
+                Some(thinking.join(" ")),
+                self.thinking,
+            )
         }
     }
 }
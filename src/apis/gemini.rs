@@ -2,18 +2,18 @@
 //!
 //! Reference: https://ai.google.dev/api
 
-use std::mem;
-
 use const_format::concatcp;
 
 use serde::{Deserialize, Serialize};
 
+use url::Url;
+
 use reqwest::header::CONTENT_TYPE;
 use reqwest::Client;
 
 use base64::prelude::*;
 
-use crate::apis::ApiClient as GenericApiClient;
+use crate::apis::{ApiClient as GenericApiClient, ApiKeyPool, ApiTransport, GenerationConf};
 use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
 
 /// Gemini API request URL prefix.
@@ -40,9 +40,23 @@ const FREE_QUOTA_API_KEY: &str = "xyzQUl6YVN5QW53SVJxYkNqLVBnLTgyWkNwX1YwT1E1U0h
 /// Max output tokens cap.
 const MAX_OUTPUT_TOKENS: u32 = 500;
 
+/// Factor to raise the max output tokens cap by when resubmitting a request
+/// whose output got truncated.
+const RAISED_TOKEN_CAP_FACTOR: u32 = 4;
+
+/// Harm categories whose block threshold can be relaxed by users.
+const RELAXABLE_HARM_CATEGORIES: [&str; 4] = [
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+
 /// Gemini API client.
 pub(crate) struct ApiClient {
-    api_key: String,
+    api_keys: ApiKeyPool,
+    gen: GenerationConf,
+    transport: ApiTransport,
     client: Client,
 }
 
@@ -53,59 +67,94 @@ struct ApiKeyCheckResponse {
     version: String,
 }
 
-/// Gemini detection API call response body.
+/// Gemini detection API call response body. Blocked prompts come back with
+/// no candidates, and blocked candidates with no content.
 #[derive(Serialize, Deserialize, Debug)]
 struct ApiDetectionResponse {
+    #[serde(default)]
     candidates: Vec<ApiDetectionResponseCandidate>,
+    #[serde(rename = "promptFeedback")]
+    prompt_feedback: Option<ApiDetectionResponseFeedback>,
     #[serde(rename = "modelVersion")]
     model_version: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponseFeedback {
+    #[serde(rename = "blockReason")]
+    block_reason: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct ApiDetectionResponseCandidate {
-    content: ApiDetectionResponseContent,
+    content: Option<ApiDetectionResponseContent>,
     #[serde(rename = "finishReason")]
-    finish_reason: String,
+    finish_reason: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct ApiDetectionResponseContent {
+    #[serde(default)]
     parts: Vec<ApiDetectionResponseContentPart>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct ApiDetectionResponseContentPart {
+    #[serde(default)]
     text: String,
 }
 
+/// Settings adjustment applied when automatically resubmitting a request.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Resubmission {
+    None,
+    RelaxSafety,
+    RaiseTokenCap,
+}
+
+/// Outcome of a single generation attempt.
+enum Attempt {
+    Done(Result<(u8, String), ApiMakeCallError>),
+    Retry(Resubmission, ApiMakeCallError),
+}
+
 impl ApiClient {
     /// Creates a new Gemini API client. Only successful if passes the API key validity check.
     /// Uses the default free quota API KEY if input key is `None`.
-    pub(crate) async fn new(api_key: Option<String>) -> Result<Self, ApiKeyCheckError> {
+    pub(crate) async fn new(
+        api_keys: Option<Vec<String>>,
+        gen: GenerationConf,
+        transport: ApiTransport,
+    ) -> Result<Self, ApiKeyCheckError> {
         let client = Self {
-            api_key: api_key.unwrap_or_else(|| {
+            api_keys: ApiKeyPool::new(api_keys.unwrap_or_else(|| {
                 let decoded = BASE64_STANDARD
                     .decode(&FREE_QUOTA_API_KEY[3..FREE_QUOTA_API_KEY.len() - 3])
                     .expect("Failed to do base64 decoding");
-                String::from_utf8(decoded).expect("API key is not a valid UTF-8 string")
-            }),
+                vec![String::from_utf8(decoded).expect("API key is not a valid UTF-8 string")]
+            })),
+            gen,
+            transport,
             client: Client::new(),
         };
 
-        client.check_api_key().await?;
+        for api_key in client.api_keys.keys() {
+            client.check_api_key(api_key).await?;
+        }
         Ok(client)
     }
 
     /// Makes an API key validity check request and returns an error if unsuccessful.
-    async fn check_api_key(&self) -> Result<(), ApiKeyCheckError> {
+    async fn check_api_key(&self, api_key: &str) -> Result<(), ApiKeyCheckError> {
         log::debug!("Choosing the Gemini API...");
 
-        let response = self
-            .client
-            .get(CHECK_API_KEY_URL)
-            .query(&[("key", &self.api_key)])
-            .send()
-            .await?;
+        let response =
+            self.client
+                .get(self.transport.url(
+                    Self::keyed_url(CHECK_API_KEY_URL, api_key).map_err(ApiKeyCheckError::parse)?,
+                ))
+                .send()
+                .await?;
 
         if !response.status().is_success() {
             // probably network error or authorization failure
@@ -129,57 +178,170 @@ impl ApiClient {
         Ok(())
     }
 
-    /// Makes an detection API call and returns the response.
+    /// Returns the model detection calls are made with.
+    #[inline]
+    pub(crate) fn model(&self) -> &str {
+        GEMINI_MODEL_NAME
+    }
+
+    /// Returns the generation settings detection calls are made with.
+    #[inline]
+    pub(crate) fn generation(&self) -> GenerationConf {
+        self.gen
+    }
+
+    /// Returns the request URL with the API key as query param. Built in full
+    /// before proxying, as proxy templates encode the whole target URL.
+    fn keyed_url(url: &str, api_key: &str) -> Result<Url, url::ParseError> {
+        Url::parse_with_params(url, &[("key", api_key)])
+    }
+
+    /// Makes an detection API call and returns the response. Safety-blocked
+    /// and truncated responses are resubmitted once with adjusted settings.
     pub(crate) async fn call(&self, prompt: String) -> Result<(u8, String), ApiMakeCallError> {
         log::debug!("Making API call to Gemini...");
 
-        let request = serde_json::json!({
+        match self.attempt(&prompt, Resubmission::None).await {
+            Attempt::Done(result) => result,
+            Attempt::Retry(resubmission, err) => {
+                log::warn!("Resubmitting to Gemini ({:?}) after: {}", resubmission, err);
+                match self.attempt(&prompt, resubmission).await {
+                    Attempt::Done(result) => result,
+                    Attempt::Retry(_, err) => Err(err),
+                }
+            }
+        }
+    }
+
+    /// Makes one generation request with the given settings adjustment.
+    async fn attempt(&self, prompt: &str, resubmission: Resubmission) -> Attempt {
+        let api_key = match self.api_keys.pick() {
+            Some(api_key) => api_key,
+            None => {
+                return Attempt::Done(Err(ApiMakeCallError::rate_limit(
+                    "all API keys are temporarily retired",
+                )))
+            }
+        };
+
+        let mut max_output_tokens = self.gen.max_tokens_or(MAX_OUTPUT_TOKENS);
+        if resubmission == Resubmission::RaiseTokenCap {
+            max_output_tokens *= RAISED_TOKEN_CAP_FACTOR;
+        }
+        let mut request = serde_json::json!({
             "contents": [{
                 "parts": [{
                     "text": prompt
                 }]
             }],
             "generationConfig": {
-                "maxOutputTokens": MAX_OUTPUT_TOKENS,
+                "maxOutputTokens": max_output_tokens,
             }
         });
+        if let Some(temp) = self.gen.temperature {
+            request["generationConfig"]["temperature"] = temp.into();
+        }
+        if let Some(seed) = self.gen.seed {
+            request["generationConfig"]["seed"] = seed.into();
+        }
+        if resubmission == Resubmission::RelaxSafety {
+            // code under analysis may legitimately touch on exploits, slurs in
+            // test data, etc.; only these categories are adjustable
+            request["safetySettings"] = RELAXABLE_HARM_CATEGORIES
+                .iter()
+                .map(|category| serde_json::json!({ "category": category, "threshold": "BLOCK_NONE" }))
+                .collect();
+        }
 
-        let response = self
+        let url = match Self::keyed_url(CHAT_COMPLETION_URL, api_key) {
+            Ok(url) => url,
+            Err(err) => return Attempt::Done(Err(ApiMakeCallError::parse(err))),
+        };
+        let response = match self
             .client
-            .post(CHAT_COMPLETION_URL)
-            .query(&[("key", &self.api_key)])
+            .post(self.transport.url(url))
             .header(CONTENT_TYPE, "application/json")
             .json(&request)
             .send()
-            .await?;
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => return Attempt::Done(Err(err.into())),
+        };
 
         if !response.status().is_success() {
             // probably network error or rate limited
-            let status = response.status();
-            let text = response.text().await?;
-            Err(ApiMakeCallError::status(format!(
-                "API call failed with {}: {}",
-                status, text
-            )))
-        } else {
-            // successful
-            let mut resp = response.json::<ApiDetectionResponse>().await?;
-            if resp.candidates.is_empty() {
-                return Err(ApiMakeCallError::parse("no candidates found in response"));
-            }
-            if resp.candidates[0].content.parts.is_empty() {
-                return Err(ApiMakeCallError::parse(
-                    "no content parts found in response",
-                ));
-            }
+            let err = ApiMakeCallError::from_response(response).await;
+            self.api_keys.retire_if_exhausted(api_key, &err);
+            return Attempt::Done(Err(err));
+        }
+
+        // successful, though the content may still be blocked
+        let resp = match response.json::<ApiDetectionResponse>().await {
+            Ok(resp) => resp,
+            Err(err) => return Attempt::Done(Err(err.into())),
+        };
+        Self::inspect_response(resp)
+    }
+
+    /// Checks block and finish reasons of a successful response before
+    /// extracting the output.
+    fn inspect_response(resp: ApiDetectionResponse) -> Attempt {
+        if let Some(reason) = resp.prompt_feedback.and_then(|fb| fb.block_reason) {
+            let err =
+                ApiMakeCallError::content_filter(format!("prompt blocked with reason {}", reason));
+            return if reason == "SAFETY" {
+                Attempt::Retry(Resubmission::RelaxSafety, err)
+            } else {
+                Attempt::Done(Err(err))
+            };
+        }
 
-            // concatinate the text output of all parts (this is likely unnecessary)
-            let mut output = mem::take(&mut resp.candidates[0].content.parts[0].text);
-            for part in resp.candidates[0].content.parts.iter_mut().skip(1) {
-                output.push(' ');
-                output.push_str(&part.text);
+        let candidate = match resp.candidates.into_iter().next() {
+            Some(candidate) => candidate,
+            None => {
+                return Attempt::Done(Err(ApiMakeCallError::parse(
+                    "no candidates found in response",
+                )))
             }
-            GenericApiClient::output_parse_pair(output)
+        };
+
+        // concatinate the text output of all parts (this is likely unnecessary)
+        let output = candidate
+            .content
+            .map(|content| {
+                content
+                    .parts
+                    .into_iter()
+                    .map(|part| part.text)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+
+        match candidate.finish_reason.as_deref() {
+            Some("SAFETY") => Attempt::Retry(
+                Resubmission::RelaxSafety,
+                ApiMakeCallError::content_filter("output blocked with finish reason SAFETY"),
+            ),
+            Some(reason @ ("BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII")) => Attempt::Done(Err(
+                ApiMakeCallError::content_filter(format!("output blocked with finish reason {}", reason)),
+            )),
+            Some("RECITATION") => Attempt::Done(Err(ApiMakeCallError::recitation(
+                "output stopped for reciting existing material; the code closely matches well-known published source",
+            ))),
+            Some("MAX_TOKENS") => match GenericApiClient::output_parse_pair(output) {
+                // the expected pair may well fit before the cap
+                Ok(pair) => Attempt::Done(Ok(pair)),
+                Err(_) => Attempt::Retry(
+                    Resubmission::RaiseTokenCap,
+                    ApiMakeCallError::truncated("output cut off at the max output tokens cap"),
+                ),
+            },
+            _ if output.is_empty() => Attempt::Done(Err(ApiMakeCallError::parse(
+                "no content parts found in response",
+            ))),
+            _ => Attempt::Done(GenericApiClient::output_parse_pair(output)),
         }
     }
 }
@@ -2,8 +2,6 @@
 //!
 //! Reference: https://console.groq.com/docs/api-reference
 
-use std::mem;
-
 use const_format::concatcp;
 
 use serde::{Deserialize, Serialize};
@@ -13,18 +11,21 @@ use reqwest::Client;
 
 use base64::prelude::*;
 
-use crate::apis::ApiClient as GenericApiClient;
+use crate::apis::{
+    is_reasoning_model, ApiClient as GenericApiClient, ApiEndpointConf, ApiKeyPool, ApiTransport,
+    GenerationConf, THINKING_BUDGET_TOKENS,
+};
 use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
 
 /// Groq Cloud API request URL prefix.
 const GROQCL_API_PREFIX: &str = "https://api.groq.com/openai/v1";
 
 /// Groq Cloud default model name.
-const GROQCL_MODEL_NAME: &str = "llama3-70b-8192";
+pub(crate) const GROQCL_MODEL_NAME: &str = "llama3-70b-8192";
 
-/// API key validity check request URL.
+/// API key validity check request URL prefix, to be followed by the model name.
 /// Accompolished with the model information URL.
-const CHECK_API_KEY_URL: &str = concatcp!(GROQCL_API_PREFIX, "/models/", GROQCL_MODEL_NAME);
+const CHECK_API_KEY_URL: &str = concatcp!(GROQCL_API_PREFIX, "/models/");
 
 /// API chat completion request URL.
 const CHAT_COMPLETION_URL: &str = concatcp!(GROQCL_API_PREFIX, "/chat/completions");
@@ -38,7 +39,12 @@ const MAX_OUTPUT_TOKENS: u32 = 500;
 
 /// Groq Cloud API client.
 pub(crate) struct ApiClient {
-    api_key: String,
+    api_keys: ApiKeyPool,
+    model: String,
+    reasoning: bool,
+    thinking: bool,
+    gen: GenerationConf,
+    transport: ApiTransport,
     client: Client,
 }
 
@@ -66,35 +72,56 @@ struct ApiDetectionResponseChoice {
 
 #[derive(Serialize, Deserialize, Debug)]
 struct ApiDetectionResponseMessage {
-    content: String,
+    content: Option<String>, // null when withheld
+    reasoning: Option<String>,
 }
 
 impl ApiClient {
-    /// Creates a new Groq Cloud API client. Only successful if passes the API key validity check.
+    /// Creates a new Groq Cloud API client, using the model given in `conf` if
+    /// any. Only successful if passes the API key validity check.
     /// Uses the default free quota API KEY if input key is `None`.
-    pub(crate) async fn new(api_key: Option<String>) -> Result<Self, ApiKeyCheckError> {
+    pub(crate) async fn new(
+        api_keys: Option<Vec<String>>,
+        conf: &ApiEndpointConf,
+        gen: GenerationConf,
+        transport: ApiTransport,
+    ) -> Result<Self, ApiKeyCheckError> {
+        let model = match conf.model.trim() {
+            "" => GROQCL_MODEL_NAME.to_string(),
+            model => model.to_string(),
+        };
         let client = Self {
-            api_key: api_key.unwrap_or_else(|| {
+            api_keys: ApiKeyPool::new(api_keys.unwrap_or_else(|| {
                 let decoded = BASE64_STANDARD
                     .decode(&FREE_QUOTA_API_KEY[3..FREE_QUOTA_API_KEY.len() - 3])
                     .expect("Failed to do base64 decoding");
-                String::from_utf8(decoded).expect("API key is not a valid UTF-8 string")
-            }),
+                vec![String::from_utf8(decoded).expect("API key is not a valid UTF-8 string")]
+            })),
+            reasoning: is_reasoning_model(&model),
+            model,
+            thinking: conf.thinking,
+            gen,
+            transport,
             client: Client::new(),
         };
 
-        client.check_api_key().await?;
+        for api_key in client.api_keys.keys() {
+            client.check_api_key(api_key).await?;
+        }
         Ok(client)
     }
 
     /// Makes an API key validity check request and returns an error if unsuccessful.
-    async fn check_api_key(&self) -> Result<(), ApiKeyCheckError> {
+    async fn check_api_key(&self, api_key: &str) -> Result<(), ApiKeyCheckError> {
         log::debug!("Choosing the Groq Cloud API...");
 
         let response = self
             .client
-            .get(CHECK_API_KEY_URL)
-            .bearer_auth(self.api_key.clone())
+            .get(
+                self.transport
+                    .url(format!("{}{}", CHECK_API_KEY_URL, self.model)),
+            )
+            .bearer_auth(api_key)
             .send()
             .await?;
 
@@ -109,7 +136,7 @@ impl ApiClient {
         } else {
             // successful (quota not guaranteed)
             let resp = response.json::<ApiKeyCheckResponse>().await?;
-            if resp.id != GROQCL_MODEL_NAME {
+            if resp.id != self.model {
                 return Err(ApiKeyCheckError::status(format!(
                     "API key validation successful, but unexpected model name: {}",
                     resp.id
@@ -118,7 +145,7 @@ impl ApiClient {
             if !resp.active {
                 return Err(ApiKeyCheckError::status(format!(
                     "API key validation successful, but model {} is inactive",
-                    GROQCL_MODEL_NAME
+                    self.model
                 )));
             }
         }
@@ -126,23 +153,48 @@ impl ApiClient {
         Ok(())
     }
 
+    /// Returns the model detection calls are made with.
+    #[inline]
+    pub(crate) fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Returns the generation settings detection calls are made with.
+    #[inline]
+    pub(crate) fn generation(&self) -> GenerationConf {
+        self.gen
+    }
+
     /// Makes an detection API call and returns the response.
     pub(crate) async fn call(&self, prompt: String) -> Result<(u8, String), ApiMakeCallError> {
         log::debug!("Making API call to Groq Cloud...");
 
-        let request = serde_json::json!({
-            "model": GROQCL_MODEL_NAME,
+        let api_key = self
+            .api_keys
+            .pick()
+            .ok_or_else(|| ApiMakeCallError::rate_limit("all API keys are temporarily retired"))?;
+
+        let mut request = serde_json::json!({
+            "model": self.model,
             "messages": [{
                 "role": "user",
                 "content": prompt
             }],
-            "max_completion_tokens": MAX_OUTPUT_TOKENS,
+            "max_completion_tokens": self.gen.max_tokens_or(MAX_OUTPUT_TOKENS),
         });
+        // Groq accepts sampling params on its reasoning models too
+        self.gen.apply_openai_style(&mut request, false);
+        if self.reasoning {
+            // have thinking returned in its own field, or not at all
+            request["max_completion_tokens"] =
+                (self.gen.max_tokens_or(MAX_OUTPUT_TOKENS) + THINKING_BUDGET_TOKENS).into();
+            request["reasoning_format"] = if self.thinking { "parsed" } else { "hidden" }.into();
+        }
 
         let response = self
             .client
-            .post(CHAT_COMPLETION_URL)
-            .bearer_auth(self.api_key.clone())
+            .post(self.transport.url(CHAT_COMPLETION_URL))
+            .bearer_auth(api_key)
             .header(CONTENT_TYPE, "application/json")
             .json(&request)
             .send()
@@ -150,20 +202,23 @@ impl ApiClient {
 
         if !response.status().is_success() {
             // probably network error or rate limited
-            let status = response.status();
-            let text = response.text().await?;
-            Err(ApiMakeCallError::status(format!(
-                "API call failed with {}: {}",
-                status, text
-            )))
+            let err = ApiMakeCallError::from_response(response).await;
+            self.api_keys.retire_if_exhausted(api_key, &err);
+            Err(err)
         } else {
             // successful
             let mut resp = response.json::<ApiDetectionResponse>().await?;
             if resp.choices.is_empty() {
                 return Err(ApiMakeCallError::parse("no choices found in response"));
             }
-            let output = mem::take(&mut resp.choices[0].message.content);
-            GenericApiClient::output_parse_pair(output)
+            if resp.choices[0].finish_reason == "content_filter" {
+                return Err(ApiMakeCallError::content_filter(
+                    "output withheld by the content filter",
+                ));
+            }
+            let output = resp.choices[0].message.content.take().unwrap_or_default();
+            let thinking = resp.choices[0].message.reasoning.take();
+            GenericApiClient::output_parse_reply(output, thinking, self.thinking)
         }
     }
 }
@@ -0,0 +1,90 @@
+//! Round-robin rotation among multiple API keys of the same provider.
+
+use std::sync::Mutex;
+
+use crate::utils::error::ApiMakeCallError;
+
+/// Number of subsequent key picks during which a retired key stays out of the
+/// rotation. Detection calls are spaced out by the task polling delay, so this
+/// amounts to roughly a minute of cooldown.
+const RETIRE_COOLDOWN_PICKS: u32 = 60;
+
+/// A pool of API keys for one provider, handed out in round-robin order.
+pub(crate) struct ApiKeyPool {
+    keys: Vec<String>,
+    state: Mutex<ApiKeyPoolState>,
+}
+
+/// Mutable rotation state, kept behind a lock so that the pool can be shared
+/// through `&self` by the adapters.
+struct ApiKeyPoolState {
+    cursor: usize,
+    cooldowns: Vec<u32>,
+}
+
+impl ApiKeyPool {
+    /// Creates a new key pool. The given key list must not be empty.
+    pub(crate) fn new(keys: Vec<String>) -> Self {
+        assert!(!keys.is_empty());
+        let num_keys = keys.len();
+        ApiKeyPool {
+            keys,
+            state: Mutex::new(ApiKeyPoolState {
+                cursor: 0,
+                cooldowns: vec![0; num_keys],
+            }),
+        }
+    }
+
+    /// Returns all keys in the pool, regardless of retirement.
+    #[inline]
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Picks the next key in round-robin order, skipping currently retired
+    /// ones. Returns `None` if all keys are retired at the moment.
+    pub(crate) fn pick(&self) -> Option<&str> {
+        let mut state = self.state.lock().expect("API key pool lock poisoned");
+
+        // every pick is one tick of cooldown for retired keys
+        for cooldown in state.cooldowns.iter_mut() {
+            *cooldown = cooldown.saturating_sub(1);
+        }
+
+        for _ in 0..self.keys.len() {
+            let idx = state.cursor;
+            state.cursor = (state.cursor + 1) % self.keys.len();
+            if state.cooldowns[idx] == 0 {
+                return Some(&self.keys[idx]);
+            }
+        }
+
+        None
+    }
+
+    /// Temporarily takes a key out of the rotation.
+    pub(crate) fn retire(&self, key: &str) {
+        if let Some(idx) = self.keys.iter().position(|k| k == key) {
+            let mut state = self.state.lock().expect("API key pool lock poisoned");
+            state.cooldowns[idx] = RETIRE_COOLDOWN_PICKS;
+            log::warn!(
+                "API key #{} retired for the next {} requests",
+                idx,
+                RETIRE_COOLDOWN_PICKS
+            );
+        }
+    }
+
+    /// Retires the key if the call failed with rate limiting or exhausted
+    /// quota/credits on it. Goes by the classified cause, as some providers
+    /// report exhausted credits with a plain 400.
+    pub(crate) fn retire_if_exhausted(&self, key: &str, err: &ApiMakeCallError) {
+        if matches!(
+            err,
+            ApiMakeCallError::RateLimit(_) | ApiMakeCallError::Quota(_)
+        ) {
+            self.retire(key);
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Built-in mock provider that answers offline, without any API key. Handy for
+//! trying out the workflow and for development; its results mean nothing.
+
+use sha2::{Digest, Sha256};
+
+use crate::apis::ApiClient as GenericApiClient;
+use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
+
+/// Mock API client.
+pub(crate) struct ApiClient;
+
+impl ApiClient {
+    /// Creates a new mock API client. Always successful.
+    pub(crate) async fn new() -> Result<Self, ApiKeyCheckError> {
+        log::debug!("Choosing the mock API...");
+        Ok(ApiClient)
+    }
+
+    /// Makes a mock detection call, with a score derived deterministically
+    /// from the prompt. Goes through the same output parsing as real replies.
+    pub(crate) async fn call(&self, prompt: String) -> Result<(u8, String), ApiMakeCallError> {
+        log::debug!("Making mock API call...");
+
+        let digest = Sha256::digest(prompt.as_bytes());
+        let output = format!(
+            "{{ \"score\": {}, \"reason\": \"Mock result derived from a hash of the code, not an actual analysis.\" }}",
+            digest[0] % 101
+        );
+        GenericApiClient::output_parse_pair(output)
+    }
+}
@@ -8,12 +8,60 @@ use serde_json::Number;
 
 use crate::api_selection::ApiProvider;
 use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
+use crate::utils::proxy::Outbound;
 
+mod azure;
+mod bedrock;
 mod claude;
 mod gemini;
 mod groqcl;
+mod mock;
+mod ollama;
 mod openai;
 mod openrt;
+mod relay;
+
+mod keypool;
+mod sigv4;
+
+#[cfg(test)]
+mod tests;
+pub(crate) use keypool::ApiKeyPool;
+
+pub(crate) use azure::DEFAULT_API_VERSION as AZURE_DEFAULT_API_VERSION;
+pub(crate) use bedrock::{
+    DEFAULT_MODEL_ID as BEDROCK_DEFAULT_MODEL_ID, DEFAULT_REGION as BEDROCK_DEFAULT_REGION,
+};
+pub(crate) use ollama::{
+    list_installed_models as ollama_list_installed_models,
+    DEFAULT_ENDPOINT as OLLAMA_DEFAULT_ENDPOINT,
+};
+pub(crate) use relay::DEFAULT_ENDPOINT as RELAY_DEFAULT_ENDPOINT;
+
+/// Output tokens budgeted for thinking, on top of each adapter's answer cap,
+/// for models that reason before answering.
+pub(crate) const THINKING_BUDGET_TOKENS: u32 = 2048;
+
+/// Max length in chars of a thinking summary kept in results.
+const MAX_THINKING_SUMMARY_LEN: usize = 1500;
+
+/// Name prefixes and substrings of known reasoning models, which reason before
+/// answering, need a larger output budget, and mostly reject sampling params.
+const REASONING_MODEL_PREFIXES: [&str; 4] = ["o1", "o3", "o4", "gpt-5"];
+const REASONING_MODEL_PATTERNS: [&str; 5] = [
+    "deepseek-r1",
+    "deepseek-reasoner",
+    "qwq",
+    "thinking",
+    "magistral",
+];
+
+/// Handcrafted AI likelihood detection rubric, identical across all files of a
+/// run. Kept in its own file, which the relay server in `relay/` shares.
+pub(crate) const RUBRIC_PROMPT: &str = include_str!("rubric.txt");
+
+/// Upper bound of user-given temperature; the widest range among providers.
+const MAX_TEMPERATURE: f64 = 2.0;
 
 /// Expected JSON output schema from responses.
 #[derive(Serialize, Deserialize, Debug)]
@@ -22,6 +70,138 @@ struct DetectionResultPair {
     reason: String,
 }
 
+/// Provider-specific connection settings entered in Step 1, beyond API keys.
+/// Only some providers make use of (some of) these fields.
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ApiEndpointConf {
+    pub(crate) endpoint: String,
+    pub(crate) deployment: String,
+    pub(crate) api_version: String,
+    pub(crate) region: String,
+    pub(crate) model: String,
+    pub(crate) thinking: bool,
+    pub(crate) temperature: String,
+    pub(crate) seed: String,
+    pub(crate) max_tokens: String,
+    pub(crate) proxy: String,
+}
+
+/// Run-level generation settings, parsed from their Step 1 input fields in
+/// `ApiEndpointConf`. `None` leaves the provider's default in place.
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub(crate) struct GenerationConf {
+    pub(crate) temperature: Option<f64>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) max_tokens: Option<u32>,
+}
+
+impl GenerationConf {
+    /// Parses the settings from the input fields of `conf`. Empty fields mean
+    /// provider defaults.
+    pub(crate) fn parse(conf: &ApiEndpointConf) -> Result<Self, ApiKeyCheckError> {
+        let temperature = match conf.temperature.trim() {
+            "" => None,
+            temp => match temp.parse::<f64>() {
+                Ok(temp) if (0.0..=MAX_TEMPERATURE).contains(&temp) => Some(temp),
+                _ => {
+                    return Err(ApiKeyCheckError::config(format!(
+                        "temperature must be a number between 0 and {}",
+                        MAX_TEMPERATURE
+                    )))
+                }
+            },
+        };
+        let seed = match conf.seed.trim() {
+            "" => None,
+            seed => Some(
+                seed.parse::<u64>()
+                    .map_err(|_| ApiKeyCheckError::config("seed must be a non-negative integer"))?,
+            ),
+        };
+        let max_tokens = match conf.max_tokens.trim() {
+            "" => None,
+            max => match max.parse::<u32>() {
+                Ok(max) if max > 0 => Some(max),
+                _ => {
+                    return Err(ApiKeyCheckError::config(
+                        "max tokens must be a positive integer",
+                    ))
+                }
+            },
+        };
+
+        Ok(GenerationConf {
+            temperature,
+            seed,
+            max_tokens,
+        })
+    }
+
+    /// Returns the answer tokens cap to use, given the adapter's default.
+    #[inline]
+    pub(crate) fn max_tokens_or(&self, default: u32) -> u32 {
+        self.max_tokens.unwrap_or(default)
+    }
+
+    /// Sets the temperature and seed fields of an OpenAI-style request body,
+    /// if given. Reasoning models reject sampling params, so skip temperature
+    /// for them.
+    pub(crate) fn apply_openai_style(&self, request: &mut serde_json::Value, reasoning: bool) {
+        if let Some(temp) = self.temperature.filter(|_| !reasoning) {
+            request["temperature"] = temp.into();
+        }
+        if let Some(seed) = self.seed {
+            request["seed"] = seed.into();
+        }
+    }
+
+    /// Returns the settings that `apply_openai_style()` actually sets.
+    #[inline]
+    pub(crate) fn openai_style(self, reasoning: bool) -> Self {
+        Self {
+            temperature: self.temperature.filter(|_| !reasoning),
+            ..self
+        }
+    }
+}
+
+/// Clock that timestamps signed requests, returning the current UTC time in
+/// the format of JavaScript's `Date.toISOString()`.
+pub(crate) type Clock = fn() -> String;
+
+/// How adapters reach their provider: where requests go, and the clock of
+/// signed requests. By default, requests go to the provider's URLs, through
+/// the user's CORS proxy if set, and the clock is the browser's.
+#[derive(Clone, Debug)]
+pub(crate) struct ApiTransport {
+    pub(crate) outbound: Outbound,
+    pub(crate) clock: Clock,
+}
+
+impl Default for ApiTransport {
+    fn default() -> Self {
+        ApiTransport {
+            outbound: Outbound::default(),
+            clock: || String::from(js_sys::Date::new_0().to_iso_string()),
+        }
+    }
+}
+
+impl ApiTransport {
+    /// Returns the URL to actually send a provider API request to.
+    #[inline]
+    pub(crate) fn url(&self, url: impl AsRef<str>) -> String {
+        self.outbound.url(url)
+    }
+
+    /// Returns the current UTC time by the transport's clock.
+    #[inline]
+    pub(crate) fn now(&self) -> String {
+        (self.clock)()
+    }
+}
+
 /// "Generic" API client.
 pub(crate) enum ApiClient {
     OpenAI(openai::ApiClient),
@@ -29,25 +209,75 @@ pub(crate) enum ApiClient {
     Gemini(gemini::ApiClient),
     OpenRt(openrt::ApiClient),
     GroqCl(groqcl::ApiClient),
+    Azure(azure::ApiClient),
+    Bedrock(bedrock::ApiClient),
+    Ollama(ollama::ApiClient),
+    Relay(relay::ApiClient),
+    Mock(mock::ApiClient),
 }
 
 impl ApiClient {
-    /// Creates a new API client for the given provider with the given API key.
-    /// Only successful if passes the API key validity check.
+    /// Creates a new API client for the given provider with the given API keys,
+    /// which are rotated among in round-robin order. Only successful if all keys
+    /// pass the API key validity check. Providers that need more than keys to
+    /// connect take their settings from `conf`, as do run-level generation
+    /// settings for all providers.
     pub(crate) async fn new(
         provider: ApiProvider,
-        api_key: String,
+        api_keys: Vec<String>,
+        conf: &ApiEndpointConf,
+    ) -> Result<Self, ApiKeyCheckError> {
+        Self::with_transport(provider, api_keys, conf, ApiTransport::default()).await
+    }
+
+    /// Like `new()`, but reaching the provider through the given transport.
+    pub(crate) async fn with_transport(
+        provider: ApiProvider,
+        api_keys: Vec<String>,
+        conf: &ApiEndpointConf,
+        transport: ApiTransport,
     ) -> Result<Self, ApiKeyCheckError> {
         // some adapters support a free-quota API key when key not given
         assert_ne!(provider, ApiProvider::Null);
-        let api_key = (provider != ApiProvider::Free).then_some(api_key);
+        let api_keys = (provider != ApiProvider::Free).then_some(api_keys);
+        let gen = GenerationConf::parse(conf)?;
+        if gen.seed.is_some() && matches!(provider, ApiProvider::Claude | ApiProvider::Bedrock) {
+            log::warn!("{} does not support a sampling seed", provider.name());
+        }
 
         match provider {
-            ApiProvider::OpenAI => Ok(Self::OpenAI(openai::ApiClient::new(api_key).await?)),
-            ApiProvider::Claude => Ok(Self::Claude(claude::ApiClient::new(api_key).await?)),
-            ApiProvider::Gemini => Ok(Self::Gemini(gemini::ApiClient::new(api_key).await?)),
-            ApiProvider::OpenRt => Ok(Self::OpenRt(openrt::ApiClient::new(api_key).await?)),
-            ApiProvider::GroqCl => Ok(Self::GroqCl(groqcl::ApiClient::new(api_key).await?)),
+            ApiProvider::OpenAI => Ok(Self::OpenAI(
+                openai::ApiClient::new(api_keys, conf, gen, transport).await?,
+            )),
+            ApiProvider::Claude => Ok(Self::Claude(
+                claude::ApiClient::new(api_keys, conf, gen, transport).await?,
+            )),
+            ApiProvider::Gemini => Ok(Self::Gemini(
+                gemini::ApiClient::new(api_keys, gen, transport).await?,
+            )),
+            ApiProvider::OpenRt => Ok(Self::OpenRt(
+                openrt::ApiClient::new(api_keys, conf, gen, transport).await?,
+            )),
+            ApiProvider::GroqCl => Ok(Self::GroqCl(
+                groqcl::ApiClient::new(api_keys, conf, gen, transport).await?,
+            )),
+            ApiProvider::Azure => Ok(Self::Azure(
+                azure::ApiClient::new(api_keys, conf, gen, transport).await?,
+            )),
+            ApiProvider::Bedrock => Ok(Self::Bedrock(
+                bedrock::ApiClient::new(api_keys, conf, gen, transport).await?,
+            )),
+            ApiProvider::Ollama => Ok(Self::Ollama(
+                ollama::ApiClient::new(conf, gen, transport).await?,
+            )),
+            ApiProvider::Relay => {
+                // an optional access token in place of keys
+                let access_token = api_keys.and_then(|keys| keys.into_iter().next());
+                Ok(Self::Relay(
+                    relay::ApiClient::new(access_token, conf, gen, transport).await?,
+                ))
+            }
+            ApiProvider::Mock => Ok(Self::Mock(mock::ApiClient::new().await?)),
 
             ApiProvider::Free => {
                 // randomly choose an adapter that might have free quota availability
@@ -59,9 +289,19 @@ impl ApiClient {
                 let provider_idx = (getrandom::u32()? as usize) % freeable_providers.len();
 
                 match freeable_providers[provider_idx] {
-                    ApiProvider::Gemini => Ok(Self::Gemini(gemini::ApiClient::new(api_key).await?)),
-                    ApiProvider::GroqCl => Ok(Self::GroqCl(groqcl::ApiClient::new(api_key).await?)),
-                    // ApiProvider::OpenRt => Ok(Self::OpenRt(openrt::ApiClient::new(api_key).await?)),
+                    ApiProvider::Gemini => Ok(Self::Gemini(
+                        gemini::ApiClient::new(api_keys, gen, transport).await?,
+                    )),
+                    ApiProvider::GroqCl => Ok(Self::GroqCl(
+                        groqcl::ApiClient::new(
+                            api_keys,
+                            &ApiEndpointConf::default(),
+                            gen,
+                            transport,
+                        )
+                        .await?,
+                    )),
+                    // ApiProvider::OpenRt => Ok(Self::OpenRt(openrt::ApiClient::new(api_keys, &ApiEndpointConf::default(), gen).await?)),
                     _ => unreachable!(),
                 }
             }
@@ -78,18 +318,69 @@ impl ApiClient {
             Self::Gemini(_) => ApiProvider::Gemini,
             Self::OpenRt(_) => ApiProvider::OpenRt,
             Self::GroqCl(_) => ApiProvider::GroqCl,
+            Self::Azure(_) => ApiProvider::Azure,
+            Self::Bedrock(_) => ApiProvider::Bedrock,
+            Self::Ollama(_) => ApiProvider::Ollama,
+            Self::Relay(_) => ApiProvider::Relay,
+            Self::Mock(_) => ApiProvider::Mock,
         }
     }
 
+    /// Returns the generation settings detection calls are actually made with,
+    /// i.e., with those the provider does not take dropped or clamped. The mock
+    /// provider takes none.
+    pub(crate) fn generation(&self) -> GenerationConf {
+        match self {
+            Self::OpenAI(client) => client.generation(),
+            Self::Claude(client) => client.generation(),
+            Self::Gemini(client) => client.generation(),
+            Self::OpenRt(client) => client.generation(),
+            Self::GroqCl(client) => client.generation(),
+            Self::Azure(client) => client.generation(),
+            Self::Bedrock(client) => client.generation(),
+            Self::Ollama(client) => client.generation(),
+            Self::Relay(client) => client.generation(),
+            Self::Mock(_) => GenerationConf::default(),
+        }
+    }
+
+    /// Returns the model detection calls are made with. The relay picks one per
+    /// call, so it reports those it has answered with so far, if any.
+    pub(crate) fn model(&self) -> Option<String> {
+        let model = match self {
+            Self::OpenAI(client) => client.model(),
+            Self::Claude(client) => client.model(),
+            Self::Gemini(client) => client.model(),
+            Self::OpenRt(client) => client.model(),
+            Self::GroqCl(client) => client.model(),
+            Self::Azure(client) => client.model(),
+            Self::Bedrock(client) => client.model(),
+            Self::Ollama(client) => client.model(),
+            Self::Relay(client) => {
+                let models = client.models_used();
+                return (!models.is_empty()).then(|| models.join(", "));
+            }
+            Self::Mock(_) => "mock",
+        };
+        Some(model.to_string())
+    }
+
     /// Makes an API call to my provider and waits for the response: a likelihood
     /// score and a short text of reasoning.
     pub(crate) async fn call(&self, code: &str) -> Result<(u8, String), ApiMakeCallError> {
         let result = match self {
             Self::OpenAI(client) => client.call(Self::prompt(code)).await,
-            Self::Claude(client) => client.call(Self::prompt(code)).await,
+            // takes the rubric separately as its system prompt
+            Self::Claude(client) => client.call(Self::code_prompt(code)).await,
             Self::Gemini(client) => client.call(Self::prompt(code)).await,
             Self::OpenRt(client) => client.call(Self::prompt(code)).await,
             Self::GroqCl(client) => client.call(Self::prompt(code)).await,
+            Self::Azure(client) => client.call(Self::prompt(code)).await,
+            Self::Bedrock(client) => client.call(Self::prompt(code)).await,
+            Self::Ollama(client) => client.call(Self::prompt(code)).await,
+            // builds the prompt on the relay's side
+            Self::Relay(client) => client.call(code).await,
+            Self::Mock(client) => client.call(Self::prompt(code)).await,
         };
 
         if let Err(err) = &result {
@@ -98,35 +389,28 @@ impl ApiClient {
         result
     }
 
-    /// Handcrafted AI likelihood detection prompt.
+    /// Full detection prompt: the shared rubric followed by the code.
     #[inline]
     fn prompt(code: &str) -> String {
-        format!("
-You are an experienced programmer and you understand deeply about large language models. I need you to analyze this code snippet to determine how likely was it generated by an AI language model:
+        format!("{}{}", RUBRIC_PROMPT, Self::code_prompt(code))
+    }
 
+    /// The file-specific part of the detection prompt.
+    #[inline]
+    fn code_prompt(code: &str) -> String {
+        format!(
+            "
 CODE BEGINS HERE
 {code}
 CODE ENDS HERE
-
-Please consider:
-1. The code's structure and syntax
-2. Use of comments and documentation
-3. Variable naming conventions
-4. Error handling approaches
-5. Overall styling consistency
-6. Appearance of esoteric algorithms
-
-You must use this exact JSON output schema, and make sure to produce a valid JSON output:
-{{ \"score\": number, \"reason\": string }}
-
-Where:
-- \"score\" is a percentage score integer between 0 and 100 (inclusive) indicating the likelihood of code being AI generated. Try to make this score fine-grained and accurate.
-- \"reason\" is a short explanation of the reasoning behind the score, which must be no longer than 150 words. Do not include any curly brackets in this string (but keep the outer brackets for JSON).
-")
+"
+        )
     }
 
     /// Strip out and parse the expected json output piece from the response.
+    /// Inline thinking blocks are dropped first, as they may contain braces.
     pub(crate) fn output_parse_pair(output: String) -> Result<(u8, String), ApiMakeCallError> {
+        let (output, _) = split_think_tags(output);
         if let Some(pos_s) = output.find('{') {
             if let Some(pos_e) = output[pos_s..].find('}') {
                 let pos_e = pos_s + pos_e;
@@ -145,4 +429,86 @@ Where:
             "failed to parse expected json pair from response",
         ))
     }
+
+    /// Like `output_parse_pair()`, but for models that return their thinking
+    /// separately or inline. If `keep_thinking` is set, a summary of it is
+    /// appended to the reasoning as extended reasoning.
+    pub(crate) fn output_parse_reply(
+        output: String,
+        thinking: Option<String>,
+        keep_thinking: bool,
+    ) -> Result<(u8, String), ApiMakeCallError> {
+        let (output, inline_thinking) = split_think_tags(output);
+        let (score, reason) = Self::output_parse_pair(output)?;
+
+        let thinking = [thinking, inline_thinking]
+            .into_iter()
+            .flatten()
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !keep_thinking || thinking.is_empty() {
+            return Ok((score, reason));
+        }
+
+        let mut summary = thinking;
+        if let Some((idx, _)) = summary.char_indices().nth(MAX_THINKING_SUMMARY_LEN) {
+            summary.truncate(idx);
+            summary.push_str("...");
+        }
+        Ok((score, format!("{} Extended reasoning: {}", reason, summary)))
+    }
+}
+
+/// Returns true if the model name looks like one of a reasoning model.
+pub(crate) fn is_reasoning_model(model: &str) -> bool {
+    let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    REASONING_MODEL_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+        || REASONING_MODEL_PATTERNS
+            .iter()
+            .any(|pattern| name.contains(pattern))
+}
+
+/// Returns the default model name of the given provider's adapter, used when
+/// the user leaves the model field empty. Empty for providers without a model
+/// choice or with their own model field.
+pub(crate) fn default_model_of(provider: ApiProvider) -> &'static str {
+    match provider {
+        ApiProvider::OpenAI => openai::OPENAI_MODEL_NAME,
+        ApiProvider::Claude => claude::CLAUDE_MODEL_NAME,
+        ApiProvider::OpenRt => openrt::OPENRT_MODEL_NAME,
+        ApiProvider::GroqCl => groqcl::GROQCL_MODEL_NAME,
+        _ => "",
+    }
+}
+
+/// Splits DeepSeek-R1-style inline `<think>...</think>` blocks off the output,
+/// returning the remaining answer and the thinking text, if any.
+fn split_think_tags(output: String) -> (String, Option<String>) {
+    const OPEN_TAG: &str = "<think>";
+    const CLOSE_TAG: &str = "</think>";
+
+    if !output.contains(CLOSE_TAG) {
+        return (output, None);
+    }
+
+    let mut answer = String::with_capacity(output.len());
+    let mut thinking = Vec::new();
+    let mut rest = output.as_str();
+    while let Some(pos_e) = rest.find(CLOSE_TAG) {
+        // some models omit the opening tag, as it is part of their template
+        let (before, inside) = match rest[..pos_e].find(OPEN_TAG) {
+            Some(pos_s) => (&rest[..pos_s], &rest[pos_s + OPEN_TAG.len()..pos_e]),
+            None => ("", &rest[..pos_e]),
+        };
+        answer.push_str(before);
+        thinking.push(inside.trim());
+        rest = &rest[pos_e + CLOSE_TAG.len()..];
+    }
+    answer.push_str(rest);
+
+    (answer, Some(thinking.join(" ")))
 }
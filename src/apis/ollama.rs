@@ -0,0 +1,249 @@
+//! API adapter for a (typically laptop-local) Ollama server, through its native API.
+//!
+//! Reference: https://github.com/ollama/ollama/blob/main/docs/api.md
+
+use serde::{Deserialize, Serialize};
+
+use url::Url;
+
+use reqwest::Client;
+
+use crate::apis::{
+    is_reasoning_model, ApiClient as GenericApiClient, ApiEndpointConf, ApiTransport,
+    GenerationConf, THINKING_BUDGET_TOKENS,
+};
+use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
+
+/// Default Ollama server endpoint, if user leaves it empty.
+pub(crate) const DEFAULT_ENDPOINT: &str = "http://localhost:11434";
+
+/// Max output tokens cap.
+const MAX_OUTPUT_TOKENS: u32 = 500;
+
+/// Ollama API client.
+pub(crate) struct ApiClient {
+    base_url: Url,
+    model: String,
+    thinking: bool,
+    gen: GenerationConf,
+    transport: ApiTransport,
+    client: Client,
+}
+
+/// Ollama installed models listing response body.
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiTagsResponse {
+    models: Vec<ApiTagsResponseModel>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiTagsResponseModel {
+    name: String,
+}
+
+/// Ollama detection API call response body.
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponse {
+    model: String,
+    message: ApiDetectionResponseMessage,
+    done: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponseMessage {
+    content: String,
+    thinking: Option<String>,
+}
+
+/// Parses the user-given endpoint into a base URL that API paths can be
+/// joined onto.
+fn base_url_of(endpoint: &str) -> Result<Url, ApiKeyCheckError> {
+    let endpoint = match endpoint.trim() {
+        "" => DEFAULT_ENDPOINT,
+        endpoint => endpoint,
+    };
+    let mut base_url = Url::parse(endpoint)
+        .map_err(|err| ApiKeyCheckError::config(format!("invalid endpoint URL: {}", err)))?;
+    if base_url.scheme() != "http" && base_url.scheme() != "https" {
+        return Err(ApiKeyCheckError::config("endpoint URL must be http(s)"));
+    }
+    if !base_url.path().ends_with('/') {
+        base_url.set_path(&format!("{}/", base_url.path()));
+    }
+    Ok(base_url)
+}
+
+/// Explains a request that never got a response. Browsers do not tell apart a
+/// server that is down from one that rejects this page's origin, so both
+/// causes are mentioned.
+fn unreachable_msg(base_url: &Url, err: reqwest::Error) -> String {
+    format!(
+        "Ollama server at {} is unreachable; make sure it is running and that \
+         OLLAMA_ORIGINS allows this page's origin ({})",
+        base_url.as_str().trim_end_matches('/'),
+        err
+    )
+}
+
+/// Lists the names of models installed on the Ollama server at the given
+/// endpoint.
+pub(crate) async fn list_installed_models(endpoint: &str) -> Result<Vec<String>, ApiKeyCheckError> {
+    let base_url = base_url_of(endpoint)?;
+    list_models_at(&ApiTransport::default(), &Client::new(), &base_url).await
+}
+
+async fn list_models_at(
+    transport: &ApiTransport,
+    client: &Client,
+    base_url: &Url,
+) -> Result<Vec<String>, ApiKeyCheckError> {
+    let response = client
+        .get(
+            transport.url(
+                base_url
+                    .join("api/tags")
+                    .map_err(ApiKeyCheckError::config)?,
+            ),
+        )
+        .send()
+        .await
+        .map_err(|err| ApiKeyCheckError::config(unreachable_msg(base_url, err)))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await?;
+        return Err(ApiKeyCheckError::status(format!(
+            "listing models failed with {}: {}",
+            status, text
+        )));
+    }
+
+    let resp = response.json::<ApiTagsResponse>().await?;
+    Ok(resp.models.into_iter().map(|model| model.name).collect())
+}
+
+impl ApiClient {
+    /// Creates a new Ollama API client for the endpoint and model given in
+    /// `conf`. Uses the first installed model if none is given. Only successful
+    /// if the server is reachable and the model is installed on it.
+    pub(crate) async fn new(
+        conf: &ApiEndpointConf,
+        gen: GenerationConf,
+        transport: ApiTransport,
+    ) -> Result<Self, ApiKeyCheckError> {
+        log::debug!("Choosing the Ollama API...");
+
+        let base_url = base_url_of(&conf.endpoint)?;
+        let client = Client::new();
+
+        let installed = list_models_at(&transport, &client, &base_url).await?;
+        let model = match conf.model.trim() {
+            "" => installed.first().cloned().ok_or_else(|| {
+                ApiKeyCheckError::config(
+                    "no models installed on the Ollama server, run e.g. `ollama pull llama3.1` first",
+                )
+            })?,
+            model => {
+                // a bare name refers to the "latest" tag
+                let tagged = if model.contains(':') {
+                    model.to_string()
+                } else {
+                    format!("{}:latest", model)
+                };
+                if !installed.contains(&tagged) {
+                    return Err(ApiKeyCheckError::config(format!(
+                        "model '{}' is not pulled yet, run `ollama pull {}` first (installed: {})",
+                        model,
+                        model,
+                        if installed.is_empty() {
+                            "none".to_string()
+                        } else {
+                            installed.join(", ")
+                        }
+                    )));
+                }
+                tagged
+            }
+        };
+        log::info!("Using Ollama model '{}'", model);
+
+        Ok(Self {
+            base_url,
+            model,
+            thinking: conf.thinking,
+            gen,
+            transport,
+            client,
+        })
+    }
+
+    /// Returns the model detection calls are made with.
+    #[inline]
+    pub(crate) fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Returns the generation settings detection calls are made with.
+    #[inline]
+    pub(crate) fn generation(&self) -> GenerationConf {
+        self.gen
+    }
+
+    /// Makes an detection API call and returns the response.
+    pub(crate) async fn call(&self, prompt: String) -> Result<(u8, String), ApiMakeCallError> {
+        log::debug!("Making API call to Ollama...");
+
+        let mut num_predict = self.gen.max_tokens_or(MAX_OUTPUT_TOKENS);
+        if self.thinking || is_reasoning_model(&self.model) {
+            num_predict += THINKING_BUDGET_TOKENS;
+        }
+        let mut request = serde_json::json!({
+            "model": self.model,
+            "messages": [{
+                "role": "user",
+                "content": prompt
+            }],
+            "format": "json",
+            "stream": false,
+            "options": {
+                "num_predict": num_predict,
+            }
+        });
+        if let Some(temp) = self.gen.temperature {
+            request["options"]["temperature"] = temp.into();
+        }
+        if let Some(seed) = self.gen.seed {
+            request["options"]["seed"] = seed.into();
+        }
+        if self.thinking {
+            // thinking-capable models then return it in its own field
+            request["think"] = true.into();
+        }
+
+        let response = self
+            .client
+            .post(
+                self.transport.url(
+                    self.base_url
+                        .join("api/chat")
+                        .map_err(ApiMakeCallError::parse)?,
+                ),
+            )
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            // probably model unloaded or server overloaded
+            Err(ApiMakeCallError::from_response(response).await)
+        } else {
+            // successful
+            let resp = response.json::<ApiDetectionResponse>().await?;
+            GenericApiClient::output_parse_reply(
+                resp.message.content,
+                resp.message.thinking,
+                self.thinking,
+            )
+        }
+    }
+}
@@ -2,8 +2,6 @@
 //!
 //! Reference: https://platform.openai.com/docs/api-reference
 
-use std::mem;
-
 use const_format::concatcp;
 
 use serde::{Deserialize, Serialize};
@@ -11,18 +9,21 @@ use serde::{Deserialize, Serialize};
 use reqwest::header::CONTENT_TYPE;
 use reqwest::Client;
 
-use crate::apis::ApiClient as GenericApiClient;
+use crate::apis::{
+    is_reasoning_model, ApiClient as GenericApiClient, ApiEndpointConf, ApiKeyPool, ApiTransport,
+    GenerationConf, THINKING_BUDGET_TOKENS,
+};
 use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
 
 /// OpenAI API request URL prefix.
 const OPENAI_API_PREFIX: &str = "https://api.openai.com/v1";
 
 /// OpenAI default model name.
-const OPENAI_MODEL_NAME: &str = "gpt-4o";
+pub(crate) const OPENAI_MODEL_NAME: &str = "gpt-4o";
 
-/// API key validity check request URL.
+/// API key validity check request URL prefix, to be followed by the model name.
 /// Accompolished with the model information URL.
-const CHECK_API_KEY_URL: &str = concatcp!(OPENAI_API_PREFIX, "/models/", OPENAI_MODEL_NAME);
+const CHECK_API_KEY_URL: &str = concatcp!(OPENAI_API_PREFIX, "/models/");
 
 /// API chat completion request URL.
 const CHAT_COMPLETION_URL: &str = concatcp!(OPENAI_API_PREFIX, "/chat/completions");
@@ -30,9 +31,49 @@ const CHAT_COMPLETION_URL: &str = concatcp!(OPENAI_API_PREFIX, "/chat/completion
 /// Max output tokens cap.
 const MAX_OUTPUT_TOKENS: u32 = 500;
 
+/// Sets the completion tokens cap of a chat completion request, given the
+/// adapter's default. Hidden reasoning tokens count towards the cap, so
+/// reasoning models get the thinking budget on top. Shared with Azure OpenAI.
+pub(crate) fn set_completion_tokens(
+    request: &mut serde_json::Value,
+    gen: &GenerationConf,
+    default: u32,
+    reasoning: bool,
+) {
+    let mut max_tokens = gen.max_tokens_or(default);
+    if reasoning {
+        max_tokens += THINKING_BUDGET_TOKENS;
+    }
+    request["max_completion_tokens"] = max_tokens.into();
+}
+
+/// Checks why a chat completion choice finished, returning an error if its
+/// output was cut off with nothing left after reasoning, or withheld by the
+/// content filter. Shared with Azure OpenAI.
+pub(crate) fn check_finish_reason(
+    finish_reason: &str,
+    content: Option<&str>,
+) -> Result<(), ApiMakeCallError> {
+    if finish_reason == "length" && content.is_none_or(str::is_empty) {
+        return Err(ApiMakeCallError::truncated(
+            "reasoning used up the whole output tokens budget",
+        ));
+    }
+    if finish_reason == "content_filter" {
+        return Err(ApiMakeCallError::content_filter(
+            "output withheld by the content filter",
+        ));
+    }
+    Ok(())
+}
+
 /// OpenAI API client.
 pub(crate) struct ApiClient {
-    api_key: String,
+    api_keys: ApiKeyPool,
+    model: String,
+    reasoning: bool,
+    gen: GenerationConf,
+    transport: ApiTransport,
     client: Client,
 }
 
@@ -59,15 +100,29 @@ struct ApiDetectionResponseChoice {
 
 #[derive(Serialize, Deserialize, Debug)]
 struct ApiDetectionResponseMessage {
-    content: String,
+    content: Option<String>, // null when withheld
 }
 
 impl ApiClient {
-    /// Creates a new OpenAI API client. Only successful if passes the API key validity check.
-    pub(crate) async fn new(api_key: Option<String>) -> Result<Self, ApiKeyCheckError> {
-        let client = if let Some(api_key) = api_key {
+    /// Creates a new OpenAI API client, using the model given in `conf` if any.
+    /// Only successful if passes the API key validity check.
+    pub(crate) async fn new(
+        api_keys: Option<Vec<String>>,
+        conf: &ApiEndpointConf,
+        gen: GenerationConf,
+        transport: ApiTransport,
+    ) -> Result<Self, ApiKeyCheckError> {
+        let model = match conf.model.trim() {
+            "" => OPENAI_MODEL_NAME.to_string(),
+            model => model.to_string(),
+        };
+        let client = if let Some(api_keys) = api_keys {
             Self {
-                api_key,
+                api_keys: ApiKeyPool::new(api_keys),
+                reasoning: is_reasoning_model(&model),
+                model,
+                gen,
+                transport,
                 client: Client::new(),
             }
         } else {
@@ -76,18 +131,23 @@ impl ApiClient {
             ));
         };
 
-        client.check_api_key().await?;
+        for api_key in client.api_keys.keys() {
+            client.check_api_key(api_key).await?;
+        }
         Ok(client)
     }
 
     /// Makes an API key validity check request and returns an error if unsuccessful.
-    async fn check_api_key(&self) -> Result<(), ApiKeyCheckError> {
+    async fn check_api_key(&self, api_key: &str) -> Result<(), ApiKeyCheckError> {
         log::debug!("Choosing the OpenAI API...");
 
         let response = self
             .client
-            .get(CHECK_API_KEY_URL)
-            .bearer_auth(self.api_key.clone())
+            .get(
+                self.transport
+                    .url(format!("{}{}", CHECK_API_KEY_URL, self.model)),
+            )
+            .bearer_auth(api_key)
             .send()
             .await?;
 
@@ -102,7 +162,7 @@ impl ApiClient {
         } else {
             // successful (quota not guaranteed)
             let resp = response.json::<ApiKeyCheckResponse>().await?;
-            if resp.id != OPENAI_MODEL_NAME {
+            if resp.id != self.model {
                 return Err(ApiKeyCheckError::status(format!(
                     "API key validation successful, but unexpected model name: {}",
                     resp.id
@@ -113,23 +173,44 @@ impl ApiClient {
         Ok(())
     }
 
+    /// Returns the model detection calls are made with.
+    #[inline]
+    pub(crate) fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Returns the generation settings detection calls are made with.
+    #[inline]
+    pub(crate) fn generation(&self) -> GenerationConf {
+        self.gen.openai_style(self.reasoning)
+    }
+
     /// Makes an detection API call and returns the response.
     pub(crate) async fn call(&self, prompt: String) -> Result<(u8, String), ApiMakeCallError> {
         log::debug!("Making API call to OpenAI...");
 
-        let request = serde_json::json!({
-            "model": OPENAI_MODEL_NAME,
+        let api_key = self
+            .api_keys
+            .pick()
+            .ok_or_else(|| ApiMakeCallError::rate_limit("all API keys are temporarily retired"))?;
+
+        let mut request = serde_json::json!({
+            "model": self.model,
             "messages": [{
                 "role": "user",
                 "content": prompt
             }],
-            "max_completion_tokens": MAX_OUTPUT_TOKENS,
         });
+        set_completion_tokens(&mut request, &self.gen, MAX_OUTPUT_TOKENS, self.reasoning);
+        self.gen.apply_openai_style(&mut request, self.reasoning);
+        if self.reasoning {
+            request["reasoning_effort"] = "low".into();
+        }
 
         let response = self
             .client
-            .post(CHAT_COMPLETION_URL)
-            .bearer_auth(self.api_key.clone())
+            .post(self.transport.url(CHAT_COMPLETION_URL))
+            .bearer_auth(api_key)
             .header(CONTENT_TYPE, "application/json")
             .json(&request)
             .send()
@@ -137,19 +218,20 @@ impl ApiClient {
 
         if !response.status().is_success() {
             // probably network error or rate limited
-            let status = response.status();
-            let text = response.text().await?;
-            Err(ApiMakeCallError::status(format!(
-                "API call failed with {}: {}",
-                status, text
-            )))
+            let err = ApiMakeCallError::from_response(response).await;
+            self.api_keys.retire_if_exhausted(api_key, &err);
+            Err(err)
         } else {
             // successful
             let mut resp = response.json::<ApiDetectionResponse>().await?;
             if resp.choices.is_empty() {
                 return Err(ApiMakeCallError::parse("no choices found in response"));
             }
-            let output = mem::take(&mut resp.choices[0].message.content);
+            check_finish_reason(
+                &resp.choices[0].finish_reason,
+                resp.choices[0].message.content.as_deref(),
+            )?;
+            let output = resp.choices[0].message.content.take().unwrap_or_default();
             GenericApiClient::output_parse_pair(output)
         }
     }
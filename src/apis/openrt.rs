@@ -2,8 +2,6 @@
 //!
 //! Reference: https://openrouter.ai/docs/api-reference/overview
 
-use std::mem;
-
 use const_format::concatcp;
 
 use serde::{Deserialize, Serialize};
@@ -14,7 +12,10 @@ use reqwest::Client;
 
 use base64::prelude::*;
 
-use crate::apis::ApiClient as GenericApiClient;
+use crate::apis::{
+    is_reasoning_model, ApiClient as GenericApiClient, ApiEndpointConf, ApiKeyPool, ApiTransport,
+    GenerationConf, THINKING_BUDGET_TOKENS,
+};
 use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
 
 /// OpenRouter API request URL prefix.
@@ -23,7 +24,7 @@ const OPENRT_API_PREFIX: &str = "https://openrouter.ai/api/v1";
 /// OpenRouter model choice. Not using `openrouter/auto` to auto select because
 /// sometimes it would pick a deep reasoning model that would likely disregard
 /// the structured JSON output instructions.
-const OPENRT_MODEL_NAME: &str = "mistralai/mistral-large";
+pub(crate) const OPENRT_MODEL_NAME: &str = "mistralai/mistral-large";
 
 /// API key validity check request URL.
 /// Accompolished with the rate/credit limit checking API.
@@ -41,7 +42,12 @@ const MAX_OUTPUT_TOKENS: u32 = 500;
 
 /// OpenRouter API client.
 pub(crate) struct ApiClient {
-    api_key: String,
+    api_keys: ApiKeyPool,
+    model: String,
+    reasoning: bool,
+    thinking: bool,
+    gen: GenerationConf,
+    transport: ApiTransport,
     client: Client,
 }
 
@@ -69,39 +75,58 @@ struct ApiDetectionResponse {
 #[derive(Serialize, Deserialize, Debug)]
 struct ApiDetectionResponseChoice {
     message: ApiDetectionResponseMessage,
+    finish_reason: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct ApiDetectionResponseMessage {
-    content: String,
+    content: Option<String>, // null when withheld
+    reasoning: Option<String>,
 }
 
 impl ApiClient {
-    /// Creates a new OpenRouter API client. Only successful if passes the API key validity check.
+    /// Creates a new OpenRouter API client, using the model given in `conf` if
+    /// any. Only successful if passes the API key validity check.
     /// Uses the default free quota API KEY if input key is `None`.
-    pub(crate) async fn new(api_key: Option<String>) -> Result<Self, ApiKeyCheckError> {
+    pub(crate) async fn new(
+        api_keys: Option<Vec<String>>,
+        conf: &ApiEndpointConf,
+        gen: GenerationConf,
+        transport: ApiTransport,
+    ) -> Result<Self, ApiKeyCheckError> {
+        let model = match conf.model.trim() {
+            "" => OPENRT_MODEL_NAME.to_string(),
+            model => model.to_string(),
+        };
         let client = Self {
-            api_key: api_key.unwrap_or_else(|| {
+            api_keys: ApiKeyPool::new(api_keys.unwrap_or_else(|| {
                 let decoded = BASE64_STANDARD
                     .decode(&FREE_QUOTA_API_KEY[3..FREE_QUOTA_API_KEY.len() - 3])
                     .expect("Failed to do base64 decoding");
-                String::from_utf8(decoded).expect("API key is not a valid UTF-8 string")
-            }),
+                vec![String::from_utf8(decoded).expect("API key is not a valid UTF-8 string")]
+            })),
+            reasoning: is_reasoning_model(&model),
+            model,
+            thinking: conf.thinking,
+            gen,
+            transport,
             client: Client::new(),
         };
 
-        client.check_api_key().await?;
+        for api_key in client.api_keys.keys() {
+            client.check_api_key(api_key).await?;
+        }
         Ok(client)
     }
 
     /// Makes an API key validity check request and returns an error if unsuccessful.
-    async fn check_api_key(&self) -> Result<(), ApiKeyCheckError> {
+    async fn check_api_key(&self, api_key: &str) -> Result<(), ApiKeyCheckError> {
         log::debug!("Choosing the OpenRouter API...");
 
         let response = self
             .client
-            .get(CHECK_API_KEY_URL)
-            .bearer_auth(self.api_key.clone())
+            .get(self.transport.url(CHECK_API_KEY_URL))
+            .bearer_auth(api_key)
             .send()
             .await?;
 
@@ -140,23 +165,49 @@ impl ApiClient {
         Ok(())
     }
 
+    /// Returns the model detection calls are made with.
+    #[inline]
+    pub(crate) fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Returns the generation settings detection calls are made with.
+    #[inline]
+    pub(crate) fn generation(&self) -> GenerationConf {
+        self.gen.openai_style(self.reasoning)
+    }
+
     /// Makes an detection API call and returns the response.
     pub(crate) async fn call(&self, prompt: String) -> Result<(u8, String), ApiMakeCallError> {
         log::debug!("Making API call to OpenRouter...");
 
-        let request = serde_json::json!({
-            "model": OPENRT_MODEL_NAME,
+        let api_key = self
+            .api_keys
+            .pick()
+            .ok_or_else(|| ApiMakeCallError::rate_limit("all API keys are temporarily retired"))?;
+
+        let mut request = serde_json::json!({
+            "model": self.model,
             "messages": [{
                 "role": "user",
                 "content": prompt
             }],
-            "max_tokens": MAX_OUTPUT_TOKENS,
+            "max_tokens": self.gen.max_tokens_or(MAX_OUTPUT_TOKENS),
         });
+        self.gen.apply_openai_style(&mut request, self.reasoning);
+        if self.reasoning || self.thinking {
+            request["max_tokens"] =
+                (self.gen.max_tokens_or(MAX_OUTPUT_TOKENS) + THINKING_BUDGET_TOKENS).into();
+            request["reasoning"] = serde_json::json!({
+                "max_tokens": THINKING_BUDGET_TOKENS,
+                "exclude": !self.thinking,
+            });
+        }
 
         let response = self
             .client
-            .post(CHAT_COMPLETION_URL)
-            .bearer_auth(self.api_key.clone())
+            .post(self.transport.url(CHAT_COMPLETION_URL))
+            .bearer_auth(api_key)
             .header(CONTENT_TYPE, "application/json")
             .json(&request)
             .send()
@@ -164,20 +215,23 @@ impl ApiClient {
 
         if !response.status().is_success() {
             // probably network error or rate limited
-            let status = response.status();
-            let text = response.text().await?;
-            Err(ApiMakeCallError::status(format!(
-                "API call failed with {}: {}",
-                status, text
-            )))
+            let err = ApiMakeCallError::from_response(response).await;
+            self.api_keys.retire_if_exhausted(api_key, &err);
+            Err(err)
         } else {
             // successful
             let mut resp = response.json::<ApiDetectionResponse>().await?;
             if resp.choices.is_empty() {
                 return Err(ApiMakeCallError::parse("no choices found in response"));
             }
-            let output = mem::take(&mut resp.choices[0].message.content);
-            GenericApiClient::output_parse_pair(output)
+            if resp.choices[0].finish_reason.as_deref() == Some("content_filter") {
+                return Err(ApiMakeCallError::content_filter(
+                    "output withheld by the content filter",
+                ));
+            }
+            let output = resp.choices[0].message.content.take().unwrap_or_default();
+            let thinking = resp.choices[0].message.reasoning.take();
+            GenericApiClient::output_parse_reply(output, thinking, self.thinking)
         }
     }
 }
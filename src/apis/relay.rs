@@ -0,0 +1,167 @@
+//! API adapter for a self-hosted Codetective relay server (see `relay/`), which
+//! holds provider keys server-side and enforces per-user quotas.
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use reqwest::Client;
+
+use crate::apis::{ApiClient as GenericApiClient, ApiEndpointConf, ApiTransport, GenerationConf};
+use crate::utils::error::{ApiKeyCheckError, ApiMakeCallError};
+
+/// Relay URL baked in at build time, for deployments that come with a shared
+/// relay instance.
+pub(crate) const DEFAULT_ENDPOINT: Option<&str> = option_env!("CODETECTIVE_RELAY_URL");
+
+/// Relay API client.
+pub(crate) struct ApiClient {
+    endpoint: String,
+    access_token: Option<String>,
+    gen: GenerationConf,
+    models_used: Mutex<Vec<String>>,
+    transport: ApiTransport,
+    client: Client,
+}
+
+/// Relay status response body.
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiKeyCheckResponse {
+    providers: Vec<String>,
+    quota: u32,
+    remaining: u32,
+    window_secs: u64,
+}
+
+/// Relay detection API call request body. The relay builds the prompt itself.
+#[derive(Serialize, Debug)]
+struct ApiDetectionRequest<'a> {
+    code: &'a str,
+    #[serde(flatten)]
+    generation: GenerationConf,
+}
+
+/// Relay detection API call response body.
+#[derive(Serialize, Deserialize, Debug)]
+struct ApiDetectionResponse {
+    output: String,
+    provider: String,
+    model: String,
+}
+
+impl ApiClient {
+    /// Creates a new relay API client for the relay at the endpoint given in
+    /// `conf`, or the build-time default. Only successful if the relay accepts
+    /// the access token, if any, and has quota left for this user.
+    pub(crate) async fn new(
+        access_token: Option<String>,
+        conf: &ApiEndpointConf,
+        gen: GenerationConf,
+        transport: ApiTransport,
+    ) -> Result<Self, ApiKeyCheckError> {
+        let endpoint = match conf.endpoint.trim() {
+            "" => DEFAULT_ENDPOINT
+                .ok_or_else(|| ApiKeyCheckError::config("relay URL is required"))?
+                .to_string(),
+            endpoint => endpoint.to_string(),
+        };
+        let client = Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            access_token,
+            gen,
+            models_used: Mutex::new(Vec::new()),
+            transport,
+            client: Client::new(),
+        };
+
+        client.check_api_key().await?;
+        Ok(client)
+    }
+
+    /// Fetches the relay status and returns an error if unsuccessful or if
+    /// this user's quota is used up.
+    async fn check_api_key(&self) -> Result<(), ApiKeyCheckError> {
+        log::debug!("Choosing the relay API...");
+
+        let mut request = self
+            .client
+            .get(self.transport.url(format!("{}/v1/status", self.endpoint)));
+        if let Some(token) = &self.access_token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            // probably network error or unknown access token
+            let status = response.status();
+            let text = response.text().await?;
+            return Err(ApiKeyCheckError::status(format!(
+                "relay check failed with {}: {}",
+                status, text
+            )));
+        } else {
+            let resp = response.json::<ApiKeyCheckResponse>().await?;
+            if resp.remaining == 0 {
+                return Err(ApiKeyCheckError::limit(format!(
+                    "relay quota of {} calls per {}s used up for now",
+                    resp.quota, resp.window_secs
+                )));
+            }
+            log::info!(
+                "Relay serves {} with {} of {} calls left",
+                resp.providers.join(", "),
+                resp.remaining,
+                resp.quota
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns the providers and models the relay has answered with so far, as
+    /// it picks one per call.
+    pub(crate) fn models_used(&self) -> Vec<String> {
+        self.models_used
+            .lock()
+            .expect("relay models lock poisoned")
+            .clone()
+    }
+
+    /// Returns the generation settings detection calls are made with.
+    #[inline]
+    pub(crate) fn generation(&self) -> GenerationConf {
+        self.gen
+    }
+
+    /// Makes an detection API call and returns the response.
+    pub(crate) async fn call(&self, code: &str) -> Result<(u8, String), ApiMakeCallError> {
+        log::debug!("Making API call to relay...");
+
+        let mut request = self
+            .client
+            .post(self.transport.url(format!("{}/v1/detect", self.endpoint)))
+            .json(&ApiDetectionRequest {
+                code,
+                generation: self.gen,
+            });
+        if let Some(token) = &self.access_token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            // probably quota used up or all upstream providers busy
+            Err(ApiMakeCallError::from_response(response).await)
+        } else {
+            // successful
+            let resp = response.json::<ApiDetectionResponse>().await?;
+            log::debug!("Relay answered via {} ({})", resp.provider, resp.model);
+            let model = format!("{}/{}", resp.provider, resp.model);
+            let mut models_used = self.models_used.lock().expect("relay models lock poisoned");
+            if !models_used.contains(&model) {
+                models_used.push(model);
+            }
+            GenericApiClient::output_parse_pair(resp.output)
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! AWS Signature Version 4 request signing, as needed by the Bedrock adapter.
+//!
+//! Reference: https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_sigv-create-signed-request.html
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+/// Signing algorithm identifier.
+const SIGV4_ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// AWS credentials parsed from a single key string of the form
+/// `ACCESS_KEY_ID:SECRET_ACCESS_KEY[:SESSION_TOKEN]`.
+pub(crate) struct AwsCredentials<'a> {
+    pub(crate) access_key_id: &'a str,
+    pub(crate) secret_access_key: &'a str,
+    pub(crate) session_token: Option<&'a str>,
+}
+
+impl<'a> AwsCredentials<'a> {
+    /// Parses a key string into credentials. Returns `None` if malformed.
+    pub(crate) fn parse(key: &'a str) -> Option<Self> {
+        let mut parts = key.split(':');
+        let access_key_id = parts.next().filter(|p| !p.is_empty())?;
+        let secret_access_key = parts.next().filter(|p| !p.is_empty())?;
+        let session_token = parts.next().filter(|p| !p.is_empty());
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(AwsCredentials {
+            access_key_id,
+            secret_access_key,
+            session_token,
+        })
+    }
+}
+
+/// Header values to attach to a signed request. The `host` header is signed
+/// but not included here, as it is always set by the browser itself.
+pub(crate) struct SignedHeaders {
+    pub(crate) amz_date: String,
+    pub(crate) authorization: String,
+    pub(crate) security_token: Option<String>,
+}
+
+/// A request to be signed. `path` must already be URI-encoded, exactly as it
+/// is going to be sent; query strings are not supported.
+pub(crate) struct SigningRequest<'a> {
+    pub(crate) method: &'a str,
+    pub(crate) host: &'a str,
+    pub(crate) path: &'a str,
+    pub(crate) payload: &'a [u8],
+    pub(crate) region: &'a str,
+    pub(crate) service: &'a str,
+}
+
+/// URI-encodes a string as specified by SigV4: everything except unreserved
+/// characters is percent-encoded with uppercase hex digits.
+pub(crate) fn uri_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Converts a UTC time given in the extended ISO 8601 format of JavaScript's
+/// `Date.toISOString()`, e.g. `2025-01-01T12:00:00.000Z`, into the basic
+/// format SigV4 expects, e.g. `20250101T120000Z`.
+pub(crate) fn amz_date(iso: &str) -> String {
+    let mut amz_date: String = iso
+        .chars()
+        .take(19)
+        .filter(|c| *c != '-' && *c != ':')
+        .collect();
+    amz_date.push('Z');
+    amz_date
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex_string(&Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Signs the request with the credentials at the given `amz_date` timestamp.
+pub(crate) fn sign_request(
+    creds: &AwsCredentials,
+    request: &SigningRequest,
+    amz_date: &str,
+) -> SignedHeaders {
+    let date = &amz_date[..8];
+    let scope = format!(
+        "{}/{}/{}/aws4_request",
+        date, request.region, request.service
+    );
+
+    // non-S3 services expect each path segment to be encoded once more
+    let canonical_uri = request
+        .path
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let mut canonical_headers = format!("host:{}\nx-amz-date:{}\n", request.host, amz_date);
+    let mut signed_headers = "host;x-amz-date".to_string();
+    if let Some(token) = creds.session_token {
+        canonical_headers.push_str(&format!("x-amz-security-token:{}\n", token));
+        signed_headers.push_str(";x-amz-security-token");
+    }
+
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        request.method,
+        canonical_uri,
+        canonical_headers,
+        signed_headers,
+        sha256_hex(request.payload)
+    );
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        SIGV4_ALGORITHM,
+        amz_date,
+        scope,
+        sha256_hex(canonical_request.as_bytes())
+    );
+
+    let k_date = hmac_sha256(
+        format!("AWS4{}", creds.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let k_region = hmac_sha256(&k_date, request.region.as_bytes());
+    let k_service = hmac_sha256(&k_region, request.service.as_bytes());
+    let k_signing = hmac_sha256(&k_service, b"aws4_request");
+    let signature = hex_string(&hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+    SignedHeaders {
+        amz_date: amz_date.to_string(),
+        authorization: format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            SIGV4_ALGORITHM, creds.access_key_id, scope, signed_headers, signature
+        ),
+        security_token: creds.session_token.map(|token| token.to_string()),
+    }
+}
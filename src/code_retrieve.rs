@@ -7,10 +7,11 @@ use reqwest::Client as CgfClient;
 
 use web_sys::DragEvent;
 
+use gloo_file::futures::read_as_text;
 use gloo_file::FileList;
 use gloo_timers::future::TimeoutFuture;
 
-use crate::file::{CodeGroup, MAX_FILE_SIZE, MAX_NUM_FILES};
+use crate::file::{parse_source_list, CodeGroup, RemoteConf, MAX_FILE_SIZE, MAX_NUM_FILES};
 use crate::utils::error::CodeImportError;
 use crate::utils::gadgets::{
     FailureIndicator, HoverInfoIcon, InvisibleIndicator, SpinningIndicator, StepHeaderCollapsed,
@@ -54,18 +55,23 @@ fn handle_import_method_button(
     import_method.set(selected_method);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_code_url_submit(
     import_method: RwSignal<ImportMethod>,
     input_code_url: RwSignal<String>,
+    input_remote_conf: RwSignal<RemoteConf>,
+    import_progress: RwSignal<usize>,
+    import_source: RwSignal<usize>,
     code_in_vstate: RwSignal<ValidationState<CodeImportError>>,
     cgf_client: RwSignal<CgfClient>,
     code_group: RwSignal<CodeGroup>,
     stage: RwSignal<StepStage>,
 ) {
     let current_import_method = import_method.get();
-    let code_url = input_code_url.read().trim().to_string();
+    let code_urls = parse_source_list(&input_code_url.read());
+    let remote_conf = input_remote_conf.get();
 
-    if code_url.is_empty() || !code_url.is_ascii() {
+    if code_urls.is_empty() || code_urls.iter().any(|code_url| !code_url.is_ascii()) {
         log::warn!("Code URL input field is empty or non-ASCII, please try again...");
         code_in_vstate.set(ValidationState::Failure(CodeImportError::ascii(
             "code URL input is empty or non-ASCII",
@@ -74,16 +80,32 @@ fn handle_code_url_submit(
     }
 
     code_in_vstate.set(ValidationState::Pending);
+    import_source.set(0);
 
     spawn_local(async move {
         log::info!(
             "Step 2 validating: importing from {} '{}'...",
             current_import_method.name(),
-            code_url
+            code_urls.join("', '")
         );
 
         let mut code_group_inner = code_group.write();
-        match code_group_inner.import_remote(cgf_client, &code_url).await {
+        let result = if let [code_url] = code_urls.as_slice() {
+            code_group_inner
+                .import_remote(cgf_client, code_url, &remote_conf, import_progress)
+                .await
+        } else {
+            code_group_inner
+                .import_batch(
+                    cgf_client,
+                    &code_urls,
+                    &remote_conf,
+                    import_progress,
+                    import_source,
+                )
+                .await
+        };
+        match result {
             Ok(()) => {
                 code_in_vstate.set(ValidationState::Success);
 
@@ -110,6 +132,39 @@ fn handle_code_url_submit(
     });
 }
 
+fn handle_url_list_upload(
+    files: FileList,
+    input_code_url: RwSignal<String>,
+    code_in_vstate: RwSignal<ValidationState<CodeImportError>>,
+) {
+    if files.len() != 1 {
+        return;
+    }
+    let file = files[0].clone();
+
+    spawn_local(async move {
+        match read_as_text(&file).await {
+            Ok(text) => {
+                let code_urls = parse_source_list(&text);
+                log::info!(
+                    "Loaded {} URL(s) from list file '{}'",
+                    code_urls.len(),
+                    file.name()
+                );
+                input_code_url.set(code_urls.join("\n"));
+                code_in_vstate.set(ValidationState::Idle);
+            }
+            Err(err) => {
+                log::error!("URL list file read failed: {}", err);
+                code_in_vstate.set(ValidationState::Failure(CodeImportError::upload(format!(
+                    "failed to read URL list file: {}",
+                    err
+                ))));
+            }
+        }
+    });
+}
+
 fn handle_code_text_submit(
     import_method: RwSignal<ImportMethod>,
     input_code_text: RwSignal<String>,
@@ -277,6 +332,11 @@ fn ValidationErrorMsg(code_in_vstate: RwSignal<ValidationState<CodeImportError>>
                             CodeImportError::Limit(msg) => &msg,
                             CodeImportError::Ascii(_) => "please provide a legit input source...",
                             CodeImportError::GitHub(msg) => &msg,
+                            CodeImportError::GitLab(msg) => &msg,
+                            CodeImportError::Gitea(msg) => &msg,
+                            CodeImportError::Bitbucket(msg) => &msg,
+                            CodeImportError::Registry(msg) => &msg,
+                            CodeImportError::Batch(msg) => &msg,
                             CodeImportError::Upload(msg) => &msg,
                         },
                     )}
@@ -292,35 +352,44 @@ fn ValidationErrorMsg(code_in_vstate: RwSignal<ValidationState<CodeImportError>>
 fn ImportFromUrlToSection(
     import_method: RwSignal<ImportMethod>,
     input_code_url: RwSignal<String>,
+    input_remote_conf: RwSignal<RemoteConf>,
     code_in_vstate: RwSignal<ValidationState<CodeImportError>>,
     cgf_client: RwSignal<CgfClient>,
     code_group: RwSignal<CodeGroup>,
     stage: RwSignal<StepStage>,
     placeholder: &'static str,
 ) -> impl IntoView {
+    let import_progress = RwSignal::new(0);
+    let import_source = RwSignal::new(0);
+    let list_input_ref = NodeRef::new();
+
     view! {
         <div class="pt-6 pb-2 px-2 animate-slide-down origin-top">
             <div class="flex items-center justify-center space-x-4">
                 <label for="code-url" class="text-base text-gray-900 whitespace-nowrap">
-                    Enter URL:
+                    Enter URL(s):
                 </label>
-                <input
-                    type="url"
+                <textarea
                     id="code-url"
                     placeholder=placeholder
+                    rows=move || input_code_url.read().lines().count().clamp(1, 6)
                     prop:value=move || input_code_url.get()
                     prop:disabled=move || code_in_vstate.get() == ValidationState::Pending
                     on:input=move |ev| {
                         input_code_url.set(event_target_value(&ev));
                     }
                     on:keydown=move |ev| {
-                        if ev.key_code() != 0 && ev.key() == "Enter"
+                        if ev.key_code() != 0 && ev.key() == "Enter" && !ev.shift_key()
                             && code_in_vstate.get() != ValidationState::Pending
                             && code_in_vstate.get() != ValidationState::Success
                         {
+                            ev.prevent_default();
                             handle_code_url_submit(
                                 import_method,
                                 input_code_url,
+                                input_remote_conf,
+                                import_progress,
+                                import_source,
                                 code_in_vstate,
                                 cgf_client,
                                 code_group,
@@ -328,10 +397,10 @@ fn ImportFromUrlToSection(
                             );
                         }
                     }
-                    class="flex-1 p-2 max-w-xl border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
-                />
+                    class="flex-1 p-2 max-w-xl border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono resize-none"
+                ></textarea>
 
-                <HoverInfoIcon text="A URL link to a raw online file or a GitHub repository. Size per file limited to 100KB. Number of files (if repo) capped to 100 (but may improve later)." />
+                <HoverInfoIcon text="A URL link to a raw online file, a .zip/.tar/.tar.gz/.7z archive (up to 64MB), a GitHub Gist, or a GitHub/GitLab/Codeberg/Gitea/Bitbucket repository. GitHub directory (/tree/...), file (/blob/...), pull request (/pull/...), commit (/commit/...), and compare (/compare/...) links copied from the address bar also work; commits and comparisons are judged by their added lines only. Published packages can be given as crates:name, pypi:name, or npm:name, optionally followed by @version, to import their released source archive. Size per file limited to 100KB. Number of files (if repo) capped to 100 (but may improve later). Several sources can be entered one per line (Shift+Enter for a new line), or loaded from a .txt/.csv file, to import up to 50 of them together, each under its own path prefix; sources that fail to import are reported and skipped." />
 
                 <button
                     on:click=move |_| {
@@ -341,6 +410,9 @@ fn ImportFromUrlToSection(
                             handle_code_url_submit(
                                 import_method,
                                 input_code_url,
+                                input_remote_conf,
+                                import_progress,
+                                import_source,
                                 code_in_vstate,
                                 cgf_client,
                                 code_group,
@@ -365,6 +437,153 @@ fn ImportFromUrlToSection(
                 <ValidationIndicator code_in_vstate />
             </div>
 
+            {move || {
+                (code_in_vstate.get() == ValidationState::Pending && import_progress.get() > 0)
+                    .then(|| {
+                        view! {
+                            <div class="text-gray-600 text-base font-mono mt-4 text-center animate-fade-in">
+                                Listing repo... {NBSP} {move || import_progress.get()} {NBSP}
+                                code file(s) found so far
+                            </div>
+                        }
+                    })
+            }}
+            {move || {
+                (code_in_vstate.get() == ValidationState::Pending && import_source.get() > 0)
+                    .then(|| {
+                        view! {
+                            <div class="text-gray-600 text-base font-mono mt-4 text-center animate-fade-in">
+                                Importing source {NBSP} {move || import_source.get()} {NBSP}/{NBSP}
+                                {move || parse_source_list(&input_code_url.read()).len()}
+                                {NBSP}...
+                            </div>
+                        }
+                    })
+            }}
+
+            <div class="flex items-center justify-center space-x-4 mt-4">
+                <label for="url-list" class="text-base text-gray-900 whitespace-nowrap">
+                    URL List File:
+                </label>
+                <input
+                    type="file"
+                    id="url-list"
+                    accept=".txt,.csv,text/plain,text/csv"
+                    node_ref=list_input_ref
+                    prop:disabled=move || code_in_vstate.get() == ValidationState::Pending
+                    on:change=move |_| {
+                        if let Some(input) = list_input_ref.get() {
+                            if let Some(file_list) = input.files() {
+                                handle_url_list_upload(
+                                    file_list.into(),
+                                    input_code_url,
+                                    code_in_vstate,
+                                );
+                            }
+                        }
+                    }
+                    class="flex-1 max-w-md text-base text-gray-700 file:mr-4 file:px-4 file:py-2 file:rounded-md file:border-0 file:bg-gray-500 file:text-white hover:file:bg-gray-600"
+                />
+
+                <HoverInfoIcon text="A .txt file of URLs one per line, or a .csv file (e.g. exported from a course roster) whose fields that look like URLs or package identifiers are taken. The list is loaded into the URL box above for review before confirming. Up to 50 sources per import." />
+            </div>
+
+            <div class="flex items-center justify-center space-x-4 mt-4">
+                <label for="gitlab-hosts" class="text-base text-gray-900 whitespace-nowrap">
+                    GitLab Hosts:
+                </label>
+                <input
+                    type="text"
+                    id="gitlab-hosts"
+                    placeholder="gitlab.example.edu (optional)"
+                    prop:value=move || input_remote_conf.read().gitlab_hosts.clone()
+                    prop:disabled=move || code_in_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_remote_conf
+                            .update(|conf| conf.gitlab_hosts = event_target_value(&ev));
+                    }
+                    class="flex-1 p-2 max-w-md border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <HoverInfoIcon text="Hosts of self-hosted GitLab instances whose repository URLs should be imported as repos, separated by commas; gitlab.com is always recognized. File sizes are only checked when fetched. An instance that does not allow cross-origin requests can be reached through the CORS proxy set in Step 1." />
+            </div>
+
+            <div class="flex items-center justify-center space-x-4 mt-4">
+                <label for="gitea-hosts" class="text-base text-gray-900 whitespace-nowrap">
+                    Gitea Hosts:
+                </label>
+                <input
+                    type="text"
+                    id="gitea-hosts"
+                    placeholder="git.example.org (optional)"
+                    prop:value=move || input_remote_conf.read().gitea_hosts.clone()
+                    prop:disabled=move || code_in_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_remote_conf
+                            .update(|conf| conf.gitea_hosts = event_target_value(&ev));
+                    }
+                    class="flex-1 p-2 max-w-md border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <HoverInfoIcon text="Hosts of self-hosted Gitea or Forgejo instances whose repository URLs should be imported as repos, separated by commas; codeberg.org is always recognized, as is bitbucket.org for Bitbucket Cloud." />
+            </div>
+
+            <div class="flex items-center justify-center space-x-4 mt-4">
+                <label for="github-token" class="text-base text-gray-900 whitespace-nowrap">
+                    GitHub Token:
+                </label>
+                <input
+                    type="password"
+                    id="github-token"
+                    placeholder="github_pat_... (optional)"
+                    prop:value=move || input_remote_conf.read().github_token.clone()
+                    prop:disabled=move || code_in_vstate.get() == ValidationState::Pending
+                    on:input=move |ev| {
+                        input_remote_conf
+                            .update(|conf| conf.github_token = event_target_value(&ev));
+                    }
+                    class="flex-1 p-2 max-w-md border border-gray-300 rounded focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono"
+                />
+
+                <HoverInfoIcon text="A GitHub personal access token with read access to repository contents, needed for private repositories. Unauthenticated requests are limited to 60 per hour; with a token the limit is much higher. The token is only sent to GitHub (through the CORS proxy if one is set) and is not stored." />
+            </div>
+
+            <div class="flex items-center justify-center space-x-4 mt-4">
+                <label class="flex items-center text-base text-gray-900 whitespace-nowrap cursor-pointer">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || input_remote_conf.read().diff_added_only
+                        prop:disabled=move || code_in_vstate.get() == ValidationState::Pending
+                        on:change=move |ev| {
+                            input_remote_conf
+                                .update(|conf| conf.diff_added_only = event_target_checked(&ev));
+                        }
+                        class="mr-2"
+                    />
+                    Judge only added lines of pull requests
+                </label>
+
+                <HoverInfoIcon text="For GitHub pull request URLs (/pull/<n>), only the files changed by the PR are imported, as of its head commit. With this checked, only the lines the PR adds are taken from each file, so unchanged code is not judged. Files whose diff GitHub does not show are imported whole." />
+            </div>
+
+            <div class="flex items-center justify-center space-x-4 mt-4">
+                <label class="flex items-center text-base text-gray-900 whitespace-nowrap cursor-pointer">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || input_remote_conf.read().use_archive
+                        prop:disabled=move || code_in_vstate.get() == ValidationState::Pending
+                        on:change=move |ev| {
+                            input_remote_conf
+                                .update(|conf| conf.use_archive = event_target_checked(&ev));
+                        }
+                        class="mr-2"
+                    />
+                    Download repos as one archive
+                </label>
+
+                <HoverInfoIcon text="Download the repo (or directory) as a single tar.gz archive of the ref instead of fetching files one by one, which is faster and spends only a couple of API requests. Archives up to 64MB are accepted. Code hosts other than GitLab and Gitea may need the CORS proxy set in Step 1 for this." />
+            </div>
+
             <ValidationErrorMsg code_in_vstate />
         </div>
     }
@@ -592,6 +811,7 @@ fn ImportFromPasteSection(
 fn CodeRetrieveExpandedView(
     import_method: RwSignal<ImportMethod>,
     input_code_url: RwSignal<String>,
+    input_remote_conf: RwSignal<RemoteConf>,
     input_code_text: RwSignal<String>,
     code_in_vstate: RwSignal<ValidationState<CodeImportError>>,
     cgf_client: RwSignal<CgfClient>,
@@ -647,6 +867,7 @@ fn CodeRetrieveExpandedView(
                             <ImportFromUrlToSection
                                 import_method
                                 input_code_url
+                                input_remote_conf
                                 code_in_vstate
                                 cgf_client
                                 code_group
@@ -733,6 +954,40 @@ fn CodeRetrieveCollapsedView(
                         },
                     )
             }}
+            {move || {
+                let failures = code_group.read().failures().to_vec();
+                (!failures.is_empty())
+                    .then(|| {
+                        view! {
+                            <div class="text-orange-700 text-base font-mono mt-4 text-center animate-fade-in">
+                                {failures.len()} {NBSP}source(s) failed to import and were skipped:
+                                {failures
+                                    .into_iter()
+                                    .map(|(source, err)| {
+                                        view! {
+                                            <div class="text-sm break-all">
+                                                {source} {NBSP}- {NBSP} {err.to_string()}
+                                            </div>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </div>
+                        }
+                    })
+            }}
+            {move || {
+                code_group
+                    .read()
+                    .rate_limit()
+                    .map(|rate_limit| {
+                        view! {
+                            <div class="text-gray-600 text-base font-mono mt-4 text-center animate-fade-in">
+                                API rate limit remaining: {NBSP} {rate_limit.remaining} {NBSP}/{NBSP}
+                                {rate_limit.limit} {NBSP}requests
+                            </div>
+                        }
+                    })
+            }}
             {move || {
                 (code_group.read().num_files() >= MAX_NUM_FILES)
                     .then_some(
@@ -790,6 +1045,7 @@ fn CodeRetrieveCollapsedView(
 pub(crate) fn CodeRetrieve(
     import_method: RwSignal<ImportMethod>,
     input_code_url: RwSignal<String>,
+    input_remote_conf: RwSignal<RemoteConf>,
     input_code_text: RwSignal<String>,
     code_in_vstate: RwSignal<ValidationState<CodeImportError>>,
     cgf_client: RwSignal<CgfClient>,
@@ -809,6 +1065,7 @@ pub(crate) fn CodeRetrieve(
                         <CodeRetrieveExpandedView
                             import_method
                             input_code_url
+                            input_remote_conf
                             input_code_text
                             code_in_vstate
                             cgf_client
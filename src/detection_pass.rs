@@ -16,7 +16,8 @@ use gloo_timers::future::TimeoutFuture;
 
 use gloo_file::{Blob, ObjectUrl};
 
-use crate::apis::ApiClient;
+use crate::api_selection::ApiProvider;
+use crate::apis::{ApiClient, GenerationConf};
 use crate::file::{CodeFile, CodeGroup};
 use crate::utils::error::ApiMakeCallError;
 use crate::utils::gadgets::{
@@ -37,6 +38,7 @@ pub(crate) enum DetectionStatus {
     Pending,
     Flying,
     Success((u8, String)), // percentage of AI authorship and reasoning
+    Recited(String),       // model refused for reciting well-known code
     Failure(String),       // error message
 }
 
@@ -49,9 +51,18 @@ pub(crate) type FileResults = Vec<(String, RwSignal<CodeFile>, RwSignal<Detectio
 /// Helper structs for putting the analysis results together for JSON downloading.
 #[derive(Serialize, Deserialize, Debug)]
 struct DownloadableResults {
+    metadata: DownloadableResultsMetadata,
     results: Vec<DownloadableResultsEntry>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct DownloadableResultsMetadata {
+    provider: String,
+    model: Option<String>,
+    #[serde(flatten)]
+    generation: GenerationConf,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct DownloadableResultsEntry {
     file: String,
@@ -59,25 +70,48 @@ struct DownloadableResultsEntry {
     size: Option<usize>,
     finished: bool,
     likelihood: Option<u8>,
+    recitation: bool,
     reasoning: Option<String>,
     error_msg: Option<String>,
 }
 
 impl DownloadableResults {
-    fn from(file_results: &FileResults) -> Self {
+    fn from(
+        file_results: &FileResults,
+        api_provider: ApiProvider,
+        api_client: Option<&ApiClient>,
+    ) -> Self {
+        // the client knows what it actually used, e.g. which provider the free
+        // option picked, which model a default resolved to, and which settings
+        // the provider took
+        let metadata = DownloadableResultsMetadata {
+            provider: api_client
+                .map_or(api_provider, |client| client.provider())
+                .id()
+                .to_string(),
+            model: api_client.and_then(|client| client.model()),
+            generation: api_client
+                .map_or_else(GenerationConf::default, |client| client.generation()),
+        };
+
         let mut results = Vec::new();
         for (path, code_file, detect_status) in file_results.iter() {
             let file = path.clone();
             let lang = CodeFile::lang_name_of(code_file.read().get_ext());
             let size = code_file.read().get_size();
             let status = detect_status.get();
-            let finished = matches!(status, DetectionStatus::Success(_));
+            let finished = matches!(
+                status,
+                DetectionStatus::Success(_) | DetectionStatus::Recited(_)
+            );
             let likelihood = match status {
                 DetectionStatus::Success((percent, _)) => Some(percent),
                 _ => None,
             };
+            let recitation = matches!(status, DetectionStatus::Recited(_));
             let (reasoning, error_msg) = match status {
                 DetectionStatus::Success((_, reason)) => (Some(reason), None),
+                DetectionStatus::Recited(reason) => (Some(reason), None),
                 DetectionStatus::Failure(err_msg) => (None, Some(err_msg)),
                 _ => (None, Some("Analysis for this file is still in progress (which generally should not happend at the time of download).".to_string())),
             };
@@ -88,12 +122,13 @@ impl DownloadableResults {
                 size,
                 finished,
                 likelihood,
+                recitation,
                 reasoning,
                 error_msg,
             });
         }
 
-        DownloadableResults { results }
+        DownloadableResults { metadata, results }
     }
 }
 
@@ -113,11 +148,32 @@ fn format_file_size(size_opt: Option<usize>) -> String {
 
 async fn detection_api_call(client: &ApiClient, code: &str) -> DetectionStatus {
     match client.call(code).await {
-        Ok((percent, reason)) => DetectionStatus::Success((percent, format!("Reasoning: {}", reason))),
-        Err(err) => DetectionStatus::Failure(match err {
-            ApiMakeCallError::Parse(_) => "Failed to parse API response. This could be due to unexpected model output format or truncation (despite being instructed otherwise), or due to rate limiting. Please try again later.",
-            ApiMakeCallError::Status(_) => "Network error when making the API call. This could be due to connection issues, model unavailability, authorization failure, or mostly likely, rate limiting. Please try again later.",
-        }.to_string()),
+        Ok((percent, reason)) => {
+            DetectionStatus::Success((percent, format!("Reasoning: {}", reason)))
+        }
+        Err(err) => {
+            let advice = match &err {
+                ApiMakeCallError::Auth(_) => "Authorization failed. Check that the API key is valid and has access to this model.",
+                ApiMakeCallError::RateLimit(_) => "Rate limited by the provider. Wait a moment, then retry the failed files.",
+                ApiMakeCallError::Quota(_) => "Quota or credits exhausted on the API key. Top up the account or use another key.",
+                ApiMakeCallError::ContextOverflow(_) => "File too large for the model's context window. Shrink or split the file, or choose a model with a longer context.",
+                ApiMakeCallError::ContentFilter(_) => "Response blocked by the provider's content filter or safety settings.",
+                ApiMakeCallError::Recitation(failure) => {
+                    // not a failure per se, but a signal in its own right
+                    return DetectionStatus::Recited(format!(
+                        "Recitation: the model recognized this code as reproducing existing published source, which suggests it is well-known (likely human-written) code rather than freshly generated. Cause: {}",
+                        failure
+                    ));
+                }
+                ApiMakeCallError::Truncated(_) => "Model output was cut off at the output token cap before giving a result. Retrying usually helps.",
+                ApiMakeCallError::ModelUnavailable(_) => "Model unavailable or overloaded at the moment. Retry later or choose another model.",
+                ApiMakeCallError::Timeout(_) => "The API call timed out. Please try again later.",
+                ApiMakeCallError::Network(_) => "Network error when making the API call. Check the connection (and any proxy or CORS settings).",
+                ApiMakeCallError::Parse(_) => "Failed to parse API response. The model output may be malformed or truncated despite instructions; retrying usually helps.",
+                ApiMakeCallError::Status(_) => "The API call failed unexpectedly. Please try again later.",
+            };
+            DetectionStatus::Failure(format!("{} Cause: {}", advice, err.failure()))
+        }
     }
 }
 
@@ -218,8 +274,16 @@ fn handle_retry_button(
     }
 }
 
-fn handle_download_button(file_results: RwSignal<FileResults>) {
-    let results = DownloadableResults::from(&file_results.read());
+fn handle_download_button(
+    file_results: RwSignal<FileResults>,
+    api_provider: RwSignal<ApiProvider>,
+    api_client: RwSignal<Option<ApiClient>>,
+) {
+    let results = DownloadableResults::from(
+        &file_results.read(),
+        api_provider.get_untracked(),
+        api_client.read_untracked().as_ref(),
+    );
     match serde_json::to_string_pretty(&results) {
         Ok(results_json) => {
             let blob = Blob::new(results_json.as_str());
@@ -306,7 +370,10 @@ fn FileDetectionRow(
                             .then_some(view! { <SpinningIndicator /> })
                     }}
                     {move || {
-                        matches!(*detect_status.read(), DetectionStatus::Success(_))
+                        matches!(
+                            *detect_status.read(),
+                            DetectionStatus::Success(_) | DetectionStatus::Recited(_)
+                        )
                             .then_some(view! { <SuccessIndicator /> })
                     }}
                     {move || {
@@ -329,6 +396,17 @@ fn FileDetectionRow(
                             None
                         }
                     }}
+                    {move || {
+                        if let DetectionStatus::Recited(reason) = detect_status.get() {
+                            Some(
+                                view! {
+                                    <HoverResultDiv percent=None message=reason label="recited" />
+                                },
+                            )
+                        } else {
+                            None
+                        }
+                    }}
                     {move || {
                         if let DetectionStatus::Failure(err_msg) = detect_status.get() {
                             Some(view! { <HoverResultDiv percent=None message=err_msg /> })
@@ -359,6 +437,8 @@ fn NoRetryErrorMsg(nothing_to_retry: RwSignal<bool>) -> impl IntoView {
 
 #[component]
 fn DetectionPassExpandedView(
+    api_provider: RwSignal<ApiProvider>,
+    api_client: RwSignal<Option<ApiClient>>,
     code_group: RwSignal<CodeGroup>,
     task_queue: RwSignal<TaskQueue>,
     num_finished: RwSignal<usize>,
@@ -461,7 +541,11 @@ fn DetectionPassExpandedView(
                                 </button>
 
                                 <button
-                                    on:click=move |_| handle_download_button(file_results)
+                                    on:click=move |_| handle_download_button(
+                                        file_results,
+                                        api_provider,
+                                        api_client,
+                                    )
                                     class="px-4 py-2 bg-gray-500 hover:bg-gray-600 text-white rounded-md shadow transition-colors flex align-middle"
                                 >
                                     Download
@@ -497,6 +581,8 @@ fn DetectionPassExpandedView(
 /// The code retrieval step wrapped in one place.
 #[component]
 pub(crate) fn DetectionPass(
+    api_provider: RwSignal<ApiProvider>,
+    api_client: RwSignal<Option<ApiClient>>,
     code_group: RwSignal<CodeGroup>,
     task_queue: RwSignal<TaskQueue>,
     num_finished: RwSignal<usize>,
@@ -511,6 +597,8 @@ pub(crate) fn DetectionPass(
                 .then_some(
                     view! {
                         <DetectionPassExpandedView
+                            api_provider
+                            api_client
                             code_group
                             task_queue
                             num_finished
@@ -0,0 +1,165 @@
+//! Archives downloaded for import, e.g. repo tarballs from code forges.
+
+use leptos::prelude::*;
+
+use url::Url;
+
+use reqwest::header::HeaderMap;
+use reqwest::Client;
+
+use crate::file::forge::RateLimit;
+use crate::file::{CodeFile, CodeGroup};
+use crate::utils::error::CodeImportError;
+use crate::utils::proxy::proxied;
+
+/// Max size of an archive download.
+pub(crate) const MAX_ARCHIVE_SIZE: usize = 64 * 1024 * 1024; // 64MB
+
+/// Supported archive formats.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    SevenZ,
+}
+
+impl ArchiveFormat {
+    /// Detects the format from the MIME type of a download.
+    pub(crate) fn of_mime(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim();
+        match mime.to_ascii_lowercase().as_str() {
+            "application/zip" | "application/x-zip-compressed" => Some(ArchiveFormat::Zip),
+            "application/x-tar" => Some(ArchiveFormat::Tar),
+            "application/gzip" | "application/x-gzip" | "application/x-compressed-tar" => {
+                Some(ArchiveFormat::TarGz)
+            }
+            "application/x-7z-compressed" => Some(ArchiveFormat::SevenZ),
+            _ => None,
+        }
+    }
+
+    /// Detects the format from the suffix of a file name.
+    pub(crate) fn of_name(name: &str) -> Option<Self> {
+        let dot_pos = name.rfind('.')?;
+        match name[dot_pos..].to_ascii_lowercase().as_str() {
+            ".zip" => Some(ArchiveFormat::Zip),
+            ".tar" => Some(ArchiveFormat::Tar),
+            ".gz" | ".tgz" => Some(ArchiveFormat::TarGz),
+            ".7z" => Some(ArchiveFormat::SevenZ),
+            _ => None,
+        }
+    }
+}
+
+/// Archive to download and import in one go, instead of fetching files one
+/// by one.
+pub(crate) struct RemoteArchive {
+    pub(crate) url: Url,
+    pub(crate) format: ArchiveFormat,
+    headers: HeaderMap,
+    /// Root of imported file paths, replacing the top-level directory that
+    /// forges and registries wrap archives in. Entry names are kept as they
+    /// are if None.
+    root: Option<String>,
+    /// Path under the top-level directory to import, empty for all of it.
+    subpath: String,
+    /// API rate-limit budget left after locating the archive.
+    pub(crate) rate_limit: Option<RateLimit>,
+}
+
+impl RemoteArchive {
+    pub(crate) fn new(url: Url, format: ArchiveFormat, headers: HeaderMap) -> Self {
+        RemoteArchive {
+            url,
+            format,
+            headers,
+            root: None,
+            subpath: String::new(),
+            rate_limit: None,
+        }
+    }
+
+    /// Imports the archive's top-level directory, or the given path under it,
+    /// as `root`.
+    pub(crate) fn rooted(mut self, root: &str, subpath: String) -> Self {
+        self.root = Some(root.to_string());
+        self.subpath = subpath;
+        self
+    }
+
+    /// Downloads the archive, rejecting it if too large.
+    pub(crate) async fn download(
+        &self,
+        client: RwSignal<Client>,
+    ) -> Result<Vec<u8>, CodeImportError> {
+        let resp = client
+            .read_untracked()
+            .get(proxied(self.url.as_str()))
+            .headers(self.headers.clone())
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CodeImportError::status(format!(
+                "archive download failed with: {}",
+                resp.status()
+            )));
+        }
+        let too_large = |size: usize| {
+            CodeImportError::limit(format!(
+                "archive too large ({}MB >= max {}MB)",
+                size / (1024 * 1024),
+                MAX_ARCHIVE_SIZE / (1024 * 1024)
+            ))
+        };
+        if let Some(size) = resp.content_length() {
+            if size as usize > MAX_ARCHIVE_SIZE {
+                return Err(too_large(size as usize));
+            }
+        }
+        let bytes = resp.bytes().await?;
+        if bytes.len() > MAX_ARCHIVE_SIZE {
+            return Err(too_large(bytes.len()));
+        }
+        Ok(bytes.to_vec())
+    }
+
+    /// Maps an archive entry name to the path it is imported as. Returns None
+    /// for entries outside the path to import.
+    pub(crate) fn import_path(&self, name: &str) -> Option<String> {
+        let Some(root) = &self.root else {
+            return Some(name.to_string());
+        };
+        let (_, path) = name.split_once('/')?;
+        if !self.subpath.is_empty()
+            && !path
+                .strip_prefix(self.subpath.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+        {
+            return None;
+        }
+        Some(format!("{}/{}", root, path))
+    }
+}
+
+impl CodeGroup {
+    /// Downloads an archive and adds the code files within. Returns the
+    /// number of files added.
+    pub(crate) async fn import_archive(
+        &mut self,
+        client: RwSignal<Client>,
+        archive: &RemoteArchive,
+    ) -> Result<usize, CodeImportError> {
+        let bytes = archive.download(client).await?;
+        let name_data_list = self
+            .extract_bytes(archive.format, bytes, |name| archive.import_path(name))
+            .await?;
+
+        let num_files = name_data_list.len();
+        for (name, (ext, content)) in name_data_list {
+            self.add_file(name, CodeFile::new_local(ext, content))?;
+        }
+        Ok(num_files)
+    }
+}
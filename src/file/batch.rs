@@ -0,0 +1,152 @@
+//! Batch import of many remote sources at once, e.g. a list of student repos.
+
+use leptos::prelude::*;
+
+use reqwest::Client;
+
+use crate::file::registry::Package;
+use crate::file::{CodeGroup, RemoteConf, MAX_NUM_FILES};
+use crate::utils::error::CodeImportError;
+
+/// Max number of sources in one batch import.
+pub(crate) const MAX_BATCH_SOURCES: usize = 50;
+
+/// Parses a list of sources, one per line, as typed or as read from an
+/// uploaded .txt/.csv file. Lines of several comma- or tab-separated fields
+/// (e.g. "Student,https://...") keep only the fields that look like URLs or
+/// package identifiers. Blank lines, "#" comments, and duplicates are dropped,
+/// where sources differing only in scheme or trailing slashes are duplicates,
+/// as their files would land under the same path prefix.
+pub(crate) fn parse_source_list(text: &str) -> Vec<String> {
+    let mut sources: Vec<String> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line
+            .split([',', '\t', ';'])
+            .map(|field| field.trim().trim_matches('"').trim())
+            .filter(|field| !field.is_empty())
+            .collect();
+        let picked = if fields.len() == 1 {
+            fields
+        } else {
+            fields
+                .into_iter()
+                .filter(|field| {
+                    !field.contains(char::is_whitespace)
+                        && (field.contains('/') || matches!(Package::parse(field), Ok(Some(_))))
+                })
+                .collect()
+        };
+        for source in picked {
+            if !sources
+                .iter()
+                .any(|seen| source_prefix(seen) == source_prefix(source))
+            {
+                sources.push(source.to_string());
+            }
+        }
+    }
+    sources
+}
+
+/// Returns the path prefix of files imported from a source, which is the
+/// source with its scheme and trailing slashes removed.
+fn source_prefix(source: &str) -> &str {
+    source
+        .split_once("://")
+        .map_or(source, |(_, rest)| rest)
+        .trim_end_matches('/')
+}
+
+impl CodeGroup {
+    /// Populates the importer with many remote sources, each imported as with
+    /// `import_remote` and placed under its own path prefix. Sources that fail,
+    /// or that would take the total past MAX_NUM_FILES, are recorded and
+    /// skipped; an error is returned only if all of them fail. The 1-based
+    /// index of the source being imported is kept in `current`.
+    pub(crate) async fn import_batch(
+        &mut self,
+        client: RwSignal<Client>,
+        sources: &[String],
+        conf: &RemoteConf,
+        progress: RwSignal<usize>,
+        current: RwSignal<usize>,
+    ) -> Result<(), CodeImportError> {
+        if sources.len() > MAX_BATCH_SOURCES {
+            return Err(CodeImportError::batch(format!(
+                "too many sources ({} > max {})",
+                sources.len(),
+                MAX_BATCH_SOURCES
+            )));
+        }
+
+        for (index, source) in sources.iter().enumerate() {
+            current.set(index + 1);
+            if self.files.len() >= MAX_NUM_FILES {
+                self.failures.push((
+                    source.clone(),
+                    CodeImportError::limit(format!(
+                        "skipped, as the batch already has max {} files",
+                        MAX_NUM_FILES
+                    )),
+                ));
+                continue;
+            }
+
+            let mut group = CodeGroup::new();
+            let result = group
+                .import_remote(client, source, conf, progress)
+                .await
+                .and_then(|()| self.merge(group, source_prefix(source)));
+            if let Err(err) = result {
+                log::warn!("Batch import of '{}' failed: {}", source, err);
+                self.failures.push((source.clone(), err));
+            }
+        }
+
+        if self.files.is_empty() {
+            let (source, err) = self
+                .failures
+                .first()
+                .ok_or_else(|| CodeImportError::batch("no sources given"))?;
+            return Err(CodeImportError::batch(format!(
+                "all {} source(s) failed to import, first '{}': {}",
+                sources.len(),
+                source,
+                err
+            )));
+        }
+        Ok(())
+    }
+
+    /// Moves the files of another importer into me, under the given prefix.
+    /// Either all of them are moved or, on error, none.
+    pub(super) fn merge(&mut self, other: CodeGroup, prefix: &str) -> Result<(), CodeImportError> {
+        if self.files.len() + other.files.len() > MAX_NUM_FILES {
+            return Err(CodeImportError::limit(format!(
+                "its {} files would take the batch past max {} files",
+                other.files.len(),
+                MAX_NUM_FILES
+            )));
+        }
+
+        let files: Vec<_> = other
+            .files
+            .into_iter()
+            .map(|(path, file)| (format!("{}/{}", prefix, path), file))
+            .collect();
+        if let Some((path, _)) = files.iter().find(|(path, _)| self.files.contains_key(path)) {
+            return Err(CodeImportError::exists(format!(
+                "file name '{}' already exists",
+                path
+            )));
+        }
+
+        self.files.extend(files);
+        self.skipped |= other.skipped;
+        self.rate_limit = other.rate_limit.or(self.rate_limit);
+        Ok(())
+    }
+}
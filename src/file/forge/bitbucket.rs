@@ -0,0 +1,271 @@
+//! Forge backend for Bitbucket Cloud repos.
+//!
+//! Reference: https://developer.atlassian.com/cloud/bitbucket/rest/api-group-source/
+
+use std::collections::VecDeque;
+
+use leptos::prelude::*;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Number;
+
+use url::Url;
+
+use reqwest::{Client, StatusCode};
+
+use reqwest::header::HeaderMap;
+
+use crate::file::archive::{ArchiveFormat, RemoteArchive};
+use crate::file::forge::{with_segments, RepoListing};
+use crate::utils::error::CodeImportError;
+use crate::utils::proxy::proxied;
+
+/// Bitbucket user-supplied repo URL host string (must match).
+const BITBUCKET_HOST_STR: &str = "bitbucket.org";
+
+/// Bitbucket API request URL prefix.
+const BITBUCKET_API_PREFIX: &str = "https://api.bitbucket.org/2.0/repositories";
+
+/// Max entries per page of directory listing allowed by Bitbucket.
+const BITBUCKET_PAGE_LEN: usize = 100;
+
+/// Bitbucket API directory listing entry struct.
+#[derive(Serialize, Deserialize)]
+struct BitbucketSrcEntry {
+    #[serde(rename = "type")]
+    o_type: String,
+    path: String,
+    size: Option<Number>,
+    commit: BitbucketCommit,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BitbucketCommit {
+    hash: String,
+}
+
+/// Bitbucket API directory listing response body, one page of it.
+#[derive(Serialize, Deserialize)]
+struct BitbucketSrcResponse {
+    values: Vec<BitbucketSrcEntry>,
+    next: Option<String>,
+}
+
+/// Bitbucket API repo metadata response body.
+#[derive(Serialize, Deserialize)]
+struct BitbucketRepoMetaResponse {
+    name: String,
+    mainbranch: Option<BitbucketBranch>, // null for empty repos
+}
+
+#[derive(Serialize, Deserialize)]
+struct BitbucketBranch {
+    name: String,
+}
+
+/// Bitbucket Cloud repo, with the ref to import if given in the URL.
+pub(crate) struct Repo {
+    workspace: String,
+    repo: String,
+    tree: Option<String>,
+}
+
+impl Repo {
+    /// Parse a user-supplied Bitbucket repo URL into workspace, repo, and ref.
+    /// If the URL is not on Bitbucket, return None.
+    pub(crate) fn from_url(url: &Url) -> Result<Option<Self>, CodeImportError> {
+        if url.host_str() != Some(BITBUCKET_HOST_STR) {
+            return Ok(None);
+        }
+
+        let segs = url
+            .path_segments()
+            .ok_or_else(|| CodeImportError::parse("invalid URL path to Bitbucket repo"))?
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<&str>>();
+        if segs.len() < 2 {
+            return Err(CodeImportError::bitbucket(
+                "repo URL must contain workspace and repo name",
+            ));
+        }
+
+        let tree = match &segs[2..] {
+            [] => None,
+            ["src", tree] | ["branch", tree] => Some(tree.to_string()),
+            _ => {
+                return Err(CodeImportError::bitbucket(
+                    "repo URL should not carry path to specific file",
+                ))
+            }
+        };
+
+        Ok(Some(Repo {
+            workspace: segs[0].to_string(),
+            repo: segs[1].strip_suffix(".git").unwrap_or(segs[1]).to_string(),
+            tree,
+        }))
+    }
+
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        &self.repo
+    }
+
+    /// Returns the API URL of the repo, or of something under it.
+    fn api_url<'a>(
+        &'a self,
+        subpath: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Url, CodeImportError> {
+        with_segments(
+            &Url::parse(BITBUCKET_API_PREFIX)?,
+            [self.workspace.as_str(), self.repo.as_str()]
+                .into_iter()
+                .chain(subpath),
+        )
+    }
+
+    /// Query repo metadata for the main branch.
+    async fn main_branch(&self, client: RwSignal<Client>) -> Result<String, CodeImportError> {
+        let response = client
+            .read_untracked()
+            .get(proxied(self.api_url([])?.as_str()))
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND || response.status() == StatusCode::FORBIDDEN
+        {
+            return Err(CodeImportError::bitbucket(format!(
+                "repo '{}/{}' not found, or is private",
+                self.workspace, self.repo
+            )));
+        } else if !response.status().is_success() {
+            return Err(CodeImportError::bitbucket(format!(
+                "repo metadata query failed with: {}",
+                response.status()
+            )));
+        }
+        let resp = response.json::<BitbucketRepoMetaResponse>().await?;
+        match resp.mainbranch {
+            Some(branch) => Ok(branch.name),
+            None => Err(CodeImportError::bitbucket(format!(
+                "repo '{}' has no main branch, is it empty?",
+                resp.name
+            ))),
+        }
+    }
+
+    /// Locate the tarball of the ref to import.
+    pub(crate) async fn archive(
+        &self,
+        client: RwSignal<Client>,
+    ) -> Result<Option<RemoteArchive>, CodeImportError> {
+        let tree = match &self.tree {
+            Some(tree) => tree.clone(),
+            None => self.main_branch(client).await?,
+        };
+        let url = with_segments(
+            &Url::parse(&format!("https://{}", BITBUCKET_HOST_STR))?,
+            [
+                self.workspace.as_str(),
+                self.repo.as_str(),
+                "get",
+                &format!("{}.tar.gz", tree),
+            ],
+        )?;
+        Ok(Some(
+            RemoteArchive::new(url, ArchiveFormat::TarGz, HeaderMap::new())
+                .rooted(&self.repo, String::new()),
+        ))
+    }
+
+    /// BFS traverse the repo directories starting from root, paging through
+    /// each listing, and gathering files into the listing.
+    pub(crate) async fn list(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+    ) -> Result<(), CodeImportError> {
+        // the ref is resolved to a commit hash by the first listing, as branch
+        // names with slashes do not work in later source paths
+        let mut tree = match &self.tree {
+            Some(tree) => tree.clone(),
+            None => self.main_branch(client).await?,
+        };
+        let mut resolved = false;
+
+        let mut bfs_queue = VecDeque::new();
+        bfs_queue.push_back(String::new());
+
+        while let Some(dir) = bfs_queue.pop_front() {
+            let mut dir_url = self.api_url(
+                ["src", tree.as_str()]
+                    .into_iter()
+                    .chain(dir.split('/').filter(|s| !s.is_empty())),
+            )?;
+            // directory listings need the trailing slash
+            dir_url.path_segments_mut().unwrap().push("");
+            dir_url
+                .query_pairs_mut()
+                .append_pair("pagelen", &BITBUCKET_PAGE_LEN.to_string());
+            let mut page_url = Some(dir_url.to_string());
+
+            while let Some(this_url) = page_url.take() {
+                let response = client
+                    .read_untracked()
+                    .get(proxied(this_url))
+                    .send()
+                    .await?;
+
+                if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                    return Err(CodeImportError::bitbucket(format!(
+                        "repo URL listing failed with: {}, rate limited?",
+                        response.status()
+                    )));
+                } else if !response.status().is_success() {
+                    return Err(CodeImportError::bitbucket(format!(
+                        "repo URL listing failed with: {}",
+                        response.status()
+                    )));
+                }
+                let resp = response.json::<BitbucketSrcResponse>().await?;
+                page_url = resp.next;
+
+                for entry in resp.values {
+                    if !resolved {
+                        tree = entry.commit.hash.clone();
+                        resolved = true;
+                    }
+
+                    match entry.o_type.as_str() {
+                        "commit_file" => {
+                            let raw_url = self.api_url(
+                                ["src", tree.as_str()]
+                                    .into_iter()
+                                    .chain(entry.path.split('/')),
+                            )?;
+                            let approx_size =
+                                entry.size.map(|s| s.as_u64().unwrap_or(0)).unwrap_or(0) as usize; // 0 means unclear size
+
+                            if listing.push(
+                                format!("{}/{}", self.repo, entry.path),
+                                raw_url,
+                                approx_size,
+                            ) {
+                                return Ok(());
+                            }
+                        }
+
+                        "commit_directory" => {
+                            // subdirectory, add to BFS queue
+                            bfs_queue.push_back(entry.path);
+                        }
+
+                        _ => {} // submodules ignored
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
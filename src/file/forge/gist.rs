@@ -0,0 +1,144 @@
+//! Forge backend for GitHub Gists.
+
+use std::collections::BTreeMap;
+
+use leptos::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use url::Url;
+
+use reqwest::{Client, StatusCode};
+
+use crate::file::forge::{github, with_segments, RepoListing};
+use crate::file::RemoteConf;
+use crate::utils::error::CodeImportError;
+use crate::utils::proxy::proxied;
+
+/// Gist user-supplied URL host string (must match).
+const GIST_HOST_STR: &str = "gist.github.com";
+
+/// GitHub API gists request URL prefix.
+const GIST_API_PREFIX: &str = "https://api.github.com/gists";
+
+/// GitHub API gist file entry struct.
+#[derive(Serialize, Deserialize)]
+struct GistFileEntry {
+    filename: String,
+    size: usize,
+    raw_url: String,
+    #[serde(default)]
+    truncated: bool,
+    content: Option<String>, // cut short if truncated
+}
+
+/// GitHub API gist response body.
+#[derive(Serialize, Deserialize)]
+struct GistResponse {
+    files: BTreeMap<String, GistFileEntry>,
+}
+
+/// GitHub Gist, by its ID.
+pub(crate) struct Gist {
+    id: String,
+    /// Personal access token to authorize requests with, if any.
+    token: Option<String>,
+}
+
+impl Gist {
+    /// Parse a user-supplied gist URL, of the form "/<user>/<id>" or "/<id>",
+    /// into the gist ID. If the URL is not on Gist, return None.
+    pub(crate) fn from_url(url: &Url, conf: &RemoteConf) -> Result<Option<Self>, CodeImportError> {
+        if url.host_str() != Some(GIST_HOST_STR) {
+            return Ok(None);
+        }
+
+        let segs = url
+            .path_segments()
+            .ok_or_else(|| CodeImportError::parse("invalid URL path to gist"))?
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<&str>>();
+        let id = match segs[..] {
+            [id] | [_, id] | [_, id, "revisions"] => id,
+            _ => {
+                return Err(CodeImportError::github(
+                    "gist URL must end with the gist ID",
+                ))
+            }
+        };
+        if !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(CodeImportError::github(format!("invalid gist ID '{}'", id)));
+        }
+
+        Ok(Some(Gist {
+            id: id.to_string(),
+            token: conf.github_token().map(str::to_string),
+        }))
+    }
+
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        &self.id
+    }
+
+    /// Gather the gist's code files, taking inline contents where the API
+    /// gives them in full.
+    pub(crate) async fn list(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+    ) -> Result<(), CodeImportError> {
+        let response = client
+            .read_untracked()
+            .get(proxied(
+                with_segments(&Url::parse(GIST_API_PREFIX)?, [self.id.as_str()])?.as_str(),
+            ))
+            .headers(github::api_headers(
+                self.token.as_deref(),
+                "application/vnd.github+json",
+            )?)
+            .send()
+            .await?;
+
+        listing.record_rate_limit(&response);
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(CodeImportError::github(format!(
+                "gist '{}' not found",
+                self.id
+            )));
+        } else if response.status() == StatusCode::FORBIDDEN
+            || response.status() == StatusCode::TOO_MANY_REQUESTS
+        {
+            // probably getting rate limited by GitHub
+            return Err(CodeImportError::github(format!(
+                "gist query failed with: {}, rate limited?",
+                response.status()
+            )));
+        } else if !response.status().is_success() {
+            return Err(CodeImportError::github(format!(
+                "gist query failed with: {}",
+                response.status()
+            )));
+        }
+        let resp = response.json::<GistResponse>().await?;
+
+        for file in resp.files.into_values() {
+            let path = format!("{}/{}", self.id, file.filename);
+            let full = match file.content {
+                Some(content) if !file.truncated => listing.push_local(path, content),
+                _ => listing.push(path, Url::parse(&file.raw_url)?, file.size),
+            };
+            if full {
+                return Ok(());
+            }
+        }
+
+        if listing.files.is_empty() {
+            return Err(CodeImportError::github(format!(
+                "gist '{}' does not contain any code files",
+                self.id
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+//! Forge backend for Gitea and Forgejo repos, on Codeberg or self-hosted.
+//!
+//! Reference: https://codeberg.org/api/swagger
+
+use leptos::prelude::*;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Number;
+
+use url::Url;
+
+use reqwest::{Client, StatusCode};
+
+use reqwest::header::HeaderMap;
+
+use crate::file::archive::{ArchiveFormat, RemoteArchive};
+use crate::file::forge::{host_matches, origin_of, with_segments, RepoListing};
+use crate::file::RemoteConf;
+use crate::utils::error::CodeImportError;
+use crate::utils::proxy::proxied;
+
+/// Codeberg host, always recognized; self-hosted instances are configured.
+const CODEBERG_HOST_STR: &str = "codeberg.org";
+
+/// Gitea API path prefix, under the instance's host.
+const GITEA_API_PATH: &str = "api/v1/repos";
+
+/// Entries per page of repo tree listing; instances may cap it lower.
+const GITEA_TREE_PAGE_SIZE: usize = 1000;
+
+/// Gitea API repo listing inner tree entry struct.
+#[derive(Serialize, Deserialize)]
+struct GiteaTreeEntry {
+    #[serde(rename = "type")]
+    o_type: String,
+    path: String,
+    size: Option<Number>,
+}
+
+/// Gitea API repo listing response body.
+#[derive(Serialize, Deserialize)]
+struct GiteaTreeResponse {
+    #[serde(default)]
+    tree: Vec<GiteaTreeEntry>,
+    truncated: bool,
+}
+
+/// Gitea API repo metadata response body.
+#[derive(Serialize, Deserialize)]
+struct GiteaRepoMetaResponse {
+    name: String,
+    default_branch: String,
+    empty: bool,
+}
+
+/// Gitea or Forgejo repo, with the ref to import if given in the URL.
+pub(crate) struct Repo {
+    origin: Url,
+    owner: String,
+    repo: String,
+    tree: Option<String>,
+}
+
+impl Repo {
+    /// Parse a user-supplied Gitea repo URL into owner, repo, and ref. The
+    /// host must be Codeberg or one of the self-hosted instances in `conf`; if
+    /// not, return None.
+    pub(crate) fn from_url(url: &Url, conf: &RemoteConf) -> Result<Option<Self>, CodeImportError> {
+        if !host_matches(url, CODEBERG_HOST_STR, &conf.gitea_hosts()) {
+            return Ok(None);
+        }
+
+        let segs = url
+            .path_segments()
+            .ok_or_else(|| CodeImportError::parse("invalid URL path to Gitea repo"))?
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<&str>>();
+        if segs.len() < 2 {
+            return Err(CodeImportError::gitea(
+                "repo URL must contain owner and repo name",
+            ));
+        }
+
+        // refs appear as "/src/branch/<name>", "/src/tag/<name>", or
+        // "/src/commit/<sha>"
+        let tree = match &segs[2..] {
+            [] => None,
+            ["src", "branch" | "tag" | "commit", tree] => Some(tree.to_string()),
+            _ => {
+                return Err(CodeImportError::gitea(
+                    "repo URL should not carry path to specific file",
+                ))
+            }
+        };
+
+        Ok(Some(Repo {
+            origin: origin_of(url)?,
+            owner: segs[0].to_string(),
+            repo: segs[1].strip_suffix(".git").unwrap_or(segs[1]).to_string(),
+            tree,
+        }))
+    }
+
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        &self.repo
+    }
+
+    /// Returns the API URL of the repo, or of something under it.
+    fn api_url<'a>(&'a self, subpath: &[&'a str]) -> Result<Url, CodeImportError> {
+        with_segments(
+            &self.origin,
+            GITEA_API_PATH
+                .split('/')
+                .chain([self.owner.as_str(), self.repo.as_str()])
+                .chain(subpath.iter().copied()),
+        )
+    }
+
+    /// Query repo metadata for the default branch.
+    async fn default_branch(&self, client: RwSignal<Client>) -> Result<String, CodeImportError> {
+        let response = client
+            .read_untracked()
+            .get(proxied(self.api_url(&[])?.as_str()))
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(CodeImportError::gitea(format!(
+                "repo '{}/{}' not found, or is private",
+                self.owner, self.repo
+            )));
+        } else if !response.status().is_success() {
+            return Err(CodeImportError::gitea(format!(
+                "repo metadata query failed with: {}",
+                response.status()
+            )));
+        }
+        let resp = response.json::<GiteaRepoMetaResponse>().await?;
+        if resp.empty {
+            return Err(CodeImportError::gitea(format!(
+                "repo '{}' is empty",
+                resp.name
+            )));
+        }
+        Ok(resp.default_branch)
+    }
+
+    /// Locate the tarball of the ref to import.
+    pub(crate) async fn archive(
+        &self,
+        client: RwSignal<Client>,
+    ) -> Result<Option<RemoteArchive>, CodeImportError> {
+        let tree = match &self.tree {
+            Some(tree) => tree.clone(),
+            None => self.default_branch(client).await?,
+        };
+        let url = self.api_url(&["archive", &format!("{}.tar.gz", tree)])?;
+        Ok(Some(
+            RemoteArchive::new(url, ArchiveFormat::TarGz, HeaderMap::new())
+                .rooted(&self.repo, String::new()),
+        ))
+    }
+
+    /// Page through the recursive repo tree listing, gathering code files into
+    /// the listing.
+    pub(crate) async fn list(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+    ) -> Result<(), CodeImportError> {
+        let tree = match &self.tree {
+            Some(tree) => tree.clone(),
+            None => self.default_branch(client).await?,
+        };
+
+        // raw file URLs of the API are of the form "<repo API>/raw/<path>?ref=<ref>",
+        // which keeps the file name last
+        let raw_prefix = self.api_url(&["raw"])?;
+        let tree_url = self.api_url(&["git", "trees", &tree])?;
+
+        let mut page = 1;
+        loop {
+            let mut url = tree_url.clone();
+            url.query_pairs_mut()
+                .append_pair("recursive", "true")
+                .append_pair("per_page", &GITEA_TREE_PAGE_SIZE.to_string())
+                .append_pair("page", &page.to_string());
+            let response = client
+                .read_untracked()
+                .get(proxied(url.as_str()))
+                .send()
+                .await?;
+
+            if response.status() == StatusCode::FORBIDDEN
+                || response.status() == StatusCode::TOO_MANY_REQUESTS
+            {
+                return Err(CodeImportError::gitea(format!(
+                    "repo URL listing failed with: {}, rate limited?",
+                    response.status()
+                )));
+            } else if !response.status().is_success() {
+                return Err(CodeImportError::gitea(format!(
+                    "repo URL listing failed with: {}",
+                    response.status()
+                )));
+            }
+            let resp = response.json::<GiteaTreeResponse>().await?;
+            let num_entries = resp.tree.len();
+
+            for entry in resp.tree {
+                // subtrees are listed recursively; submodules ignored
+                if entry.o_type != "blob" {
+                    continue;
+                }
+                let mut raw_url = with_segments(&raw_prefix, entry.path.split('/'))?;
+                raw_url.query_pairs_mut().append_pair("ref", &tree);
+                let approx_size = entry.size.map(|s| s.as_u64().unwrap_or(0)).unwrap_or(0) as usize; // 0 means unclear size
+
+                if listing.push(
+                    format!("{}/{}", self.repo, entry.path),
+                    raw_url,
+                    approx_size,
+                ) {
+                    return Ok(());
+                }
+            }
+
+            // more pages follow if truncated
+            if !resp.truncated || num_entries == 0 {
+                return Ok(());
+            }
+            page += 1;
+        }
+    }
+}
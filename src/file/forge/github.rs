@@ -0,0 +1,778 @@
+//! Forge backend for GitHub repos.
+
+use std::collections::{HashSet, VecDeque};
+
+use leptos::prelude::*;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Number;
+
+use url::Url;
+
+use reqwest::header::{HeaderMap, ACCEPT, AUTHORIZATION};
+use reqwest::{Client, Response, StatusCode};
+
+use crate::file::archive::{ArchiveFormat, RemoteArchive};
+use crate::file::forge::{added_lines, join_path, with_segments, RepoListing};
+use crate::file::RemoteConf;
+use crate::utils::error::CodeImportError;
+use crate::utils::proxy::proxied;
+
+/// GitHub user-supplied repo URL host string (must match).
+const GITHUB_HOST_STR: &str = "github.com";
+
+/// GitHub API request URL prefix.
+const GITHUB_API_PREFIX: &str = "https://api.github.com/repos";
+
+/// GitHub raw content URL prefix.
+const GITHUB_RAW_PREFIX: &str = "https://raw.githubusercontent.com";
+
+/// GitHub API repo listing inner tree entry struct.
+#[derive(Serialize, Deserialize)]
+struct GitHubGetTreeEntry {
+    #[serde(rename = "type")]
+    o_type: String,
+    path: String,
+    sha: String,
+    size: Option<Number>,
+}
+
+/// GitHub API repo listing response body.
+#[derive(Serialize, Deserialize)]
+struct GitHubGetTreeResponse {
+    sha: String,
+    tree: Vec<GitHubGetTreeEntry>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+/// GitHub API repo metadata response body.
+#[derive(Serialize, Deserialize)]
+struct GitHubRepoMetaResponse {
+    name: String,
+    default_branch: String,
+}
+
+/// GitHub API repo contents listing entry struct.
+#[derive(Serialize, Deserialize)]
+struct GitHubContentEntry {
+    #[serde(rename = "type")]
+    o_type: String,
+    name: String,
+    sha: String,
+}
+
+/// GitHub API pull request response body.
+#[derive(Serialize, Deserialize)]
+struct GitHubPullResponse {
+    head: GitHubPullHead,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GitHubPullHead {
+    sha: String,
+}
+
+/// GitHub API changed file entry of a pull request, commit, or comparison.
+#[derive(Serialize, Deserialize)]
+struct GitHubChangedFileEntry {
+    filename: String,
+    status: String,
+    patch: Option<String>, // absent for binary or very large diffs
+}
+
+/// GitHub API single commit response body, one page of its files.
+#[derive(Serialize, Deserialize)]
+struct GitHubCommitResponse {
+    sha: String,
+    #[serde(default)]
+    files: Vec<GitHubChangedFileEntry>,
+}
+
+/// GitHub API comparison response body.
+#[derive(Serialize, Deserialize)]
+struct GitHubCompareResponse {
+    #[serde(default)]
+    files: Vec<GitHubChangedFileEntry>,
+}
+
+/// Part of a GitHub repo that a URL points to. Refs may contain slashes, so
+/// the URL segments after "/tree/" or "/blob/" are kept unsplit until the ref
+/// is resolved.
+enum Target {
+    /// The whole repo at its default branch.
+    Default,
+    /// A ref, optionally followed by a directory path within.
+    Tree(Vec<String>),
+    /// A ref followed by a file path within.
+    Blob(Vec<String>),
+    /// Files changed in a pull request, by its number.
+    Pull(u64),
+    /// Files changed in a commit.
+    Commit(String),
+    /// Files changed in a "<base>...<head>" comparison.
+    Compare(String),
+}
+
+/// Max entries per page of pull request files allowed by GitHub.
+const GITHUB_FILES_PAGE_SIZE: usize = 100;
+
+/// GitHub repo, with the part to import given in the URL.
+pub(crate) struct Repo {
+    owner: String,
+    repo: String,
+    target: Target,
+    /// Personal access token to authorize requests with, if any.
+    token: Option<String>,
+    /// Whether to take only the added lines of changed files.
+    added_only: bool,
+}
+
+impl Repo {
+    /// Parse a user-supplied GitHub repo URL into owner, repo, and target. If
+    /// the URL is not on GitHub, return None.
+    pub(crate) fn from_url(url: &Url, conf: &RemoteConf) -> Result<Option<Self>, CodeImportError> {
+        if url.host_str() != Some(GITHUB_HOST_STR) {
+            return Ok(None);
+        }
+
+        if let Some(segs) = url.path_segments() {
+            let segs = segs.filter(|s| !s.is_empty()).collect::<Vec<&str>>();
+            if segs.len() < 2 {
+                return Err(CodeImportError::github(
+                    "repo URL must contain owner and repo name",
+                ));
+            }
+
+            let rest = |from: usize| segs[from..].iter().map(|s| s.to_string()).collect();
+            let target = match segs[2..] {
+                [] => Target::Default,
+                ["tree", _, ..] => Target::Tree(rest(3)),
+                ["blob", _, _, ..] => Target::Blob(rest(3)),
+                ["pull", number, ..] => Target::Pull(number.parse().map_err(|_| {
+                    CodeImportError::github(format!("invalid pull request number '{}'", number))
+                })?),
+                ["commit", sha, ..] => Target::Commit(sha.to_string()),
+                ["compare", _, ..] => Target::Compare(segs[3..].join("/")),
+                _ => {
+                    return Err(CodeImportError::github(
+                        "URL should point to a repo, a directory, or a file in it",
+                    ))
+                }
+            };
+
+            Ok(Some(Repo {
+                owner: segs[0].to_string(),
+                repo: segs[1].strip_suffix(".git").unwrap_or(segs[1]).to_string(),
+                target,
+                token: conf.github_token().map(str::to_string),
+                added_only: conf.diff_added_only,
+            }))
+        } else {
+            Err(CodeImportError::parse("invalid URL path to GitHub repo"))
+        }
+    }
+
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        &self.repo
+    }
+
+    #[inline]
+    fn api_headers(&self, media_type: &str) -> Result<HeaderMap, CodeImportError> {
+        api_headers(self.token.as_deref(), media_type)
+    }
+
+    /// Turns a failed API response status into an error.
+    fn check_status(&self, response: &Response, action: &str) -> Result<(), CodeImportError> {
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status == StatusCode::UNAUTHORIZED {
+            Err(CodeImportError::github(format!(
+                "{} failed with: {}, bad or expired token?",
+                action, status
+            )))
+        } else if status == StatusCode::NOT_FOUND && self.token.is_none() {
+            Err(CodeImportError::github(format!(
+                "repo '{}/{}' not found, or is private (needs a token)",
+                self.owner, self.repo
+            )))
+        } else if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS {
+            // probably getting rate limited by GitHub
+            Err(CodeImportError::github(format!(
+                "{} failed with: {}, rate limited?",
+                action, status
+            )))
+        } else {
+            Err(CodeImportError::github(format!(
+                "{} failed with: {}",
+                action, status
+            )))
+        }
+    }
+
+    /// Query repo metadata for the default branch.
+    async fn default_branch(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+    ) -> Result<String, CodeImportError> {
+        let response = client
+            .read_untracked()
+            .get(proxied(format!(
+                "{}/{}/{}",
+                GITHUB_API_PREFIX, self.owner, self.repo
+            )))
+            .headers(self.api_headers("application/vnd.github+json")?)
+            .send()
+            .await?;
+
+        listing.record_rate_limit(&response);
+        self.check_status(&response, "repo metadata query")?;
+        let resp = response.json::<GitHubRepoMetaResponse>().await?;
+        Ok(resp.default_branch)
+    }
+
+    /// Query the commit SHA1 that a ref, given as its slash-separated segments,
+    /// resolves to. Returns None if it is not a ref of the repo.
+    async fn lookup_commit<'a>(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+        ref_segs: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Option<String>, CodeImportError> {
+        let commits_url = with_segments(
+            &Url::parse(GITHUB_API_PREFIX)?,
+            [self.owner.as_str(), self.repo.as_str(), "commits"],
+        )?;
+        let url = with_segments(&commits_url, ref_segs)?;
+        let response = client
+            .read_untracked()
+            .get(proxied(url.as_str()))
+            .headers(self.api_headers("application/vnd.github.sha")?)
+            .send()
+            .await?;
+
+        listing.record_rate_limit(&response);
+        if response.status() == StatusCode::NOT_FOUND
+            || response.status() == StatusCode::UNPROCESSABLE_ENTITY
+        {
+            return Ok(None);
+        }
+        self.check_status(&response, "ref lookup")?;
+        Ok(Some(response.text().await?.trim().to_string()))
+    }
+
+    /// Splits the URL segments of a target into a ref and a path within,
+    /// trying the shortest ref first. Returns the commit SHA the ref resolves
+    /// to, and the path. A blob target needs a non-empty path.
+    async fn resolve_ref(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+        segs: &[String],
+        is_blob: bool,
+    ) -> Result<(String, String), CodeImportError> {
+        let max_ref_len = if is_blob { segs.len() - 1 } else { segs.len() };
+        for ref_len in 1..=max_ref_len {
+            let ref_segs = segs[..ref_len].iter().map(String::as_str);
+            if let Some(commit_sha) = self.lookup_commit(client, listing, ref_segs).await? {
+                return Ok((commit_sha, segs[ref_len..].join("/")));
+            }
+            // not a ref, try a longer one
+        }
+
+        Err(CodeImportError::github(format!(
+            "no branch, tag, or commit of repo '{}/{}' matches the URL{}",
+            self.owner,
+            self.repo,
+            if self.token.is_none() {
+                " (private repo needs a token)"
+            } else {
+                ""
+            }
+        )))
+    }
+
+    /// Query the SHA1 of a directory's tree, through its parent's contents.
+    async fn subtree_sha(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+        commit_sha: &str,
+        path: &str,
+    ) -> Result<String, CodeImportError> {
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let mut url = with_segments(
+            &Url::parse(GITHUB_API_PREFIX)?,
+            [self.owner.as_str(), self.repo.as_str(), "contents"]
+                .into_iter()
+                .chain(parent.split('/').filter(|s| !s.is_empty())),
+        )?;
+        url.query_pairs_mut().append_pair("ref", commit_sha);
+        let response = client
+            .read_untracked()
+            .get(proxied(url.as_str()))
+            .headers(self.api_headers("application/vnd.github+json")?)
+            .send()
+            .await?;
+
+        listing.record_rate_limit(&response);
+        self.check_status(&response, "directory lookup")?;
+        // a file parent gives an object rather than an array
+        let entries = response
+            .json::<Vec<GitHubContentEntry>>()
+            .await
+            .unwrap_or_default();
+        entries
+            .into_iter()
+            .find(|entry| entry.name == name && entry.o_type == "dir")
+            .map(|entry| entry.sha)
+            .ok_or_else(|| {
+                CodeImportError::github(format!(
+                    "path '{}' is not a directory in repo '{}'",
+                    path, self.repo
+                ))
+            })
+    }
+
+    /// Returns the URL to fetch a file's raw content from. Private repos are
+    /// not served by the raw content host, so with a token the contents API
+    /// is used instead.
+    fn raw_url(&self, root_sha: &str, file_path: &str) -> Result<Url, CodeImportError> {
+        if self.token.is_some() {
+            let mut url = with_segments(
+                &Url::parse(GITHUB_API_PREFIX)?,
+                [self.owner.as_str(), self.repo.as_str(), "contents"]
+                    .into_iter()
+                    .chain(file_path.split('/')),
+            )?;
+            url.query_pairs_mut().append_pair("ref", root_sha);
+            Ok(url)
+        } else {
+            Ok(Url::parse(
+                format!(
+                    "{}/{}/{}/{}/{}",
+                    GITHUB_RAW_PREFIX, self.owner, self.repo, root_sha, file_path
+                )
+                .as_str(),
+            )?)
+        }
+    }
+
+    /// Adds a changed file as of the head commit, or only its added lines.
+    /// Removed files are left out. Returns true if the listing has become
+    /// full.
+    fn push_changed(
+        &self,
+        listing: &mut RepoListing,
+        head_sha: &str,
+        entry: GitHubChangedFileEntry,
+        added_only: bool,
+    ) -> Result<bool, CodeImportError> {
+        if entry.status == "removed" {
+            return Ok(false);
+        }
+        match (entry.patch, added_only) {
+            (Some(patch), true) => {
+                let content = added_lines(&patch);
+                Ok(!content.is_empty()
+                    && listing.push_local(format!("{}/{}", self.repo, entry.filename), content))
+            }
+            // whole file if its patch is not given
+            _ => self.push_blob(listing, head_sha, &entry.filename, None),
+        }
+    }
+
+    /// Gathers the added lines of files changed in a commit.
+    async fn list_commit(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+        sha: &str,
+    ) -> Result<(), CodeImportError> {
+        let commit_url = with_segments(
+            &Url::parse(GITHUB_API_PREFIX)?,
+            [self.owner.as_str(), self.repo.as_str(), "commits", sha],
+        )?;
+        let mut page = 1;
+        loop {
+            let mut url = commit_url.clone();
+            url.query_pairs_mut()
+                .append_pair("per_page", &GITHUB_FILES_PAGE_SIZE.to_string())
+                .append_pair("page", &page.to_string());
+            let response = client
+                .read_untracked()
+                .get(proxied(url.as_str()))
+                .headers(self.api_headers("application/vnd.github+json")?)
+                .send()
+                .await?;
+
+            listing.record_rate_limit(&response);
+            self.check_status(&response, "commit query")?;
+            let resp = response.json::<GitHubCommitResponse>().await?;
+            let num_entries = resp.files.len();
+
+            for entry in resp.files {
+                if self.push_changed(listing, &resp.sha, entry, true)? {
+                    return Ok(());
+                }
+            }
+
+            if num_entries < GITHUB_FILES_PAGE_SIZE {
+                break;
+            }
+            page += 1;
+        }
+
+        if listing.files.is_empty() {
+            return Err(CodeImportError::github(format!(
+                "commit '{}' does not add or change any code files",
+                sha
+            )));
+        }
+        Ok(())
+    }
+
+    /// Gathers the added lines of files changed between the base and head of
+    /// a comparison, as of the head.
+    async fn list_compare(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+        spec: &str,
+    ) -> Result<(), CodeImportError> {
+        let (_, head) = spec
+            .split_once("...")
+            .or_else(|| spec.split_once(".."))
+            .ok_or_else(|| {
+                CodeImportError::github("compare URL must be of the form '<base>...<head>'")
+            })?;
+        let head_sha = self
+            .lookup_commit(client, listing, head.split('/'))
+            .await?
+            .ok_or_else(|| {
+                CodeImportError::github(format!(
+                    "head '{}' not found in repo '{}/{}'",
+                    head, self.owner, self.repo
+                ))
+            })?;
+
+        let url = with_segments(
+            &Url::parse(GITHUB_API_PREFIX)?,
+            [self.owner.as_str(), self.repo.as_str(), "compare"]
+                .into_iter()
+                .chain(spec.split('/')),
+        )?;
+        let response = client
+            .read_untracked()
+            .get(proxied(url.as_str()))
+            .headers(self.api_headers("application/vnd.github+json")?)
+            .send()
+            .await?;
+
+        listing.record_rate_limit(&response);
+        self.check_status(&response, "comparison query")?;
+        // GitHub lists at most 300 changed files of a comparison
+        for entry in response.json::<GitHubCompareResponse>().await?.files {
+            if self.push_changed(listing, &head_sha, entry, true)? {
+                return Ok(());
+            }
+        }
+
+        if listing.files.is_empty() {
+            return Err(CodeImportError::github(format!(
+                "comparison '{}' does not add or change any code files",
+                spec
+            )));
+        }
+        Ok(())
+    }
+
+    /// Gathers the files changed in a pull request, as of its head commit.
+    /// Removed files are left out.
+    async fn list_pull(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+        number: u64,
+    ) -> Result<(), CodeImportError> {
+        let pull_url = with_segments(
+            &Url::parse(GITHUB_API_PREFIX)?,
+            [
+                self.owner.as_str(),
+                self.repo.as_str(),
+                "pulls",
+                &number.to_string(),
+            ],
+        )?;
+        let response = client
+            .read_untracked()
+            .get(proxied(pull_url.as_str()))
+            .headers(self.api_headers("application/vnd.github+json")?)
+            .send()
+            .await?;
+
+        listing.record_rate_limit(&response);
+        self.check_status(&response, "pull request query")?;
+        let head_sha = response.json::<GitHubPullResponse>().await?.head.sha;
+
+        let files_url = with_segments(&pull_url, ["files"])?;
+        let mut page = 1;
+        loop {
+            let mut url = files_url.clone();
+            url.query_pairs_mut()
+                .append_pair("per_page", &GITHUB_FILES_PAGE_SIZE.to_string())
+                .append_pair("page", &page.to_string());
+            let response = client
+                .read_untracked()
+                .get(proxied(url.as_str()))
+                .headers(self.api_headers("application/vnd.github+json")?)
+                .send()
+                .await?;
+
+            listing.record_rate_limit(&response);
+            self.check_status(&response, "pull request files listing")?;
+            let entries = response.json::<Vec<GitHubChangedFileEntry>>().await?;
+            let num_entries = entries.len();
+
+            for entry in entries {
+                if self.push_changed(listing, &head_sha, entry, self.added_only)? {
+                    return Ok(());
+                }
+            }
+
+            if num_entries < GITHUB_FILES_PAGE_SIZE {
+                break;
+            }
+            page += 1;
+        }
+
+        if listing.files.is_empty() {
+            return Err(CodeImportError::github(format!(
+                "pull request #{} does not add or change any code files",
+                number
+            )));
+        }
+        Ok(())
+    }
+
+    /// Locate the tarball of the ref to import, for whole-repo and directory
+    /// targets; diffs and single files are still fetched per file.
+    pub(crate) async fn archive(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+    ) -> Result<Option<RemoteArchive>, CodeImportError> {
+        let (tree, path) = match &self.target {
+            Target::Default => (self.default_branch(client, listing).await?, String::new()),
+            Target::Tree(segs) => self.resolve_ref(client, listing, segs, false).await?,
+            _ => return Ok(None),
+        };
+        let url = with_segments(
+            &Url::parse(GITHUB_API_PREFIX)?,
+            [self.owner.as_str(), self.repo.as_str(), "tarball", &tree],
+        )?;
+        Ok(Some(
+            RemoteArchive::new(
+                url,
+                ArchiveFormat::TarGz,
+                self.api_headers("application/vnd.github+json")?,
+            )
+            .rooted(&self.repo, path),
+        ))
+    }
+
+    /// Make a "Get a tree" API request, listing subtrees too if recursive.
+    async fn get_tree(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+        tree: &str,
+        recursive: bool,
+    ) -> Result<GitHubGetTreeResponse, CodeImportError> {
+        let mut url = with_segments(
+            &Url::parse(GITHUB_API_PREFIX)?,
+            [
+                self.owner.as_str(),
+                self.repo.as_str(),
+                "git",
+                "trees",
+                tree,
+            ],
+        )?;
+        if recursive {
+            url.query_pairs_mut().append_pair("recursive", "1");
+        }
+        let response = client
+            .read_untracked()
+            .get(proxied(url.as_str()))
+            .headers(self.api_headers("application/vnd.github+json")?)
+            .send()
+            .await?;
+
+        listing.record_rate_limit(&response);
+        self.check_status(&response, "repo URL listing")?;
+        Ok(response.json::<GitHubGetTreeResponse>().await?)
+    }
+
+    /// Adds a blob entry to the listing. Returns true if the listing has
+    /// become full.
+    fn push_blob(
+        &self,
+        listing: &mut RepoListing,
+        root_sha: &str,
+        file_path: &str,
+        size: Option<Number>,
+    ) -> Result<bool, CodeImportError> {
+        let raw_url = self.raw_url(root_sha, file_path)?;
+        let approx_size = size.map(|s| s.as_u64().unwrap_or(0)).unwrap_or(0) as usize; // 0 means unclear size
+        Ok(listing.push(format!("{}/{}", self.repo, file_path), raw_url, approx_size))
+    }
+
+    /// List the repo tree in one recursive request. If GitHub truncates that
+    /// listing, the directories it may have cut short are listed one level at
+    /// a time, and the subtrees missing from it are listed recursively in turn.
+    pub(crate) async fn list(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+    ) -> Result<(), CodeImportError> {
+        if self.token.is_some() {
+            listing.headers = self.api_headers("application/vnd.github.raw+json")?;
+        }
+
+        // record the commit or repo tree root SHA1 value to make composing raw
+        // content URLs easier
+        let mut root_sha = String::new();
+
+        let (path, tree) = match &self.target {
+            Target::Default => ("".to_string(), self.default_branch(client, listing).await?),
+
+            Target::Tree(segs) => {
+                let (commit_sha, path) = self.resolve_ref(client, listing, segs, false).await?;
+                let tree = if path.is_empty() {
+                    commit_sha.clone()
+                } else {
+                    self.subtree_sha(client, listing, &commit_sha, &path)
+                        .await?
+                };
+                root_sha = commit_sha;
+                (path, tree)
+            }
+
+            Target::Blob(segs) => {
+                let (commit_sha, path) = self.resolve_ref(client, listing, segs, true).await?;
+                self.push_blob(listing, &commit_sha, &path, None)?;
+                if listing.files.is_empty() {
+                    return Err(CodeImportError::github(format!(
+                        "file '{}' is not a recognized code file",
+                        path
+                    )));
+                }
+                return Ok(());
+            }
+
+            Target::Pull(number) => return self.list_pull(client, listing, *number).await,
+            Target::Commit(sha) => return self.list_commit(client, listing, sha).await,
+            Target::Compare(spec) => return self.list_compare(client, listing, spec).await,
+        };
+        let mut queue = VecDeque::new();
+        queue.push_back((path, tree));
+
+        while let Some((path, tree)) = queue.pop_front() {
+            let resp = self.get_tree(client, listing, &tree, true).await?;
+            if root_sha.is_empty() {
+                root_sha = resp.sha.clone();
+            }
+
+            // entries come in pre-order, so a truncated listing is complete
+            // for every subtree it has moved past; the ones on the way to its
+            // last entry, or listed without any of their entries, may not be
+            let entries = resp.tree;
+            let mut incomplete = Vec::new();
+            if resp.truncated {
+                incomplete.push(("".to_string(), resp.sha));
+                let last = entries.last().map_or("", |entry| entry.path.as_str());
+                for (i, entry) in entries.iter().enumerate() {
+                    if entry.o_type != "tree" {
+                        continue;
+                    }
+                    let prefix = format!("{}/", entry.path);
+                    let complete = entries
+                        .get(i + 1)
+                        .is_some_and(|next| next.path.starts_with(&prefix))
+                        && !last.starts_with(&prefix);
+                    if !complete {
+                        incomplete.push((entry.path.clone(), entry.sha.clone()));
+                    }
+                }
+            }
+            let listed = if resp.truncated {
+                entries.iter().map(|entry| entry.path.clone()).collect()
+            } else {
+                HashSet::new()
+            };
+
+            for entry in entries {
+                // subtrees are listed recursively; submodules ignored
+                if entry.o_type == "blob"
+                    && self.push_blob(
+                        listing,
+                        &root_sha,
+                        &join_path(&path, &entry.path),
+                        entry.size,
+                    )?
+                {
+                    return Ok(());
+                }
+            }
+
+            for (dir, sha) in incomplete {
+                let resp = self.get_tree(client, listing, &sha, false).await?;
+                for entry in resp.tree {
+                    let entry_path = join_path(&dir, &entry.path);
+                    if listed.contains(&entry_path) {
+                        continue;
+                    }
+                    match entry.o_type.as_str() {
+                        "blob"
+                            if self.push_blob(
+                                listing,
+                                &root_sha,
+                                &join_path(&path, &entry_path),
+                                entry.size,
+                            )? =>
+                        {
+                            return Ok(());
+                        }
+
+                        "tree" => {
+                            // subtree missing from the listing, list it on its own
+                            queue.push_back((join_path(&path, &entry_path), entry.sha));
+                        }
+
+                        _ => {} // submodules ignored
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Headers of GitHub REST API requests, of the given media type.
+pub(super) fn api_headers(
+    token: Option<&str>,
+    media_type: &str,
+) -> Result<HeaderMap, CodeImportError> {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT, media_type.parse()?);
+    headers.insert("X-GitHub-Api-Version", "2022-11-28".parse()?);
+    if let Some(token) = token {
+        headers.insert(AUTHORIZATION, format!("Bearer {}", token).parse()?);
+    }
+    Ok(headers)
+}
@@ -0,0 +1,305 @@
+//! Forge backend for GitLab repos, on gitlab.com or self-hosted.
+
+use leptos::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use url::{form_urlencoded, Url};
+
+use reqwest::{Client, StatusCode};
+
+use reqwest::header::HeaderMap;
+
+use crate::file::archive::{ArchiveFormat, RemoteArchive};
+use crate::file::forge::{host_matches, origin_of, with_segments, RepoListing};
+use crate::file::RemoteConf;
+use crate::utils::error::CodeImportError;
+use crate::utils::proxy::proxied;
+
+/// GitLab SaaS host, always recognized; self-hosted ones are configured.
+const GITLAB_HOST_STR: &str = "gitlab.com";
+
+/// GitLab API path prefix, under the instance's host.
+const GITLAB_API_PATH: &str = "api/v4/projects";
+
+/// Max entries per page of repo tree listing allowed by GitLab.
+const GITLAB_TREE_PAGE_SIZE: usize = 100;
+
+/// GitLab API repo tree listing entry struct.
+#[derive(Serialize, Deserialize)]
+struct GitLabTreeEntry {
+    #[serde(rename = "type")]
+    o_type: String,
+    path: String,
+}
+
+/// GitLab API project metadata response body.
+#[derive(Serialize, Deserialize)]
+struct GitLabProjectResponse {
+    id: u64,
+    path_with_namespace: String,
+    default_branch: Option<String>, // null for empty projects
+}
+
+/// GitLab API single commit response body.
+#[derive(Serialize, Deserialize)]
+struct GitLabCommitResponse {
+    id: String,
+}
+
+/// GitLab repo (project), with the part to import given in the URL.
+pub(crate) struct Repo {
+    origin: Url,
+    /// Full project path, which may span nested groups.
+    project: String,
+    /// URL segments after "/-/tree/": a ref, optionally followed by a
+    /// directory path within. Refs may contain slashes, so these are kept
+    /// unsplit until the ref is resolved. Empty for the default branch.
+    tree: Vec<String>,
+}
+
+impl Repo {
+    /// Parse a user-supplied GitLab repo URL into the project path and the
+    /// ref with directory path. The host must be gitlab.com or one of the
+    /// self-hosted instances in `conf`; if not, return None.
+    pub(crate) fn from_url(url: &Url, conf: &RemoteConf) -> Result<Option<Self>, CodeImportError> {
+        if !host_matches(url, GITLAB_HOST_STR, &conf.gitlab_hosts()) {
+            return Ok(None);
+        }
+
+        let segs = url
+            .path_segments()
+            .ok_or_else(|| CodeImportError::parse("invalid URL path to GitLab repo"))?
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<&str>>();
+
+        // project path ends where the "/-/" separator begins
+        let (project, rest) = match segs.iter().position(|&s| s == "-") {
+            Some(pos) => (&segs[..pos], &segs[pos + 1..]),
+            None => (&segs[..], &[][..]),
+        };
+        if project.len() < 2 {
+            return Err(CodeImportError::gitlab(
+                "repo URL must contain namespace and project name",
+            ));
+        }
+        let project = project.join("/");
+        let project = project.strip_suffix(".git").unwrap_or(&project).to_string();
+
+        let tree = match rest {
+            [] => vec![],
+            ["tree", _, ..] => rest[1..].iter().map(|s| s.to_string()).collect(),
+            _ => {
+                return Err(CodeImportError::gitlab(
+                    "URL should point to a repo, or a directory in it",
+                ))
+            }
+        };
+
+        Ok(Some(Repo {
+            origin: origin_of(url)?,
+            project,
+            tree,
+        }))
+    }
+
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        self.project.rsplit('/').next().unwrap_or(&self.project)
+    }
+
+    /// Returns the API URL of the project, or of something under it.
+    fn api_url(&self, project_id: &str, subpath: &[&str]) -> Result<Url, CodeImportError> {
+        with_segments(
+            &self.origin,
+            GITLAB_API_PATH
+                .split('/')
+                .chain([project_id])
+                .chain(subpath.iter().copied()),
+        )
+    }
+
+    /// Query the project metadata by its path.
+    async fn query_project(
+        &self,
+        client: RwSignal<Client>,
+    ) -> Result<GitLabProjectResponse, CodeImportError> {
+        // the path itself serves as the ID, with slashes encoded
+        let response = client
+            .read_untracked()
+            .get(proxied(self.api_url(&self.project, &[])?.as_str()))
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND
+            || response.status() == StatusCode::UNAUTHORIZED
+        {
+            return Err(CodeImportError::gitlab(format!(
+                "project '{}' not found, or is private",
+                self.project
+            )));
+        } else if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Err(CodeImportError::gitlab(format!(
+                "project metadata query failed with: {}, rate limited?",
+                response.status()
+            )));
+        } else if !response.status().is_success() {
+            return Err(CodeImportError::gitlab(format!(
+                "project metadata query failed with: {}",
+                response.status()
+            )));
+        }
+        Ok(response.json::<GitLabProjectResponse>().await?)
+    }
+
+    /// Query the commit SHA1 that a ref resolves to. Returns None if it is not
+    /// a ref of the project.
+    async fn lookup_commit(
+        &self,
+        client: RwSignal<Client>,
+        project: &GitLabProjectResponse,
+        tree: &str,
+    ) -> Result<Option<String>, CodeImportError> {
+        let url = self.api_url(&project.id.to_string(), &["repository", "commits", tree])?;
+        let response = client
+            .read_untracked()
+            .get(proxied(url.as_str()))
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        } else if !response.status().is_success() {
+            return Err(CodeImportError::gitlab(format!(
+                "ref lookup failed with: {}",
+                response.status()
+            )));
+        }
+        Ok(Some(response.json::<GitLabCommitResponse>().await?.id))
+    }
+
+    /// Returns the ref to import and the directory path within. Without a ref
+    /// in the URL, that is the default branch. Otherwise, the URL segments are
+    /// split into a ref and a path, trying the shortest ref first, and the ref
+    /// is returned as the commit SHA1 it resolves to.
+    async fn resolve_tree(
+        &self,
+        client: RwSignal<Client>,
+        project: &GitLabProjectResponse,
+    ) -> Result<(String, String), CodeImportError> {
+        if self.tree.is_empty() {
+            return match &project.default_branch {
+                Some(branch) => Ok((branch.clone(), String::new())),
+                None => Err(CodeImportError::gitlab(format!(
+                    "project '{}' has no default branch, is it empty?",
+                    project.path_with_namespace
+                ))),
+            };
+        }
+
+        for ref_len in 1..=self.tree.len() {
+            let tree = self.tree[..ref_len].join("/");
+            if let Some(commit_sha) = self.lookup_commit(client, project, &tree).await? {
+                return Ok((commit_sha, self.tree[ref_len..].join("/")));
+            }
+            // not a ref, try a longer one
+        }
+
+        Err(CodeImportError::gitlab(format!(
+            "no branch, tag, or commit of project '{}' matches the URL",
+            project.path_with_namespace
+        )))
+    }
+
+    /// Locate the tarball of the ref to import.
+    pub(crate) async fn archive(
+        &self,
+        client: RwSignal<Client>,
+    ) -> Result<Option<RemoteArchive>, CodeImportError> {
+        let project = self.query_project(client).await?;
+        let (tree, path) = self.resolve_tree(client, &project).await?;
+        let mut url = self.api_url(&project.id.to_string(), &["repository", "archive.tar.gz"])?;
+        url.query_pairs_mut().append_pair("sha", &tree);
+        Ok(Some(
+            RemoteArchive::new(url, ArchiveFormat::TarGz, HeaderMap::new())
+                .rooted(self.name(), path),
+        ))
+    }
+
+    /// Page through the recursive repo tree listing, gathering code files into
+    /// the listing. GitLab does not report file sizes in listings, so those
+    /// are left unclear and checked when fetched.
+    pub(crate) async fn list(
+        &self,
+        client: RwSignal<Client>,
+        listing: &mut RepoListing,
+    ) -> Result<(), CodeImportError> {
+        let project = self.query_project(client).await?;
+        let (tree, path) = self.resolve_tree(client, &project).await?;
+
+        let project_id = project.id.to_string();
+        let tree_url = self.api_url(&project_id, &["repository", "tree"])?;
+        let mut page = Some("1".to_string());
+
+        while let Some(this_page) = page.take() {
+            let mut url = tree_url.clone();
+            let mut query = form_urlencoded::Serializer::new(String::new());
+            query.append_pair("ref", &tree);
+            if !path.is_empty() {
+                query.append_pair("path", &path);
+            }
+            url.set_query(Some(
+                &query
+                    .append_pair("recursive", "true")
+                    .append_pair("per_page", &GITLAB_TREE_PAGE_SIZE.to_string())
+                    .append_pair("page", &this_page)
+                    .finish(),
+            ));
+            let response = client
+                .read_untracked()
+                .get(proxied(url.as_str()))
+                .send()
+                .await?;
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                return Err(CodeImportError::gitlab(format!(
+                    "repo URL listing failed with: {}, rate limited?",
+                    response.status()
+                )));
+            } else if !response.status().is_success() {
+                return Err(CodeImportError::gitlab(format!(
+                    "repo URL listing failed with: {}",
+                    response.status()
+                )));
+            }
+
+            // empty header on the last page
+            page = response
+                .headers()
+                .get("x-next-page")
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty());
+            let entries = response.json::<Vec<GitLabTreeEntry>>().await?;
+
+            for entry in entries {
+                // subtrees are listed recursively; submodules ignored
+                if entry.o_type != "blob" {
+                    continue;
+                }
+                // through the API, as "/-/raw/" URLs send no CORS headers;
+                // the file path is a single segment, with slashes encoded
+                let mut raw_url =
+                    self.api_url(&project_id, &["repository", "files", &entry.path, "raw"])?;
+                raw_url.query_pairs_mut().append_pair("ref", &tree);
+                let display_path = format!("{}/{}", self.name(), entry.path);
+                // 0 means unclear size
+                if listing.push(display_path, raw_url, 0) {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
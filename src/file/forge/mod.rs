@@ -0,0 +1,289 @@
+//! Code hosting forges that repos can be imported from. Each forge backend
+//! recognizes its own repo URLs and lists the code files in a repo along with
+//! their raw content URLs, or with their changed lines for diff imports.
+
+use leptos::prelude::*;
+
+use url::Url;
+
+use reqwest::header::HeaderMap;
+use reqwest::{Client, Response};
+
+use crate::file::archive::RemoteArchive;
+use crate::file::suffix::LANGUAGE_MAP;
+use crate::file::{CodeFile, RemoteConf, MAX_FILE_SIZE, MAX_NUM_FILES};
+use crate::utils::error::CodeImportError;
+
+mod bitbucket;
+mod gist;
+mod gitea;
+mod github;
+mod gitlab;
+
+/// Files gathered from a repo listing, under the import limits.
+pub(crate) struct RepoListing {
+    /// Display path and handle of each file.
+    pub(crate) files: Vec<(String, CodeFile)>,
+    /// Whether any file was skipped for being too large.
+    pub(crate) skipped: bool,
+    /// Headers needed to fetch the raw contents, e.g. for authorization.
+    headers: HeaderMap,
+    /// API rate-limit budget left after listing, if the forge reports it.
+    pub(crate) rate_limit: Option<RateLimit>,
+    /// Number of files gathered so far, shown while listing.
+    progress: RwSignal<usize>,
+}
+
+impl RepoListing {
+    fn new(progress: RwSignal<usize>) -> Self {
+        progress.set(0);
+        RepoListing {
+            files: Vec::new(),
+            skipped: false,
+            headers: HeaderMap::new(),
+            rate_limit: None,
+            progress,
+        }
+    }
+
+    /// Adds a remote file if it is a code file and is not too large, where an
+    /// approximate size of 0 means unclear. Returns true if the listing has
+    /// become full.
+    fn push(&mut self, path: String, raw_url: Url, approx_size: usize) -> bool {
+        if let Some(ext) = code_ext(&path) {
+            if approx_size > MAX_FILE_SIZE {
+                self.skipped = true;
+            } else {
+                let file = CodeFile::new_remote(
+                    ext.to_string(),
+                    raw_url,
+                    approx_size,
+                    self.headers.clone(),
+                );
+                self.add(path, file);
+            }
+        }
+        self.is_full()
+    }
+
+    /// Adds a file of known content, e.g. lines taken from a diff, if it is a
+    /// code file and is not too large. Returns true if the listing has become
+    /// full.
+    fn push_local(&mut self, path: String, content: String) -> bool {
+        if let Some(ext) = code_ext(&path) {
+            if content.len() > MAX_FILE_SIZE {
+                self.skipped = true;
+            } else {
+                let file = CodeFile::new_local(ext.to_string(), content);
+                self.add(path, file);
+            }
+        }
+        self.is_full()
+    }
+
+    fn add(&mut self, path: String, file: CodeFile) {
+        self.files.push((path, file));
+        self.progress.set(self.files.len());
+    }
+
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.files.len() >= MAX_NUM_FILES
+    }
+
+    /// Records the rate-limit budget from the standard "x-ratelimit-*"
+    /// headers of an API response, if present.
+    fn record_rate_limit(&mut self, response: &Response) {
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+        if let (Some(remaining), Some(limit)) =
+            (header("x-ratelimit-remaining"), header("x-ratelimit-limit"))
+        {
+            self.rate_limit = Some(RateLimit { remaining, limit });
+        }
+    }
+}
+
+/// API rate-limit budget of a forge, as of the last request.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct RateLimit {
+    pub(crate) remaining: u64,
+    pub(crate) limit: u64,
+}
+
+/// "Generic" repo on one of the supported forges.
+pub(crate) enum RepoSource {
+    GitHub(github::Repo),
+    GitLab(gitlab::Repo),
+    Gitea(gitea::Repo),
+    Bitbucket(bitbucket::Repo),
+    Gist(gist::Gist),
+}
+
+impl RepoSource {
+    /// Detects the forge from the URL and parses it as a repo URL there.
+    /// Returns `None` if the URL is not on any known forge.
+    pub(crate) fn detect(url: &Url, conf: &RemoteConf) -> Result<Option<Self>, CodeImportError> {
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(CodeImportError::parse(format!(
+                "unsupported URL scheme: {}",
+                url.scheme()
+            )));
+        }
+
+        if let Some(gist) = gist::Gist::from_url(url, conf)? {
+            Ok(Some(Self::Gist(gist)))
+        } else if let Some(repo) = github::Repo::from_url(url, conf)? {
+            Ok(Some(Self::GitHub(repo)))
+        } else if let Some(repo) = gitlab::Repo::from_url(url, conf)? {
+            Ok(Some(Self::GitLab(repo)))
+        } else if let Some(repo) = gitea::Repo::from_url(url, conf)? {
+            Ok(Some(Self::Gitea(repo)))
+        } else if let Some(repo) = bitbucket::Repo::from_url(url)? {
+            Ok(Some(Self::Bitbucket(repo)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the repo name, used as the root of imported file paths.
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            Self::GitHub(repo) => repo.name(),
+            Self::GitLab(repo) => repo.name(),
+            Self::Gitea(repo) => repo.name(),
+            Self::Bitbucket(repo) => repo.name(),
+            Self::Gist(gist) => gist.name(),
+        }
+    }
+
+    /// Lists the repo's code files, taking at most MAX_NUM_FILES and skipping
+    /// any file known to be larger than MAX_FILE_SIZE. The number of files
+    /// gathered so far is kept in `progress`.
+    pub(crate) async fn list_files(
+        &self,
+        client: RwSignal<Client>,
+        progress: RwSignal<usize>,
+    ) -> Result<RepoListing, CodeImportError> {
+        let mut listing = RepoListing::new(progress);
+        match self {
+            Self::GitHub(repo) => repo.list(client, &mut listing).await?,
+            Self::GitLab(repo) => repo.list(client, &mut listing).await?,
+            Self::Gitea(repo) => repo.list(client, &mut listing).await?,
+            Self::Bitbucket(repo) => repo.list(client, &mut listing).await?,
+            Self::Gist(gist) => gist.list(client, &mut listing).await?,
+        }
+
+        if listing.files.is_empty() {
+            Err(self.error(format!(
+                "repo '{}' does not contain any code files",
+                self.name()
+            )))
+        } else {
+            Ok(listing)
+        }
+    }
+
+    /// Locates the archive download of the ref to import, if the forge offers
+    /// one for what the URL points to.
+    pub(crate) async fn archive(
+        &self,
+        client: RwSignal<Client>,
+        progress: RwSignal<usize>,
+    ) -> Result<Option<RemoteArchive>, CodeImportError> {
+        let mut listing = RepoListing::new(progress);
+        let archive = match self {
+            Self::GitHub(repo) => repo.archive(client, &mut listing).await?,
+            Self::GitLab(repo) => repo.archive(client).await?,
+            Self::Gitea(repo) => repo.archive(client).await?,
+            Self::Bitbucket(repo) => repo.archive(client).await?,
+            Self::Gist(_) => None,
+        };
+        Ok(archive.map(|mut archive| {
+            archive.rate_limit = listing.rate_limit;
+            archive
+        }))
+    }
+
+    /// Wraps a message into the error type of my forge.
+    pub(crate) fn error(&self, msg: impl ToString) -> CodeImportError {
+        match self {
+            Self::GitHub(_) => CodeImportError::github(msg),
+            Self::GitLab(_) => CodeImportError::gitlab(msg),
+            Self::Gitea(_) => CodeImportError::gitea(msg),
+            Self::Bitbucket(_) => CodeImportError::bitbucket(msg),
+            Self::Gist(_) => CodeImportError::github(msg),
+        }
+    }
+}
+
+/// Returns the extension of a path if it is that of a code file.
+fn code_ext(path: &str) -> Option<&str> {
+    let dot_pos = path.rfind('.')?;
+    let extension = &path[dot_pos..];
+    (!extension.is_empty() && LANGUAGE_MAP.contains_key(extension)).then_some(extension)
+}
+
+/// Gathers the added lines of a unified diff patch, with each run of them
+/// separated by a blank line. Returns an empty string if nothing was added.
+fn added_lines(patch: &str) -> String {
+    let mut content = String::new();
+    let mut in_added_run = false;
+    for line in patch.lines() {
+        if let Some(added) = line.strip_prefix('+') {
+            if !in_added_run && !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(added);
+            content.push('\n');
+            in_added_run = true;
+        } else if line.starts_with('@') || line.starts_with(' ') || line.starts_with('-') {
+            in_added_run = false;
+        }
+    }
+    content
+}
+
+/// Joins a directory path and an entry name within it.
+fn join_path(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Returns a copy of the URL with the given path segments appended, each
+/// percent-encoded as needed.
+fn with_segments<'a>(
+    url: &Url,
+    segments: impl IntoIterator<Item = &'a str>,
+) -> Result<Url, CodeImportError> {
+    let mut url = url.clone();
+    url.path_segments_mut()
+        .map_err(|_| CodeImportError::parse("URL cannot carry a path"))?
+        .pop_if_empty()
+        .extend(segments);
+    Ok(url)
+}
+
+/// Returns true if the URL's host is the given one or one of the configured
+/// self-hosted ones.
+fn host_matches(url: &Url, host: &str, self_hosted: &[&str]) -> bool {
+    url.host_str().is_some_and(|url_host| {
+        url_host.eq_ignore_ascii_case(host)
+            || self_hosted
+                .iter()
+                .any(|self_host| self_host.eq_ignore_ascii_case(url_host))
+    })
+}
+
+/// Returns the scheme, host and port part of the URL, with no path.
+fn origin_of(url: &Url) -> Result<Url, CodeImportError> {
+    Ok(Url::parse(&url.origin().ascii_serialization())?)
+}
@@ -9,15 +9,28 @@ use gloo_file::FileList;
 
 use url::{ParseError, Url};
 
+use reqwest::header::HeaderMap;
 use reqwest::Client;
 
+pub(crate) use crate::file::batch::parse_source_list;
+use crate::file::forge::{RateLimit, RepoSource};
+use crate::file::registry::Package;
+use crate::file::remote::RemoteFile;
 use crate::utils::error::CodeImportError;
+use crate::utils::proxy::proxied;
 
+mod archive;
+mod batch;
+mod forge;
+mod registry;
 mod remote;
 mod upload;
 
 mod suffix;
 
+#[cfg(test)]
+mod tests;
+
 // Hardcoded limits on the scale of imported files.
 pub(crate) const MAX_NUM_FILES: usize = 100;
 pub(crate) const MAX_FILE_SIZE: usize = 100 * 1024; // 100KB
@@ -26,12 +39,60 @@ pub(crate) const MAX_FILE_SIZE: usize = 100 * 1024; // 100KB
 const PATH_LENGTH_CUTOFF: usize = 36;
 const LANG_LENGTH_CUTOFF: usize = 10;
 
+/// Settings for importing from code hosts, entered in Step 2.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub(crate) struct RemoteConf {
+    /// Hosts of self-hosted GitLab instances, separated by commas or spaces.
+    pub(crate) gitlab_hosts: String,
+    /// Hosts of self-hosted Gitea/Forgejo instances, likewise separated.
+    pub(crate) gitea_hosts: String,
+    /// GitHub personal access token, for private repos and higher rate limits.
+    pub(crate) github_token: String,
+    /// Whether diff imports, e.g. of pull requests, take only the added lines.
+    pub(crate) diff_added_only: bool,
+    /// Whether repos are downloaded as one archive instead of file by file.
+    pub(crate) use_archive: bool,
+}
+
+impl RemoteConf {
+    /// Returns the configured self-hosted GitLab hosts.
+    pub(crate) fn gitlab_hosts(&self) -> Vec<&str> {
+        Self::split_hosts(&self.gitlab_hosts)
+    }
+
+    /// Returns the configured self-hosted Gitea/Forgejo hosts.
+    pub(crate) fn gitea_hosts(&self) -> Vec<&str> {
+        Self::split_hosts(&self.gitea_hosts)
+    }
+
+    /// Returns the GitHub token if one is entered.
+    pub(crate) fn github_token(&self) -> Option<&str> {
+        Some(self.github_token.trim()).filter(|token| !token.is_empty())
+    }
+
+    /// Splits a user-entered host list, tolerating schemes and trailing slashes.
+    fn split_hosts(hosts: &str) -> Vec<&str> {
+        hosts
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .map(|host| host.trim_end_matches('/'))
+            .map(|host| host.split_once("://").map_or(host, |(_, host)| host))
+            .filter(|host| !host.is_empty())
+            .collect()
+    }
+}
+
 /// Handle to a single code file.
 pub(crate) enum CodeFile {
     /// Content of a local file.
     Local { ext: String, content: String },
-    /// URL to a raw file.
-    Remote { url: Url, approx_size: usize },
+    /// URL to a raw file, with any headers needed to fetch it. The extension
+    /// is that of the file path, which API URLs may not end with.
+    Remote {
+        ext: String,
+        url: Url,
+        approx_size: usize,
+        headers: HeaderMap,
+    },
 }
 
 impl CodeFile {
@@ -39,8 +100,13 @@ impl CodeFile {
         CodeFile::Local { ext, content }
     }
 
-    fn new_remote(url: Url, approx_size: usize) -> Self {
-        CodeFile::Remote { url, approx_size }
+    fn new_remote(ext: String, url: Url, approx_size: usize, headers: HeaderMap) -> Self {
+        CodeFile::Remote {
+            ext,
+            url,
+            approx_size,
+            headers,
+        }
     }
 
     /// Returns the (approximate) size in bytes of the file.
@@ -60,8 +126,7 @@ impl CodeFile {
     /// Returns the file extension.
     pub(crate) fn get_ext(&self) -> Option<&str> {
         match self {
-            CodeFile::Local { ext, .. } => Some(ext),
-            CodeFile::Remote { url, .. } => CodeGroup::get_url_extension(url).ok(),
+            CodeFile::Local { ext, .. } | CodeFile::Remote { ext, .. } => Some(ext),
         }
     }
 
@@ -90,15 +155,30 @@ impl CodeFile {
     }
 
     /// Fetches the actual content of the text file, making web requests if necessary.
-    pub(crate) async fn content(&self, client: &Client) -> Result<Cow<String>, CodeImportError> {
+    pub(crate) async fn content(
+        &self,
+        client: &Client,
+    ) -> Result<Cow<'_, String>, CodeImportError> {
         match self {
             CodeFile::Local { content, .. } => Ok(Cow::Borrowed(content)),
 
-            CodeFile::Remote { url, .. } => {
-                let resp = client.get(url.clone()).send().await?;
+            CodeFile::Remote { url, headers, .. } => {
+                let resp = client
+                    .get(proxied(url.as_str()))
+                    .headers(headers.clone())
+                    .send()
+                    .await?;
 
                 if resp.status().is_success() {
                     let text = resp.text().await?;
+                    // some listings do not tell sizes ahead of fetching
+                    if text.len() > MAX_FILE_SIZE {
+                        return Err(CodeImportError::limit(format!(
+                            "remote file too large ({}KB >= max {}KB)",
+                            text.len() / 1024,
+                            MAX_FILE_SIZE / 1024
+                        )));
+                    }
                     Ok(Cow::Owned(text))
                 } else {
                     // probably network error or authorization failure
@@ -118,6 +198,8 @@ impl CodeFile {
 pub(crate) struct CodeGroup {
     files: HashMap<String, RwSignal<CodeFile>>,
     skipped: bool,
+    rate_limit: Option<RateLimit>,
+    failures: Vec<(String, CodeImportError)>,
 }
 
 impl CodeGroup {
@@ -126,6 +208,8 @@ impl CodeGroup {
         CodeGroup {
             files: HashMap::new(),
             skipped: false,
+            rate_limit: None,
+            failures: Vec::new(),
         }
     }
 
@@ -141,6 +225,18 @@ impl CodeGroup {
         self.skipped
     }
 
+    /// Get the API rate-limit budget reported by the forge of the last import.
+    #[inline]
+    pub(crate) fn rate_limit(&self) -> Option<&RateLimit> {
+        self.rate_limit.as_ref()
+    }
+
+    /// Get the sources of a batch import that failed, with their errors.
+    #[inline]
+    pub(crate) fn failures(&self) -> &[(String, CodeImportError)] {
+        &self.failures
+    }
+
     /// Get the approximate total size in bytes of imported files.
     pub(crate) fn total_size(&self) -> Option<usize> {
         self.files
@@ -153,6 +249,8 @@ impl CodeGroup {
     pub(crate) fn reset(&mut self) {
         self.files.clear();
         self.skipped = false;
+        self.rate_limit = None;
+        self.failures.clear();
     }
 
     /// Return a sorted, owning collection of the imported files.
@@ -166,12 +264,27 @@ impl CodeGroup {
         files
     }
 
-    /// Populates the importer with a remote file or a repo of files.
+    /// Populates the importer with a remote file or a repo of files. The
+    /// number of repo files listed so far is kept in `progress`.
     pub(crate) async fn import_remote(
         &mut self,
         client: RwSignal<Client>,
         url_str: &str,
+        conf: &RemoteConf,
+        progress: RwSignal<usize>,
     ) -> Result<(), CodeImportError> {
+        // package identifiers like "crates:serde" would parse as URLs
+        if let Some(package) = Package::parse(url_str)? {
+            let archive = package.archive(client).await?;
+            if self.import_archive(client, &archive).await? == 0 {
+                return Err(CodeImportError::registry(format!(
+                    "package '{}' does not contain any code files",
+                    package.name()
+                )));
+            }
+            return Ok(());
+        }
+
         let url = match Url::parse(url_str) {
             Ok(url) => url,
             Err(ParseError::RelativeUrlWithoutBase) => {
@@ -183,23 +296,52 @@ impl CodeGroup {
             }
         };
 
-        // first try as URL to github repo
-        if let Some(path_info_list) = self.list_github_repo(client, &url).await? {
-            for (path, (file_url, approx_size)) in path_info_list {
-                self.add_file(path, CodeFile::new_remote(file_url, approx_size))?;
+        // first try as URL to a repo on one of the known forges
+        if let Some(source) = RepoSource::detect(&url, conf)? {
+            if conf.use_archive {
+                if let Some(archive) = source.archive(client, progress).await? {
+                    self.rate_limit = archive.rate_limit;
+                    if self.import_archive(client, &archive).await? == 0 {
+                        return Err(source.error(format!(
+                            "repo '{}' does not contain any code files",
+                            source.name()
+                        )));
+                    }
+                    return Ok(());
+                }
+            }
+
+            let listing = source.list_files(client, progress).await?;
+            self.skipped |= listing.skipped;
+            self.rate_limit = listing.rate_limit;
+            for (path, file) in listing.files {
+                self.add_file(path, file)?;
             }
             return Ok(());
         }
 
-        // then try as URL to a single raw file
-        if let Some((path, final_url, approx_size)) = self.head_single_file(client, url).await? {
-            self.add_file(path, CodeFile::new_remote(final_url, approx_size))?;
-            return Ok(());
+        // then try as URL to a single raw file or archive
+        match self.head_single_file(client, url).await? {
+            Some(RemoteFile::Code(path, final_url, approx_size)) => {
+                let ext = CodeGroup::get_url_extension(&final_url)?.to_string();
+                self.add_file(
+                    path,
+                    CodeFile::new_remote(ext, final_url, approx_size, HeaderMap::new()),
+                )?;
+                Ok(())
+            }
+            Some(RemoteFile::Archive(archive)) => {
+                if self.import_archive(client, &archive).await? == 0 {
+                    return Err(CodeImportError::exten(
+                        "archive does not contain any code files",
+                    ));
+                }
+                Ok(())
+            }
+            None => Err(CodeImportError::parse(
+                "URL not pointing to raw file, archive, or repo on a known forge",
+            )),
         }
-
-        Err(CodeImportError::parse(
-            "URL not pointing to raw file or GitHub repo",
-        ))
     }
 
     /// Populates the importer with a plain textbox content.
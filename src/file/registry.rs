@@ -0,0 +1,295 @@
+//! Published packages on language package registries, imported from the
+//! source archive that the registry serves for a release.
+//!
+//! References:
+//!   - https://crates.io/data-access
+//!   - https://warehouse.pypa.io/api-reference/json.html
+//!   - https://github.com/npm/registry/blob/main/docs/REGISTRY-API.md
+
+use leptos::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use url::Url;
+
+use reqwest::header::HeaderMap;
+use reqwest::{Client, StatusCode};
+
+use crate::file::archive::{ArchiveFormat, RemoteArchive};
+use crate::utils::error::CodeImportError;
+use crate::utils::proxy::Outbound;
+
+/// crates.io API request URL prefix.
+const CRATES_API_PREFIX: &str = "https://crates.io/api/v1/crates";
+
+/// crates.io static download URL prefix.
+const CRATES_DL_PREFIX: &str = "https://static.crates.io/crates";
+
+/// PyPI JSON API request URL prefix.
+const PYPI_API_PREFIX: &str = "https://pypi.org/pypi";
+
+/// npm registry request URL prefix.
+const NPM_API_PREFIX: &str = "https://registry.npmjs.org";
+
+/// crates.io API crate metadata response body.
+#[derive(Serialize, Deserialize)]
+struct CratesCrateResponse {
+    versions: Vec<CratesVersion>, // newest first
+}
+
+#[derive(Serialize, Deserialize)]
+struct CratesVersion {
+    num: String,
+    yanked: bool,
+}
+
+/// PyPI JSON API release metadata response body.
+#[derive(Serialize, Deserialize)]
+struct PypiReleaseResponse {
+    info: PypiInfo,
+    urls: Vec<PypiFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PypiInfo {
+    version: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PypiFile {
+    packagetype: String,
+    filename: String,
+    url: String,
+}
+
+/// npm registry version metadata response body.
+#[derive(Serialize, Deserialize)]
+struct NpmVersionResponse {
+    version: String,
+    dist: NpmDist,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NpmDist {
+    tarball: String,
+}
+
+/// Supported package registries.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Registry {
+    Crates,
+    PyPI,
+    Npm,
+}
+
+impl Registry {
+    /// Returns the identifier prefix that selects this registry.
+    pub(crate) fn prefix(&self) -> &'static str {
+        match self {
+            Self::Crates => "crates",
+            Self::PyPI => "pypi",
+            Self::Npm => "npm",
+        }
+    }
+}
+
+/// Package release to import, e.g. parsed from "crates:serde@1.0".
+pub(crate) struct Package {
+    registry: Registry,
+    name: String,
+    version: Option<String>,
+    /// Where registry metadata requests go; tests point this at a stand-in.
+    pub(crate) outbound: Outbound,
+}
+
+impl Package {
+    /// Parses a "<registry>:<name>[@<version>]" package identifier. Returns
+    /// None if the input does not start with a known registry prefix.
+    pub(crate) fn parse(input: &str) -> Result<Option<Self>, CodeImportError> {
+        let Some((prefix, spec)) = input.trim().split_once(':') else {
+            return Ok(None);
+        };
+        let Some(registry) = [Registry::Crates, Registry::PyPI, Registry::Npm]
+            .into_iter()
+            .find(|registry| prefix.eq_ignore_ascii_case(registry.prefix()))
+        else {
+            return Ok(None);
+        };
+
+        // scoped npm names start with an '@' themselves
+        let (name, version) = match spec.char_indices().skip(1).find(|&(_, c)| c == '@') {
+            Some((at_pos, _)) => (&spec[..at_pos], Some(spec[at_pos + 1..].to_string())),
+            None => (spec, None),
+        };
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(CodeImportError::registry(format!(
+                "invalid package name '{}'",
+                name
+            )));
+        }
+        if version.as_ref().is_some_and(|version| version.is_empty()) {
+            return Err(CodeImportError::registry("empty version after '@'"));
+        }
+
+        Ok(Some(Package {
+            registry,
+            name: name.to_string(),
+            version,
+            outbound: Outbound::default(),
+        }))
+    }
+
+    /// Returns the package name, used as the root of imported file paths.
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Resolves the package to the source archive of the requested release,
+    /// or of the latest one if no version is given.
+    pub(crate) async fn archive(
+        &self,
+        client: RwSignal<Client>,
+    ) -> Result<RemoteArchive, CodeImportError> {
+        match self.registry {
+            Registry::Crates => self.crates_archive(client).await,
+            Registry::PyPI => self.pypi_archive(client).await,
+            Registry::Npm => self.npm_archive(client).await,
+        }
+    }
+
+    /// Queries registry metadata, treating 404 as an unknown package.
+    async fn query<T: for<'de> Deserialize<'de>>(
+        &self,
+        client: RwSignal<Client>,
+        url: Url,
+    ) -> Result<T, CodeImportError> {
+        let response = client
+            .read_untracked()
+            .get(self.outbound.url(url))
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(CodeImportError::registry(format!(
+                "package '{}' not found on {}",
+                self.spec(),
+                self.registry.prefix()
+            )));
+        } else if !response.status().is_success() {
+            return Err(CodeImportError::registry(format!(
+                "package metadata query failed with: {}",
+                response.status()
+            )));
+        }
+        Ok(response.json::<T>().await?)
+    }
+
+    /// Picks the newest non-yanked crate version matching the requested one,
+    /// where e.g. "1.0" matches "1.0.219", and downloads its .crate file.
+    async fn crates_archive(
+        &self,
+        client: RwSignal<Client>,
+    ) -> Result<RemoteArchive, CodeImportError> {
+        let api_url = format!("{}/{}", CRATES_API_PREFIX, self.name);
+        let resp = self
+            .query::<CratesCrateResponse>(client, Url::parse(&api_url)?)
+            .await?;
+
+        let version = resp
+            .versions
+            .into_iter()
+            .filter(|version| !version.yanked)
+            .map(|version| version.num)
+            .find(|num| match &self.version {
+                Some(wanted) => {
+                    num == wanted
+                        || num
+                            .strip_prefix(wanted.as_str())
+                            .is_some_and(|rest| rest.starts_with('.'))
+                }
+                None => true,
+            })
+            .ok_or_else(|| {
+                CodeImportError::registry(format!(
+                    "no published release of '{}' matches",
+                    self.spec()
+                ))
+            })?;
+
+        let url = Url::parse(&format!(
+            "{}/{}/{}-{}.crate",
+            CRATES_DL_PREFIX, self.name, self.name, version
+        ))?;
+        Ok(
+            RemoteArchive::new(url, ArchiveFormat::TarGz, HeaderMap::new())
+                .rooted(&self.name, String::new()),
+        )
+    }
+
+    /// Looks up the sdist of the requested release.
+    async fn pypi_archive(
+        &self,
+        client: RwSignal<Client>,
+    ) -> Result<RemoteArchive, CodeImportError> {
+        let api_url = match &self.version {
+            Some(version) => format!("{}/{}/{}/json", PYPI_API_PREFIX, self.name, version),
+            None => format!("{}/{}/json", PYPI_API_PREFIX, self.name),
+        };
+        let resp = self
+            .query::<PypiReleaseResponse>(client, Url::parse(&api_url)?)
+            .await?;
+
+        let (file, format) = resp
+            .urls
+            .into_iter()
+            .filter(|file| file.packagetype == "sdist")
+            .find_map(|file| ArchiveFormat::of_name(&file.filename).map(|format| (file, format)))
+            .ok_or_else(|| {
+                CodeImportError::registry(format!(
+                    "release {} of '{}' has no source distribution",
+                    resp.info.version, self.name
+                ))
+            })?;
+
+        Ok(
+            RemoteArchive::new(Url::parse(&file.url)?, format, HeaderMap::new())
+                .rooted(&self.name, String::new()),
+        )
+    }
+
+    /// Looks up the tarball of the requested version or dist-tag.
+    async fn npm_archive(
+        &self,
+        client: RwSignal<Client>,
+    ) -> Result<RemoteArchive, CodeImportError> {
+        let mut api_url = Url::parse(NPM_API_PREFIX)?;
+        api_url
+            .path_segments_mut()
+            .map_err(|_| CodeImportError::parse("URL cannot carry a path"))?
+            .push(&self.name) // scoped names are encoded as "@scope%2Fname"
+            .push(self.version.as_deref().unwrap_or("latest"));
+        let resp = self.query::<NpmVersionResponse>(client, api_url).await?;
+
+        if !resp.dist.tarball.ends_with(".tgz") {
+            return Err(CodeImportError::registry(format!(
+                "release {} of '{}' has no tarball",
+                resp.version, self.name
+            )));
+        }
+        Ok(RemoteArchive::new(
+            Url::parse(&resp.dist.tarball)?,
+            ArchiveFormat::TarGz,
+            HeaderMap::new(),
+        )
+        .rooted(&self.name, String::new()))
+    }
+
+    /// Returns the package identifier as given, for error messages.
+    fn spec(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}@{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! Archive detection, forge URL parsing, package registry resolution, and
+//! batch import tests, the latter ones served through the local HTTP stand-in
+//! server, with recorded registry metadata from `fixtures/`.
+
+use leptos::prelude::*;
+
+use serde_json::Value;
+
+use url::Url;
+
+use reqwest::header::HeaderMap;
+use reqwest::Client;
+
+use crate::file::archive::{ArchiveFormat, RemoteArchive};
+use crate::file::forge::RepoSource;
+use crate::file::registry::Package;
+use crate::file::{parse_source_list, CodeFile, CodeGroup, RemoteConf, MAX_NUM_FILES};
+use crate::utils::error::CodeImportError;
+use crate::utils::standin::{standin_outbound, standin_url, start_standin, RequestLog, Route};
+
+/// Serves the given fixture case at its recorded path, then resolves the
+/// package identifier against it.
+async fn resolve(case: &str, spec: &str) -> (Result<RemoteArchive, CodeImportError>, RequestLog) {
+    let fixtures: Value = serde_json::from_str(include_str!("fixtures/registry.json")).unwrap();
+    let fixture = &fixtures[case];
+    let log = start_standin(vec![Route {
+        method: "GET",
+        path_prefix: fixture["path"].as_str().unwrap().to_string(),
+        status: 200,
+        headers: vec![],
+        body: fixture["body"].to_string(),
+    }])
+    .await;
+
+    let mut package = Package::parse(spec)
+        .unwrap()
+        .unwrap_or_else(|| panic!("'{}' not parsed as a package", spec));
+    package.outbound = standin_outbound();
+    let result = package.archive(RwSignal::new(Client::new())).await;
+    (result, log)
+}
+
+#[test]
+fn detects_archive_formats() {
+    for (name, format) in [
+        ("release-v1.2.zip", Some(ArchiveFormat::Zip)),
+        ("hw3.TAR", Some(ArchiveFormat::Tar)),
+        ("src.tar.gz", Some(ArchiveFormat::TarGz)),
+        ("left-pad-1.3.0.tgz", Some(ArchiveFormat::TarGz)),
+        ("submission.7z", Some(ArchiveFormat::SevenZ)),
+        ("main.rs", None),
+        ("README", None),
+    ] {
+        assert_eq!(ArchiveFormat::of_name(name), format, "{}", name);
+    }
+    assert_eq!(
+        ArchiveFormat::of_mime("application/zip; charset=binary"),
+        Some(ArchiveFormat::Zip)
+    );
+    assert_eq!(ArchiveFormat::of_mime("text/plain"), None);
+}
+
+#[test]
+fn keeps_entry_names_of_unrooted_archives() {
+    let url = Url::parse("https://example.edu/course/hw3.zip").unwrap();
+    let archive = RemoteArchive::new(url, ArchiveFormat::Zip, HeaderMap::new());
+    assert_eq!(
+        archive.import_path("hw3/main.py").as_deref(),
+        Some("hw3/main.py")
+    );
+    assert_eq!(archive.import_path("main.py").as_deref(), Some("main.py"));
+
+    let archive = archive.rooted("repo", "src".to_string());
+    assert_eq!(
+        archive.import_path("repo-main/src/lib.rs").as_deref(),
+        Some("repo/src/lib.rs")
+    );
+    assert_eq!(archive.import_path("repo-main/srcx/lib.rs"), None);
+    assert_eq!(archive.import_path("repo-main/build.rs"), None);
+}
+
+#[test]
+fn parses_gitlab_urls() {
+    let conf = RemoteConf {
+        gitlab_hosts: "https://git.example.edu/".to_string(),
+        ..Default::default()
+    };
+    for (url, name) in [
+        ("https://gitlab.com/alice/hw3", "hw3"),
+        ("https://gitlab.com/cs101/fall/alice/hw3.git", "hw3"),
+        ("https://gitlab.com/alice/hw3/-/tree/feature/x/src", "hw3"),
+        ("https://git.example.edu/alice/hw3", "hw3"),
+    ] {
+        let url = Url::parse(url).unwrap();
+        match RepoSource::detect(&url, &conf) {
+            Ok(Some(source @ RepoSource::GitLab(_))) => assert_eq!(source.name(), name),
+            _ => panic!("'{}' not parsed as a GitLab repo", url),
+        }
+    }
+
+    for url in [
+        "https://gitlab.com/alice",
+        "https://gitlab.com/alice/hw3/-/blob/main/main.py",
+        "https://gitlab.com/alice/hw3/-/tree",
+    ] {
+        let result = RepoSource::detect(&Url::parse(url).unwrap(), &conf);
+        assert!(matches!(result, Err(CodeImportError::GitLab(_))), "{}", url);
+    }
+
+    // self-hosted instances only when configured
+    let url = Url::parse("https://gitlab.example.edu/alice/hw3").unwrap();
+    assert!(!matches!(
+        RepoSource::detect(&url, &RemoteConf::default()),
+        Ok(Some(RepoSource::GitLab(_)))
+    ));
+}
+
+#[tokio::test]
+async fn gitlab_resolves_slashed_ref_and_lists_through_api() {
+    let route = |path: &str, headers: Vec<(String, String)>, body: &str| Route {
+        method: "GET",
+        path_prefix: path.to_string(),
+        status: 200,
+        headers,
+        body: body.to_string(),
+    };
+    let log = start_standin(vec![
+        route(
+            "/api/v4/projects/alice%2Fhw3",
+            vec![],
+            r#"{"id": 42, "path_with_namespace": "alice/hw3", "default_branch": "main"}"#,
+        ),
+        route(
+            "/api/v4/projects/42/repository/commits/feature%2Fx",
+            vec![],
+            r#"{"id": "c0ffee"}"#,
+        ),
+        route(
+            "/api/v4/projects/42/repository/tree?",
+            vec![("x-next-page".to_string(), String::new())],
+            r#"[{"type": "tree", "path": "src/util"},
+                {"type": "blob", "path": "src/util/io.py"},
+                {"type": "blob", "path": "src/README.md"}]"#,
+        ),
+    ])
+    .await;
+
+    // "feature" is not a ref, and "src" is a directory within "feature/x"
+    let url = standin_url("https://git.example.edu/alice/hw3/-/tree/feature/x/src");
+    let conf = RemoteConf {
+        gitlab_hosts: "127.0.0.1".to_string(),
+        ..Default::default()
+    };
+    let mut group = CodeGroup::new();
+    group
+        .import_remote(RwSignal::new(Client::new()), &url, &conf, RwSignal::new(0))
+        .await
+        .unwrap();
+
+    let files = group.sorted_files();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].0, "hw3/src/util/io.py");
+    match &*files[0].1.read_untracked() {
+        CodeFile::Remote { ext, url, .. } => {
+            assert_eq!(ext, ".py");
+            assert_eq!(
+                url.path(),
+                "/api/v4/projects/42/repository/files/src%2Futil%2Fio.py/raw"
+            );
+            assert_eq!(url.query(), Some("ref=c0ffee"));
+        }
+        CodeFile::Local { .. } => panic!("listed file is not remote"),
+    }
+
+    let paths: Vec<String> = log
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| request.path.clone())
+        .collect();
+    assert_eq!(
+        paths[1..3],
+        [
+            "/api/v4/projects/42/repository/commits/feature",
+            "/api/v4/projects/42/repository/commits/feature%2Fx",
+        ]
+    );
+    assert!(paths[3].contains("ref=c0ffee&path=src&"), "{:?}", paths);
+}
+
+#[test]
+fn parses_package_identifiers() {
+    for spec in [
+        "crates:serde@1.0",
+        "PyPI:requests",
+        "npm:@types/left-pad@1.2.0",
+    ] {
+        assert!(Package::parse(spec).unwrap().is_some(), "{}", spec);
+    }
+    for spec in ["https://crates.io/crates/serde", "github.com/a/b", "serde"] {
+        assert!(Package::parse(spec).unwrap().is_none(), "{}", spec);
+    }
+    for spec in ["crates:", "pypi:requests@", "npm:left pad"] {
+        assert!(Package::parse(spec).is_err(), "{}", spec);
+    }
+    assert_eq!(
+        Package::parse("npm:@types/left-pad")
+            .unwrap()
+            .unwrap()
+            .name(),
+        "@types/left-pad"
+    );
+}
+
+#[tokio::test]
+async fn crates_picks_newest_matching_release() {
+    let (result, log) = resolve("crates", "crates:serde@1.0").await;
+    let archive = result.unwrap();
+    assert_eq!(
+        archive.url.as_str(),
+        "https://static.crates.io/crates/serde/serde-1.0.219.crate"
+    );
+    assert_eq!(archive.format, ArchiveFormat::TarGz);
+    assert_eq!(
+        archive.import_path("serde-1.0.219/src/lib.rs").as_deref(),
+        Some("serde/src/lib.rs")
+    );
+    assert_eq!(log.lock().unwrap().len(), 1);
+
+    let (result, _) = resolve("crates", "crates:serde@0.9").await;
+    assert!(result.unwrap().url.as_str().ends_with("serde-0.9.15.crate"));
+
+    // yanked and non-matching releases are never picked
+    for spec in ["crates:serde@2", "crates:serde@1.0.2"] {
+        let (result, _) = resolve("crates", spec).await;
+        assert!(
+            matches!(result, Err(CodeImportError::Registry(_))),
+            "{}",
+            spec
+        );
+    }
+}
+
+#[tokio::test]
+async fn pypi_picks_sdist() {
+    let (result, log) = resolve("pypi", "pypi:requests").await;
+    let archive = result.unwrap();
+    assert!(archive.url.as_str().ends_with("/requests-2.32.3.tar.gz"));
+    assert_eq!(archive.format, ArchiveFormat::TarGz);
+    assert_eq!(log.lock().unwrap()[0].path, "/pypi/requests/json");
+
+    // unknown releases surface as not found
+    let (result, _) = resolve("pypi", "pypi:requests@0.0.1").await;
+    match result {
+        Err(CodeImportError::Registry(msg)) => assert!(msg.contains("not found"), "{}", msg),
+        result => panic!("{:?}", result.map(|archive| archive.url)),
+    }
+}
+
+#[tokio::test]
+async fn npm_resolves_scoped_package() {
+    let (result, _) = resolve("npm", "npm:@types/left-pad@1.2.0").await;
+    let archive = result.unwrap();
+    assert_eq!(
+        archive.url.as_str(),
+        "https://registry.npmjs.org/@types/left-pad/-/left-pad-1.2.0.tgz"
+    );
+    assert_eq!(
+        archive.import_path("package/index.d.ts").as_deref(),
+        Some("@types/left-pad/index.d.ts")
+    );
+}
+
+#[test]
+fn parses_source_lists() {
+    let text = "\
+# hw3 submissions
+https://github.com/alice/hw3
+
+Alice Liu,https://github.com/alice/hw3
+http://github.com/alice/hw3/
+github.com/alice/hw3
+\"Bob Ng\",\"https://gitlab.com/bob/hw3/\",bob@example.edu
+Carol\tcrates:serde@1.0
+https://example.edu/dan/hw3.zip
+";
+    assert_eq!(
+        parse_source_list(text),
+        [
+            "https://github.com/alice/hw3",
+            "https://gitlab.com/bob/hw3/",
+            "crates:serde@1.0",
+            "https://example.edu/dan/hw3.zip",
+        ]
+    );
+    assert!(parse_source_list(" \n# nothing\n").is_empty());
+}
+
+/// Builds an importer holding empty local files of the given names.
+fn group_of(names: &[&str]) -> CodeGroup {
+    let mut group = CodeGroup::new();
+    for name in names {
+        group
+            .add_file(
+                name.to_string(),
+                CodeFile::new_local("py".to_string(), String::new()),
+            )
+            .unwrap();
+    }
+    group
+}
+
+#[test]
+fn batch_merge_is_all_or_nothing() {
+    let mut group = group_of(&["alice/hw3/b.py"]);
+    let result = group.merge(group_of(&["a.py", "b.py", "c.py"]), "alice/hw3");
+    assert!(
+        matches!(result, Err(CodeImportError::Exists(_))),
+        "{:?}",
+        result
+    );
+    assert_eq!(group.num_files(), 1);
+
+    group.merge(group_of(&["a.py", "b.py"]), "bob/hw3").unwrap();
+    assert_eq!(group.num_files(), 3);
+}
+
+#[test]
+fn batch_merge_keeps_total_file_cap() {
+    let names: Vec<String> = (0..MAX_NUM_FILES - 1)
+        .map(|i| format!("alice/{}.py", i))
+        .collect();
+    let mut group = group_of(&names.iter().map(String::as_str).collect::<Vec<_>>());
+    let result = group.merge(group_of(&["a.py", "b.py"]), "bob");
+    assert!(
+        matches!(result, Err(CodeImportError::Limit(_))),
+        "{:?}",
+        result
+    );
+    assert_eq!(group.num_files(), MAX_NUM_FILES - 1);
+
+    group.merge(group_of(&["a.py"]), "carol").unwrap();
+    assert_eq!(group.num_files(), MAX_NUM_FILES);
+}
+
+#[tokio::test]
+async fn batch_prefixes_sources_and_skips_failures() {
+    let route = |path: &str| Route {
+        method: "HEAD",
+        path_prefix: path.to_string(),
+        status: 200,
+        headers: vec![("Content-Length".to_string(), "42".to_string())],
+        body: String::new(),
+    };
+    start_standin(vec![route("/alice/hw3.py"), route("/bob/hw3.py")]).await;
+
+    // the same file path from two sources must not collide
+    let sources: Vec<String> = [
+        "https://example.edu/alice/hw3.py?v=1",
+        "https://example.edu/alice/hw3.py?v=2",
+        "https://example.edu/carol/hw3.py",
+        "https://example.edu/bob/hw3.py",
+    ]
+    .into_iter()
+    .map(standin_url)
+    .collect();
+    let current = RwSignal::new(0);
+    let mut group = CodeGroup::new();
+    group
+        .import_batch(
+            RwSignal::new(Client::new()),
+            &sources,
+            &RemoteConf::default(),
+            RwSignal::new(0),
+            current,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(group.num_files(), 3);
+    assert_eq!(current.get_untracked(), 4);
+    let paths: Vec<String> = group
+        .sorted_files()
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    assert!(
+        paths[0].ends_with("/alice/hw3.py?v=1/alice/hw3.py"),
+        "{:?}",
+        paths
+    );
+    assert!(
+        paths[1].ends_with("/alice/hw3.py?v=2/alice/hw3.py"),
+        "{:?}",
+        paths
+    );
+    assert!(paths[2].ends_with("/bob/hw3.py/bob/hw3.py"), "{:?}", paths);
+
+    let failures = group.failures();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].0, sources[2]);
+    assert!(
+        matches!(failures[0].1, CodeImportError::Status(_)),
+        "{:?}",
+        failures[0].1
+    );
+}
+
+#[tokio::test]
+async fn batch_fails_if_all_sources_fail() {
+    start_standin(vec![]).await;
+    let sources: Vec<String> = ["https://example.edu/a.py", "https://example.edu/b.py"]
+        .into_iter()
+        .map(standin_url)
+        .collect();
+    let mut group = CodeGroup::new();
+    let result = group
+        .import_batch(
+            RwSignal::new(Client::new()),
+            &sources,
+            &RemoteConf::default(),
+            RwSignal::new(0),
+            RwSignal::new(0),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(CodeImportError::Batch(_))),
+        "{:?}",
+        result
+    );
+    assert_eq!(group.failures().len(), 2);
+}
@@ -13,6 +13,7 @@ use tar::Archive as TarArchive;
 
 use sevenz_rust::{Error as SevenZError, Password, SevenZReader};
 
+use crate::file::archive::ArchiveFormat;
 use crate::file::suffix::LANGUAGE_MAP;
 use crate::file::{CodeGroup, MAX_FILE_SIZE, MAX_NUM_FILES};
 use crate::utils::error::CodeImportError;
@@ -58,15 +59,17 @@ impl CodeGroup {
         }
     }
 
-    /// Extract code files from a zip archive.
+    /// Extract code files from a zip archive. Entry names are mapped through
+    /// `rename`, which returns None for entries to leave out.
     async fn extract_zip(
         &mut self,
         archive: impl Read + Seek,
+        rename: impl Fn(&str) -> Option<String>,
     ) -> Result<Vec<(String, (String, String))>, CodeImportError> {
         let mut name_data_list = Vec::new();
 
         let mut archive = ZipArchive::new(archive)
-            .map_err(|_| CodeImportError::upload("failed to read uploaded zip archive"))?;
+            .map_err(|_| CodeImportError::upload("failed to read zip archive"))?;
 
         for i in 0..archive.len() {
             let mut file = archive.by_index(i).map_err(|_| {
@@ -74,7 +77,9 @@ impl CodeGroup {
             })?;
 
             if file.is_file() {
-                let name = file.name();
+                let Some(name) = rename(file.name()) else {
+                    continue;
+                };
                 if let Some(dot_pos) = name.rfind('.') {
                     let extension = &name[dot_pos..];
                     if !extension.is_empty() && LANGUAGE_MAP.contains_key(extension) {
@@ -83,7 +88,6 @@ impl CodeGroup {
                             continue;
                         }
 
-                        let name = name.to_string();
                         let ext = extension.to_string();
                         let mut content = String::new();
                         file.read_to_string(&mut content)?;
@@ -100,17 +104,19 @@ impl CodeGroup {
         Ok(name_data_list)
     }
 
-    /// Extract code files from a tar archive.
+    /// Extract code files from a tar archive. Entry names are mapped through
+    /// `rename`, which returns None for entries to leave out.
     async fn extract_tar(
         &mut self,
         archive: impl Read,
+        rename: impl Fn(&str) -> Option<String>,
     ) -> Result<Vec<(String, (String, String))>, CodeImportError> {
         let mut name_data_list = Vec::new();
 
         let mut archive = TarArchive::new(archive);
         for entry in archive
             .entries()
-            .map_err(|_| CodeImportError::upload("failed to read uploaded tar archive"))?
+            .map_err(|_| CodeImportError::upload("failed to read tar archive"))?
         {
             let mut file = entry
                 .map_err(|_| CodeImportError::upload("failed to read entry from tar archive"))?;
@@ -119,7 +125,9 @@ impl CodeGroup {
                 let name = file.path().map_err(|_| {
                     CodeImportError::upload("failed to get file path from tar archive")
                 })?;
-                let name = name.to_string_lossy().to_string();
+                let Some(name) = rename(&name.to_string_lossy()) else {
+                    continue;
+                };
                 if let Some(dot_pos) = name.rfind('.') {
                     let extension = &name[dot_pos..];
                     if !extension.is_empty() && LANGUAGE_MAP.contains_key(extension) {
@@ -144,10 +152,12 @@ impl CodeGroup {
         Ok(name_data_list)
     }
 
-    /// Extract code files from a 7z archive.
+    /// Extract code files from a 7z archive. Entry names are mapped through
+    /// `rename`, which returns None for entries to leave out.
     async fn extract_7z(
         &mut self,
         mut archive: impl Read + Seek,
+        rename: impl Fn(&str) -> Option<String>,
     ) -> Result<Vec<(String, (String, String))>, CodeImportError> {
         let mut name_data_list = Vec::new();
 
@@ -164,7 +174,9 @@ impl CodeGroup {
                 reader.read_to_end(&mut content)?;
 
                 if !entry.is_anti_item && !entry.is_directory {
-                    let name = &entry.name;
+                    let Some(name) = rename(&entry.name) else {
+                        return Ok(true); // continue
+                    };
                     if let Some(dot_pos) = name.rfind('.') {
                         let extension = &name[dot_pos..];
                         if !extension.is_empty() && LANGUAGE_MAP.contains_key(extension) {
@@ -175,7 +187,7 @@ impl CodeGroup {
 
                             let ext = extension.to_string();
                             name_data_list.push((
-                                name.to_string(),
+                                name,
                                 (
                                     ext,
                                     String::from_utf8(content)
@@ -198,6 +210,26 @@ impl CodeGroup {
         Ok(name_data_list)
     }
 
+    /// Extract code files from archive bytes of the given format. Entry names
+    /// are mapped through `rename`, which returns None for entries to leave
+    /// out.
+    pub(crate) async fn extract_bytes(
+        &mut self,
+        format: ArchiveFormat,
+        bytes: Vec<u8>,
+        rename: impl Fn(&str) -> Option<String>,
+    ) -> Result<Vec<(String, (String, String))>, CodeImportError> {
+        match format {
+            ArchiveFormat::Zip => self.extract_zip(Cursor::new(bytes), rename).await,
+            ArchiveFormat::Tar => self.extract_tar(Cursor::new(bytes), rename).await,
+            ArchiveFormat::TarGz => {
+                self.extract_tar(GzDecoder::new(Cursor::new(bytes)), rename)
+                    .await
+            }
+            ArchiveFormat::SevenZ => self.extract_7z(Cursor::new(bytes), rename).await,
+        }
+    }
+
     /// Try to treat the input file as an archive and extract valid code files
     /// from it.
     pub(crate) async fn extract_archive(
@@ -209,31 +241,10 @@ impl CodeGroup {
             return Err(CodeImportError::parse("encountered empty file name"));
         }
 
-        if let Some(dot_pos) = name.rfind('.') {
-            let name_data_list = match &name[dot_pos..] {
-                ".zip" => {
-                    self.extract_zip(Cursor::new(read_as_bytes(file.deref()).await?))
-                        .await?
-                }
-                ".tar" => {
-                    self.extract_tar(Cursor::new(read_as_bytes(file.deref()).await?))
-                        .await?
-                }
-                ".gz" | ".tgz" => {
-                    self.extract_tar(GzDecoder::new(Cursor::new(
-                        read_as_bytes(file.deref()).await?,
-                    )))
-                    .await?
-                }
-                ".7z" => {
-                    self.extract_7z(Cursor::new(read_as_bytes(file.deref()).await?))
-                        .await?
-                }
-                _ => {
-                    // unsupported archive type
-                    return Ok(None);
-                }
-            };
+        if let Some(format) = ArchiveFormat::of_name(&name) {
+            let name_data_list = self
+                .extract_bytes(format, read_as_bytes(file.deref()).await?, keep_name)
+                .await?;
 
             if name_data_list.is_empty() {
                 Err(CodeImportError::upload(
@@ -247,3 +258,8 @@ impl CodeGroup {
         }
     }
 }
+
+/// Keeps an archive entry under its own name.
+fn keep_name(name: &str) -> Option<String> {
+    Some(name.to_string())
+}
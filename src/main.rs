@@ -19,9 +19,10 @@ pub(crate) mod detection_pass;
 use detection_pass::{detection_analysis_task, DetectionPass, FileResults, TaskQueue};
 
 pub(crate) mod apis;
+use apis::ApiEndpointConf;
 
 pub(crate) mod file;
-use file::CodeGroup;
+use file::{CodeGroup, RemoteConf};
 
 pub(crate) mod utils;
 use utils::gadgets::GitHubBanner;
@@ -55,10 +56,14 @@ fn Home() -> impl IntoView {
 
     let api_provider = RwSignal::new(ApiProvider::Null);
     let input_api_key = RwSignal::new(String::new());
+    let remember_key = RwSignal::new(false);
+    let input_passphrase = RwSignal::new(String::new());
+    let input_api_conf = RwSignal::new(ApiEndpointConf::default());
     let api_key_vstate = RwSignal::new(ValidationState::Idle);
 
     let import_method = RwSignal::new(ImportMethod::Null);
     let input_code_url = RwSignal::new(String::new());
+    let input_remote_conf = RwSignal::new(RemoteConf::default());
     let input_code_text = RwSignal::new(String::new());
     let code_in_vstate = RwSignal::new(ValidationState::Idle);
 
@@ -107,6 +112,9 @@ fn Home() -> impl IntoView {
                     <ApiSelection
                         api_provider
                         input_api_key
+                        remember_key
+                        input_passphrase
+                        input_api_conf
                         api_key_vstate
                         api_client
                         code_in_vstate
@@ -123,6 +131,7 @@ fn Home() -> impl IntoView {
                     <CodeRetrieve
                         import_method
                         input_code_url
+                        input_remote_conf
                         input_code_text
                         code_in_vstate
                         cgf_client
@@ -137,6 +146,8 @@ fn Home() -> impl IntoView {
 
                     // step 3:
                     <DetectionPass
+                        api_provider
+                        api_client
                         code_group
                         task_queue
                         num_finished
@@ -3,6 +3,11 @@
 use std::error::Error;
 use std::fmt;
 
+use leptos::wasm_bindgen::JsValue;
+
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+
 /// Error type for API key validation.
 #[derive(Clone, PartialEq, Debug)]
 pub(crate) enum ApiKeyCheckError {
@@ -11,6 +16,8 @@ pub(crate) enum ApiKeyCheckError {
     Limit(String),
     Ascii(String),
     Random(String),
+    Store(String),
+    Config(String),
 }
 
 impl ApiKeyCheckError {
@@ -33,6 +40,14 @@ impl ApiKeyCheckError {
     pub(crate) fn random(msg: impl ToString) -> Self {
         ApiKeyCheckError::Random(msg.to_string())
     }
+
+    pub(crate) fn store(msg: impl ToString) -> Self {
+        ApiKeyCheckError::Store(msg.to_string())
+    }
+
+    pub(crate) fn config(msg: impl ToString) -> Self {
+        ApiKeyCheckError::Config(msg.to_string())
+    }
 }
 
 impl fmt::Display for ApiKeyCheckError {
@@ -43,6 +58,8 @@ impl fmt::Display for ApiKeyCheckError {
             ApiKeyCheckError::Limit(msg) => write!(f, "Limit error: {}", msg),
             ApiKeyCheckError::Ascii(msg) => write!(f, "Ascii error: {}", msg),
             ApiKeyCheckError::Random(msg) => write!(f, "Random error: {}", msg),
+            ApiKeyCheckError::Store(msg) => write!(f, "Store error: {}", msg),
+            ApiKeyCheckError::Config(msg) => write!(f, "Config error: {}", msg),
         }
     }
 }
@@ -67,6 +84,12 @@ impl From<getrandom::Error> for ApiKeyCheckError {
     }
 }
 
+impl From<JsValue> for ApiKeyCheckError {
+    fn from(err: JsValue) -> Self {
+        ApiKeyCheckError::store(format!("{:?}", err))
+    }
+}
+
 /// Error type for code import validation.
 #[derive(Clone, PartialEq, Debug)]
 pub(crate) enum CodeImportError {
@@ -77,6 +100,11 @@ pub(crate) enum CodeImportError {
     Limit(String),
     Ascii(String),
     GitHub(String),
+    GitLab(String),
+    Gitea(String),
+    Bitbucket(String),
+    Registry(String),
+    Batch(String),
     Upload(String),
 }
 
@@ -109,6 +137,26 @@ impl CodeImportError {
         CodeImportError::GitHub(msg.to_string())
     }
 
+    pub(crate) fn gitlab(msg: impl ToString) -> Self {
+        CodeImportError::GitLab(msg.to_string())
+    }
+
+    pub(crate) fn gitea(msg: impl ToString) -> Self {
+        CodeImportError::Gitea(msg.to_string())
+    }
+
+    pub(crate) fn bitbucket(msg: impl ToString) -> Self {
+        CodeImportError::Bitbucket(msg.to_string())
+    }
+
+    pub(crate) fn registry(msg: impl ToString) -> Self {
+        CodeImportError::Registry(msg.to_string())
+    }
+
+    pub(crate) fn batch(msg: impl ToString) -> Self {
+        CodeImportError::Batch(msg.to_string())
+    }
+
     pub(crate) fn upload(msg: impl ToString) -> Self {
         CodeImportError::Upload(msg.to_string())
     }
@@ -124,6 +172,11 @@ impl fmt::Display for CodeImportError {
             CodeImportError::Limit(msg) => write!(f, "Limit error: {}", msg),
             CodeImportError::Ascii(msg) => write!(f, "Ascii error: {}", msg),
             CodeImportError::GitHub(msg) => write!(f, "GitHub error: {}", msg),
+            CodeImportError::GitLab(msg) => write!(f, "GitLab error: {}", msg),
+            CodeImportError::Gitea(msg) => write!(f, "Gitea error: {}", msg),
+            CodeImportError::Bitbucket(msg) => write!(f, "Bitbucket error: {}", msg),
+            CodeImportError::Registry(msg) => write!(f, "Registry error: {}", msg),
+            CodeImportError::Batch(msg) => write!(f, "Batch error: {}", msg),
             CodeImportError::Upload(msg) => write!(f, "Upload error: {}", msg),
         }
     }
@@ -161,28 +214,195 @@ impl From<reqwest::Error> for CodeImportError {
     }
 }
 
-/// Error type for detection API call.
+/// Max length of provider error body kept in a detection API call error.
+const MAX_ERROR_BODY_LEN: usize = 400;
+
+/// Details of a failed detection API call: the HTTP status if a response was
+/// received at all, and the provider's error body or a description.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct ApiCallFailure {
+    pub(crate) status: Option<u16>,
+    pub(crate) msg: String,
+}
+
+impl ApiCallFailure {
+    fn new(status: Option<u16>, msg: impl ToString) -> Self {
+        let mut msg = msg.to_string();
+        if let Some((idx, _)) = msg.char_indices().nth(MAX_ERROR_BODY_LEN) {
+            msg.truncate(idx);
+            msg.push_str("...");
+        }
+        ApiCallFailure { status, msg }
+    }
+}
+
+impl fmt::Display for ApiCallFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "HTTP {}: {}", status, self.msg),
+            None => write!(f, "{}", self.msg),
+        }
+    }
+}
+
+/// Error type for detection API call, classified by cause so that users can
+/// tell whether to wait, fix a key, or shrink a file.
 #[derive(Clone, PartialEq, Debug)]
 pub(crate) enum ApiMakeCallError {
-    Parse(String),
-    Status(String),
+    Auth(ApiCallFailure),
+    RateLimit(ApiCallFailure),
+    Quota(ApiCallFailure),
+    ContextOverflow(ApiCallFailure),
+    ContentFilter(ApiCallFailure),
+    Recitation(ApiCallFailure),
+    Truncated(ApiCallFailure),
+    ModelUnavailable(ApiCallFailure),
+    Timeout(ApiCallFailure),
+    Network(ApiCallFailure),
+    Parse(ApiCallFailure),
+    Status(ApiCallFailure),
 }
 
 impl ApiMakeCallError {
     pub(crate) fn parse(msg: impl ToString) -> Self {
-        ApiMakeCallError::Parse(msg.to_string())
+        ApiMakeCallError::Parse(ApiCallFailure::new(None, msg))
     }
 
-    pub(crate) fn status(msg: impl ToString) -> Self {
-        ApiMakeCallError::Status(msg.to_string())
+    pub(crate) fn rate_limit(msg: impl ToString) -> Self {
+        ApiMakeCallError::RateLimit(ApiCallFailure::new(None, msg))
+    }
+
+    pub(crate) fn content_filter(msg: impl ToString) -> Self {
+        ApiMakeCallError::ContentFilter(ApiCallFailure::new(None, msg))
+    }
+
+    pub(crate) fn recitation(msg: impl ToString) -> Self {
+        ApiMakeCallError::Recitation(ApiCallFailure::new(None, msg))
+    }
+
+    pub(crate) fn truncated(msg: impl ToString) -> Self {
+        ApiMakeCallError::Truncated(ApiCallFailure::new(None, msg))
+    }
+
+    pub(crate) fn context_overflow(msg: impl ToString) -> Self {
+        ApiMakeCallError::ContextOverflow(ApiCallFailure::new(None, msg))
+    }
+
+    /// Classifies a non-success HTTP response by its status code and, where
+    /// providers reuse status codes for different causes, by its error body.
+    pub(crate) fn from_status(status: StatusCode, body: String) -> Self {
+        let lower = body.to_lowercase();
+        let mentions = |needles: &[&str]| needles.iter().any(|n| lower.contains(n));
+        let failure = ApiCallFailure::new(Some(status.as_u16()), body.trim());
+
+        if mentions(&[
+            "context_length",
+            "context length",
+            "context window",
+            "maximum context",
+            "too many tokens",
+            "prompt is too long",
+            "input is too long",
+        ]) || status == StatusCode::PAYLOAD_TOO_LARGE
+        {
+            ApiMakeCallError::ContextOverflow(failure)
+        } else if mentions(&["content_filter", "content management policy", "safety"]) {
+            ApiMakeCallError::ContentFilter(failure)
+        } else if status == StatusCode::PAYMENT_REQUIRED
+            || mentions(&[
+                "insufficient_quota",
+                "exceeded your current quota",
+                "credit",
+                "billing",
+            ])
+        {
+            ApiMakeCallError::Quota(failure)
+        } else if status == StatusCode::TOO_MANY_REQUESTS {
+            ApiMakeCallError::RateLimit(failure)
+        } else if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            ApiMakeCallError::Auth(failure)
+        } else if status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::GATEWAY_TIMEOUT {
+            ApiMakeCallError::Timeout(failure)
+        } else if status == StatusCode::NOT_FOUND
+            || status == StatusCode::SERVICE_UNAVAILABLE
+            || status.as_u16() == 529 // Anthropic's "overloaded"
+            || mentions(&["model_not_found", "does not exist", "overloaded"])
+        {
+            ApiMakeCallError::ModelUnavailable(failure)
+        } else {
+            ApiMakeCallError::Status(failure)
+        }
+    }
+
+    /// Classifies a non-success HTTP response, consuming it for its body.
+    /// Rate limit errors note the provider's `Retry-After` hint, if any.
+    pub(crate) async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        match response.text().await {
+            Ok(body) => {
+                let mut err = Self::from_status(status, body);
+                if let (ApiMakeCallError::RateLimit(failure), Some(retry_after)) =
+                    (&mut err, retry_after)
+                {
+                    // either delay seconds or an HTTP date
+                    let retry_after = if retry_after.chars().all(|c| c.is_ascii_digit()) {
+                        format!("{}s", retry_after)
+                    } else {
+                        retry_after
+                    };
+                    failure.msg = format!("{} (retry after {})", failure.msg, retry_after);
+                }
+                err
+            }
+            Err(err) => err.into(),
+        }
+    }
+
+    /// Returns the failure details regardless of the cause.
+    pub(crate) fn failure(&self) -> &ApiCallFailure {
+        match self {
+            ApiMakeCallError::Auth(failure)
+            | ApiMakeCallError::RateLimit(failure)
+            | ApiMakeCallError::Quota(failure)
+            | ApiMakeCallError::ContextOverflow(failure)
+            | ApiMakeCallError::ContentFilter(failure)
+            | ApiMakeCallError::Recitation(failure)
+            | ApiMakeCallError::Truncated(failure)
+            | ApiMakeCallError::ModelUnavailable(failure)
+            | ApiMakeCallError::Timeout(failure)
+            | ApiMakeCallError::Network(failure)
+            | ApiMakeCallError::Parse(failure)
+            | ApiMakeCallError::Status(failure) => failure,
+        }
     }
 }
 
 impl fmt::Display for ApiMakeCallError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ApiMakeCallError::Parse(msg) => write!(f, "Parse error: {}", msg),
-            ApiMakeCallError::Status(msg) => write!(f, "Status error: {}", msg),
+            ApiMakeCallError::Auth(failure) => write!(f, "Auth error: {}", failure),
+            ApiMakeCallError::RateLimit(failure) => write!(f, "Rate limit error: {}", failure),
+            ApiMakeCallError::Quota(failure) => write!(f, "Quota error: {}", failure),
+            ApiMakeCallError::ContextOverflow(failure) => {
+                write!(f, "Context overflow error: {}", failure)
+            }
+            ApiMakeCallError::ContentFilter(failure) => {
+                write!(f, "Content filter error: {}", failure)
+            }
+            ApiMakeCallError::Recitation(failure) => write!(f, "Recitation error: {}", failure),
+            ApiMakeCallError::Truncated(failure) => write!(f, "Truncated error: {}", failure),
+            ApiMakeCallError::ModelUnavailable(failure) => {
+                write!(f, "Model unavailable error: {}", failure)
+            }
+            ApiMakeCallError::Timeout(failure) => write!(f, "Timeout error: {}", failure),
+            ApiMakeCallError::Network(failure) => write!(f, "Network error: {}", failure),
+            ApiMakeCallError::Parse(failure) => write!(f, "Parse error: {}", failure),
+            ApiMakeCallError::Status(failure) => write!(f, "Status error: {}", failure),
         }
     }
 }
@@ -203,6 +423,13 @@ impl From<reqwest::header::InvalidHeaderValue> for ApiMakeCallError {
 
 impl From<reqwest::Error> for ApiMakeCallError {
     fn from(err: reqwest::Error) -> Self {
-        ApiMakeCallError::status(err)
+        let status = err.status().map(|status| status.as_u16());
+        if err.is_timeout() {
+            ApiMakeCallError::Timeout(ApiCallFailure::new(status, err))
+        } else if err.is_decode() || err.is_body() {
+            ApiMakeCallError::Parse(ApiCallFailure::new(status, err))
+        } else {
+            ApiMakeCallError::Network(ApiCallFailure::new(status, err))
+        }
     }
 }
@@ -161,17 +161,24 @@ pub(crate) fn HoverInfoIcon(text: &'static str) -> impl IntoView {
     }
 }
 
-/// A block that shows the percentage AI authorship result on it, and that
-/// also shows an associated message when hovered over.
+/// A block that shows the percentage AI authorship result (or a short label
+/// in its place) on it, and that also shows an associated message when
+/// hovered over.
 #[component]
-pub(crate) fn HoverResultDiv(percent: Option<u8>, message: String) -> impl IntoView {
-    let percent_s = match percent {
-        Some(p) => format!("{}%", cmp::min(p, 100)),
-        None => "-N/A-".to_string(),
+pub(crate) fn HoverResultDiv(
+    percent: Option<u8>,
+    message: String,
+    #[prop(optional)] label: Option<&'static str>,
+) -> impl IntoView {
+    let percent_s = match (percent, label) {
+        (Some(p), _) => format!("{}%", cmp::min(p, 100)),
+        (None, Some(label)) => label.to_string(),
+        (None, None) => "-N/A-".to_string(),
     };
-    let color_style = match percent {
-        Some(p) => blended_color(p),
-        None => "text-red-600 text-sm",
+    let color_style = match (percent, label) {
+        (Some(p), _) => blended_color(p),
+        (None, Some(_)) => "text-amber-600 text-sm",
+        (None, None) => "text-red-600 text-sm",
     };
 
     let show_popup = RwSignal::new(false);
@@ -0,0 +1,230 @@
+//! Optional encrypted persistence of API keys across sessions.
+//!
+//! Keys are encrypted, together with the provider's Step 1 settings, with
+//! WebCrypto AES-GCM under a key derived from a user-supplied passphrase
+//! (PBKDF2-SHA256), then kept in the browser's local storage. The passphrase
+//! itself is never stored.
+
+use serde::{Deserialize, Serialize};
+
+use leptos::wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use js_sys::{Array, Uint8Array};
+use web_sys::{AesDerivedKeyParams, AesGcmParams, CryptoKey, Pbkdf2Params, Storage, SubtleCrypto};
+
+use base64::prelude::*;
+
+use crate::api_selection::ApiProvider;
+use crate::apis::ApiEndpointConf;
+use crate::utils::error::ApiKeyCheckError;
+
+/// Local storage entry name prefix for remembered keys, one entry per provider.
+const STORAGE_KEY_PREFIX: &str = "codetective-api-keys-";
+
+/// Local storage entry name for the most recently remembered provider.
+const LAST_PROVIDER_KEY: &str = "codetective-last-provider";
+
+/// PBKDF2 iteration count, following current OWASP guidance for SHA-256.
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// Random salt and AES-GCM nonce lengths in bytes.
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// Minimum accepted passphrase length.
+pub(crate) const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Plaintext of a remembered entry: the keys, and the settings they were
+/// validated with, e.g. the Azure deployment or the relay URL.
+#[derive(Serialize, Deserialize, Debug)]
+struct RememberedEntry {
+    api_keys: Vec<String>,
+    conf: ApiEndpointConf,
+}
+
+/// Encrypted entry as stored in local storage, all fields base64-encoded.
+#[derive(Serialize, Deserialize, Debug)]
+struct StoredApiKeys {
+    salt: String,
+    nonce: String,
+    cipher: String,
+}
+
+fn local_storage() -> Result<Storage, ApiKeyCheckError> {
+    web_sys::window()
+        .ok_or_else(|| ApiKeyCheckError::store("no window found in the DOM"))?
+        .local_storage()?
+        .ok_or_else(|| ApiKeyCheckError::store("local storage is not available"))
+}
+
+fn subtle_crypto() -> Result<SubtleCrypto, ApiKeyCheckError> {
+    Ok(web_sys::window()
+        .ok_or_else(|| ApiKeyCheckError::store("no window found in the DOM"))?
+        .crypto()?
+        .subtle())
+}
+
+fn storage_key_of(provider: ApiProvider) -> String {
+    format!("{}{}", STORAGE_KEY_PREFIX, provider.id())
+}
+
+/// Derives an AES-GCM key from the passphrase and salt, usable for the given
+/// operation ("encrypt" or "decrypt").
+async fn derive_aes_key(
+    subtle: &SubtleCrypto,
+    passphrase: &str,
+    salt: &[u8],
+    usage: &str,
+) -> Result<CryptoKey, ApiKeyCheckError> {
+    let base_key = JsFuture::from(subtle.import_key_with_str(
+        "raw",
+        &Uint8Array::from(passphrase.as_bytes()),
+        "PBKDF2",
+        false,
+        &Array::of1(&JsValue::from_str("deriveKey")),
+    )?)
+    .await?;
+
+    let pbkdf2_params = Pbkdf2Params::new(
+        "PBKDF2",
+        &JsValue::from_str("SHA-256"),
+        PBKDF2_ITERATIONS,
+        &Uint8Array::from(salt),
+    );
+    let aes_params = AesDerivedKeyParams::new("AES-GCM", 256);
+    let aes_key = JsFuture::from(subtle.derive_key_with_object_and_object(
+        &pbkdf2_params,
+        &base_key.unchecked_into(),
+        &aes_params,
+        false,
+        &Array::of1(&JsValue::from_str(usage)),
+    )?)
+    .await?;
+
+    Ok(aes_key.unchecked_into())
+}
+
+/// Returns true if there are remembered keys for the given provider.
+pub(crate) fn has_remembered(provider: ApiProvider) -> bool {
+    local_storage()
+        .ok()
+        .and_then(|storage| storage.get_item(&storage_key_of(provider)).ok().flatten())
+        .is_some()
+}
+
+/// Returns the provider whose keys were remembered most recently, if any.
+pub(crate) fn last_remembered_provider() -> Option<ApiProvider> {
+    let storage = local_storage().ok()?;
+    let id = storage.get_item(LAST_PROVIDER_KEY).ok().flatten()?;
+    ApiProvider::from_id(&id).filter(|&provider| has_remembered(provider))
+}
+
+/// Encrypts the given keys and settings under the passphrase and stores them
+/// for the given provider, replacing any previously remembered ones.
+pub(crate) async fn remember_api_keys(
+    provider: ApiProvider,
+    api_keys: &[String],
+    conf: &ApiEndpointConf,
+    passphrase: &str,
+) -> Result<(), ApiKeyCheckError> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(ApiKeyCheckError::store(format!(
+            "passphrase must be at least {} characters long",
+            MIN_PASSPHRASE_LEN
+        )));
+    }
+
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    getrandom::fill(&mut salt)?;
+    getrandom::fill(&mut nonce)?;
+
+    let plain = serde_json::to_string(&RememberedEntry {
+        api_keys: api_keys.to_vec(),
+        conf: conf.clone(),
+    })
+    .map_err(|err| ApiKeyCheckError::store(format!("failed to serialize entry: {}", err)))?;
+
+    let subtle = subtle_crypto()?;
+    let aes_key = derive_aes_key(&subtle, passphrase, &salt, "encrypt").await?;
+    let gcm_params = AesGcmParams::new("AES-GCM", &Uint8Array::from(&nonce[..]));
+    let cipher = JsFuture::from(subtle.encrypt_with_object_and_u8_array(
+        &gcm_params,
+        &aes_key,
+        plain.as_bytes(),
+    )?)
+    .await?;
+
+    let stored = StoredApiKeys {
+        salt: BASE64_STANDARD.encode(salt),
+        nonce: BASE64_STANDARD.encode(nonce),
+        cipher: BASE64_STANDARD.encode(Uint8Array::new(&cipher).to_vec()),
+    };
+    let stored_json = serde_json::to_string(&stored)
+        .map_err(|err| ApiKeyCheckError::store(format!("failed to serialize entry: {}", err)))?;
+
+    let storage = local_storage()?;
+    storage.set_item(&storage_key_of(provider), &stored_json)?;
+    storage.set_item(LAST_PROVIDER_KEY, provider.id())?;
+    Ok(())
+}
+
+/// Decrypts the remembered keys and settings of the given provider with the
+/// passphrase.
+pub(crate) async fn recall_api_keys(
+    provider: ApiProvider,
+    passphrase: &str,
+) -> Result<(Vec<String>, ApiEndpointConf), ApiKeyCheckError> {
+    let stored_json = local_storage()?
+        .get_item(&storage_key_of(provider))?
+        .ok_or_else(|| ApiKeyCheckError::store("no remembered keys for this provider"))?;
+    let stored = serde_json::from_str::<StoredApiKeys>(&stored_json)
+        .map_err(|_| ApiKeyCheckError::store("remembered entry is corrupted"))?;
+
+    let decode = |field: &str| {
+        BASE64_STANDARD
+            .decode(field)
+            .map_err(|_| ApiKeyCheckError::store("remembered entry is corrupted"))
+    };
+    let salt = decode(&stored.salt)?;
+    let nonce = decode(&stored.nonce)?;
+    let cipher = decode(&stored.cipher)?;
+
+    let subtle = subtle_crypto()?;
+    let aes_key = derive_aes_key(&subtle, passphrase, &salt, "decrypt").await?;
+    let gcm_params = AesGcmParams::new("AES-GCM", &Uint8Array::from(&nonce[..]));
+    // AES-GCM authentication fails on a wrong passphrase
+    let plain =
+        JsFuture::from(subtle.decrypt_with_object_and_u8_array(&gcm_params, &aes_key, &cipher)?)
+            .await
+            .map_err(|_| ApiKeyCheckError::store("wrong passphrase for remembered keys"))?;
+
+    let plain = String::from_utf8(Uint8Array::new(&plain).to_vec())
+        .map_err(|_| ApiKeyCheckError::store("remembered entry is corrupted"))?;
+    let entry = serde_json::from_str::<RememberedEntry>(&plain)
+        .map_err(|_| ApiKeyCheckError::store("remembered entry is corrupted"))?;
+    Ok((entry.api_keys, entry.conf))
+}
+
+/// Removes all remembered keys of all providers from local storage.
+pub(crate) fn forget_all_api_keys() -> Result<(), ApiKeyCheckError> {
+    let storage = local_storage()?;
+
+    // collect names first, as removal shifts the storage indices
+    let mut names = Vec::new();
+    for idx in 0..storage.length()? {
+        if let Some(name) = storage.key(idx)? {
+            if name.starts_with(STORAGE_KEY_PREFIX) {
+                names.push(name);
+            }
+        }
+    }
+    for name in names {
+        storage.remove_item(&name)?;
+    }
+
+    storage.remove_item(LAST_PROVIDER_KEY)?;
+    log::info!("Forgot all remembered API keys");
+    Ok(())
+}
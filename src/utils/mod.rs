@@ -2,6 +2,11 @@
 
 pub(crate) mod error;
 pub(crate) mod gadgets;
+pub(crate) mod keystore;
+pub(crate) mod proxy;
+
+#[cfg(test)]
+pub(crate) mod standin;
 
 pub(crate) const NBSP: &str = "\u{00A0}"; // space
 pub(crate) const NBHY: &str = "\u{2011}"; // hyphen
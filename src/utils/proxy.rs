@@ -0,0 +1,88 @@
+//! User-specified CORS proxy that all outbound requests, to both API providers
+//! and code hosts, go through when set.
+
+use std::sync::RwLock;
+
+use url::{form_urlencoded, Url};
+
+/// Placeholder in a proxy URL template, replaced by the percent-encoded target
+/// URL. Templates without it get the target URL appended as is.
+pub(crate) const URL_PLACEHOLDER: &str = "{url}";
+
+/// URL template of the reference proxy in `proxy/`, run locally.
+pub(crate) const LOCAL_PROXY_TEMPLATE: &str = "http://localhost:8787/";
+
+/// Currently active proxy URL template, if any.
+static PROXY_TEMPLATE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Sets the proxy URL template to use from now on; an empty template turns
+/// proxying off. Returns an error message if the template is invalid.
+pub(crate) fn set_template(template: &str) -> Result<(), String> {
+    let template = template.trim();
+    let template = if template.is_empty() {
+        None
+    } else {
+        let example = expand(template, "https://example.com/");
+        let example =
+            Url::parse(&example).map_err(|err| format!("invalid proxy URL template: {}", err))?;
+        if example.scheme() != "http" && example.scheme() != "https" {
+            return Err("proxy URL template must be http(s)".to_string());
+        }
+        log::info!("Routing outbound requests through proxy '{}'", template);
+        Some(template.to_string())
+    };
+
+    *PROXY_TEMPLATE
+        .write()
+        .expect("proxy template lock poisoned") = template;
+    Ok(())
+}
+
+/// Returns the URL to send a request for the given target URL to, which goes
+/// through the proxy if one is set.
+pub(crate) fn proxied(url: impl AsRef<str>) -> String {
+    match PROXY_TEMPLATE
+        .read()
+        .expect("proxy template lock poisoned")
+        .as_deref()
+    {
+        Some(template) => expand(template, url.as_ref()),
+        None => url.as_ref().to_string(),
+    }
+}
+
+/// Where the outbound requests of a client go: to their target URLs, through
+/// the proxy if one is set, unless an origin is given to send them all to
+/// instead, keeping their paths and queries, e.g. a local stand-in server.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub(crate) struct Outbound {
+    pub(crate) origin: Option<Url>,
+}
+
+impl Outbound {
+    /// Returns the URL to send a request for the given target URL to.
+    pub(crate) fn url(&self, url: impl AsRef<str>) -> String {
+        let Some(origin) = &self.origin else {
+            return proxied(url);
+        };
+        match Url::parse(url.as_ref()) {
+            Ok(mut url) => {
+                // both are http(s) URLs, which always accept these
+                let _ = url.set_scheme(origin.scheme());
+                let _ = url.set_host(origin.host_str());
+                let _ = url.set_port(origin.port_or_known_default());
+                url.to_string()
+            }
+            Err(_) => url.as_ref().to_string(),
+        }
+    }
+}
+
+fn expand(template: &str, url: &str) -> String {
+    if template.contains(URL_PLACEHOLDER) {
+        let encoded: String = form_urlencoded::byte_serialize(url.as_bytes()).collect();
+        template.replace(URL_PLACEHOLDER, &encoded)
+    } else {
+        format!("{}{}", template, url)
+    }
+}
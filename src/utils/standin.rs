@@ -0,0 +1,158 @@
+//! Local HTTP stand-in server that tests send their outbound requests to,
+//! serving canned responses and recording the requests it receives.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+
+use url::Url;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use reqwest::StatusCode;
+
+use crate::utils::proxy::Outbound;
+
+thread_local! {
+    /// Address of the stand-in server of the test running on this thread.
+    static STANDIN_ADDR: Cell<Option<SocketAddr>> = const { Cell::new(None) };
+}
+
+/// Returns the origin of the stand-in server of the test running on this
+/// thread. Panics if none is running, so that tests never go online.
+pub(crate) fn standin_origin() -> Url {
+    let addr = STANDIN_ADDR
+        .get()
+        .expect("no stand-in server running for this test");
+    Url::parse(&format!("http://{}/", addr)).unwrap()
+}
+
+/// Where requests go in tests: all to the stand-in server.
+pub(crate) fn standin_outbound() -> Outbound {
+    Outbound {
+        origin: Some(standin_origin()),
+    }
+}
+
+/// Redirects a URL to the stand-in server, keeping its path and query.
+pub(crate) fn standin_url(url: &str) -> String {
+    standin_outbound().url(url)
+}
+
+/// One canned response, served to requests of the given method whose path
+/// starts with the given prefix.
+#[derive(Clone, Debug)]
+pub(crate) struct Route {
+    pub(crate) method: &'static str,
+    pub(crate) path_prefix: String,
+    pub(crate) status: u16,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: String,
+}
+
+/// A request received by the stand-in server.
+#[derive(Clone, Debug)]
+pub(crate) struct RecordedRequest {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) body: String,
+}
+
+impl RecordedRequest {
+    pub(crate) fn json(&self) -> Value {
+        serde_json::from_str(&self.body).expect("request body is not JSON")
+    }
+}
+
+pub(crate) type RequestLog = Arc<Mutex<Vec<RecordedRequest>>>;
+
+/// Starts a stand-in server for the test on this thread, serving the given
+/// routes. Returns the log of requests it receives.
+pub(crate) async fn start_standin(routes: Vec<Route>) -> RequestLog {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    STANDIN_ADDR.set(Some(listener.local_addr().unwrap()));
+
+    let log = RequestLog::default();
+    let server_log = log.clone();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let record = serve_one(stream, &routes).await;
+            server_log.lock().unwrap().push(record);
+        }
+    });
+    log
+}
+
+/// Reads one request off the connection and answers it with the first
+/// matching route, or 404 if none matches.
+async fn serve_one(mut stream: TcpStream, routes: &[Route]) -> RecordedRequest {
+    let mut buf = Vec::new();
+    let header_end = loop {
+        let mut chunk = [0u8; 4096];
+        let n = stream.read(&mut chunk).await.unwrap();
+        assert!(n > 0, "connection closed before request headers ended");
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap().split(' ');
+    let method = request_line.next().unwrap().to_string();
+    let path = request_line.next().unwrap().to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let content_len = headers
+        .get("content-length")
+        .map_or(0, |len| len.parse::<usize>().unwrap());
+    while buf.len() < header_end + content_len {
+        let mut chunk = [0u8; 4096];
+        let n = stream.read(&mut chunk).await.unwrap();
+        assert!(n > 0, "connection closed before request body ended");
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let body = String::from_utf8_lossy(&buf[header_end..header_end + content_len]).to_string();
+
+    let route = routes
+        .iter()
+        .find(|route| route.method == method && path.starts_with(&route.path_prefix));
+    let (status, extra_headers, resp_body) = match route {
+        Some(route) => (route.status, route.headers.clone(), route.body.clone()),
+        None => (404, vec![], format!("no fixture for {} {}", method, path)),
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        StatusCode::from_u16(status)
+            .unwrap()
+            .canonical_reason()
+            .unwrap_or("Unknown"),
+        resp_body.len()
+    );
+    for (name, value) in extra_headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    response.push_str(&resp_body);
+    stream.write_all(response.as_bytes()).await.unwrap();
+    stream.shutdown().await.ok();
+
+    RecordedRequest {
+        method,
+        path,
+        headers,
+        body,
+    }
+}